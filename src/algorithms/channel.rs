@@ -0,0 +1,526 @@
+use crate::algorithms::hasher::{m31_to_script_num, DigestVar, ScriptHasher, Sha256ScriptHasher};
+use crate::algorithms::pow::{verify_pow, verify_pow_with_hasher, NonceEncoding};
+use anyhow::Result;
+use bitcoin_circle_stark::treepp::*;
+use bitcoin_script_dsl::builtins::cm31::CM31Var;
+use bitcoin_script_dsl::builtins::hash::HashVar;
+use bitcoin_script_dsl::builtins::m31::M31Var;
+use bitcoin_script_dsl::builtins::qm31::QM31Var;
+use bitcoin_script_dsl::bvar::{AllocVar, BVar};
+use bitcoin_script_dsl::constraint_system::ConstraintSystemRef;
+use bitcoin_script_dsl::options::Options;
+use bitcoin_script_dsl::stack::Stack;
+use std::marker::PhantomData;
+use stwo_prover::core::channel::Sha256Channel;
+use stwo_prover::core::fields::cm31::CM31;
+use stwo_prover::core::fields::m31::M31;
+use stwo_prover::core::fields::qm31::QM31;
+
+/// The Fiat-Shamir channel of the verifier in the script.
+///
+/// The steps that hash the transcript or open the Merkle trees are generic over this trait, and
+/// the Merkle trees are hashed with the hasher of the channel.
+pub trait ChannelVar: Clone {
+    type Hasher: ScriptHasher;
+
+    /// The channel at the start of the transcript.
+    fn new(cs: &ConstraintSystemRef) -> Result<Self>;
+
+    /// Mix a digest, e.g., a commitment, into the channel.
+    fn mix_digest(&mut self, digest: &<Self::Hasher as ScriptHasher>::Var) -> Result<()>;
+
+    fn mix_felt(&mut self, felt: &QM31Var) -> Result<()>;
+
+    fn draw_felt(&mut self) -> Result<QM31Var>;
+
+    /// Draw `n` numbers of `logn` bits.
+    fn draw_numbers(&mut self, n: usize, logn: usize) -> Result<Vec<M31Var>>;
+
+    /// Check the proof of work of `n_bits` with the nonce, and mix the nonce into the channel.
+    fn verify_pow(&mut self, n_bits: u32, nonce: u64) -> Result<()>;
+}
+
+/// The variable that holds a digest of the hasher of the channel.
+pub type ChannelDigestVar<C> = <<C as ChannelVar>::Hasher as ScriptHasher>::Var;
+
+/// stwo's `Sha256Channel`, which is implemented by `HashVar` in the DSL.
+impl ChannelVar for HashVar {
+    type Hasher = Sha256ScriptHasher;
+
+    fn new(cs: &ConstraintSystemRef) -> Result<Self> {
+        Ok(HashVar::new_constant(
+            cs,
+            Sha256Channel::default().digest().as_ref().to_vec(),
+        )?)
+    }
+
+    fn mix_digest(&mut self, digest: &HashVar) -> Result<()> {
+        *self = &*self + digest;
+        Ok(())
+    }
+
+    fn mix_felt(&mut self, felt: &QM31Var) -> Result<()> {
+        *self = &*self + felt;
+        Ok(())
+    }
+
+    fn draw_felt(&mut self) -> Result<QM31Var> {
+        Ok(HashVar::draw_felt(self))
+    }
+
+    fn draw_numbers(&mut self, n: usize, logn: usize) -> Result<Vec<M31Var>> {
+        Ok(HashVar::draw_numbers(self, n, logn))
+    }
+
+    fn verify_pow(&mut self, n_bits: u32, nonce: u64) -> Result<()> {
+        verify_pow(self, n_bits, nonce)
+    }
+}
+
+/// A channel whose digest is computed with `H`.
+///
+/// - The channel starts with a digest of zeroes.
+/// - A digest `d` is mixed as `H(d || digest)`.
+/// - A field element is mixed limb by limb, each limb `v` as `H(v || digest)`, where `v` is the
+///   limb as a script number.
+/// - A draw replaces the digest with `H(digest)` and reads 4-byte little-endian limbs from it.
+///   The top bit of each limb is dropped and `2^31 - 1` is read as zero, which leaves a bias of
+///   `2^-31` towards zero.
+/// - The nonce of the proof of work is mixed by `H::mix_nonce`.
+///
+/// `HasherChannel` computes the same natively, which is what a prover under `H` has to use.
+/// For SHA-256, use `HashVar` instead, which matches stwo's `Sha256Channel`.
+pub struct HasherChannelVar<H: ScriptHasher> {
+    pub digest: H::Var,
+    _hasher: PhantomData<H>,
+}
+
+impl<H: ScriptHasher> Clone for HasherChannelVar<H> {
+    fn clone(&self) -> Self {
+        Self {
+            digest: self.digest.clone(),
+            _hasher: PhantomData,
+        }
+    }
+}
+
+impl<H: ScriptHasher> HasherChannelVar<H> {
+    /// Replace the digest with `H(digest)` and return the limbs read from the new digest.
+    fn draw_limbs(&mut self) -> Result<Vec<M31Var>> {
+        let cs = self.digest.cs();
+
+        let mut channel = HasherChannel::<H>::from_digest(self.digest.digest().to_vec());
+        let limbs = channel.draw_limbs();
+
+        let mut byte_vars = vec![];
+        for &byte in channel.digest.iter() {
+            byte_vars.push(M31Var::new_hint(&cs, M31::from(byte as u32))?);
+        }
+
+        cs.insert_script_complex(
+            draw_limbs_gadget::<H>,
+            self.digest
+                .variables()
+                .into_iter()
+                .chain(byte_vars.iter().map(|var| var.variable)),
+            &Options::new(),
+        )?;
+
+        let mut limb_vars = vec![];
+        for &limb in limbs.iter() {
+            limb_vars.push(M31Var::new_function_output(&cs, limb)?);
+        }
+        self.digest = H::Var::new_digest_function_output(&cs, channel.digest)?;
+
+        Ok(limb_vars)
+    }
+}
+
+impl<H: ScriptHasher> ChannelVar for HasherChannelVar<H> {
+    type Hasher = H;
+
+    fn new(cs: &ConstraintSystemRef) -> Result<Self> {
+        Ok(Self {
+            digest: H::Var::new_digest_constant(cs, HasherChannel::<H>::default().digest)?,
+            _hasher: PhantomData,
+        })
+    }
+
+    fn mix_digest(&mut self, digest: &H::Var) -> Result<()> {
+        let cs = self.digest.cs().and(&digest.cs());
+
+        let mut channel = HasherChannel::<H>::from_digest(self.digest.digest().to_vec());
+        channel.mix_digest(digest.digest());
+
+        cs.insert_script_complex(
+            mix_digest_gadget::<H>,
+            self.digest
+                .variables()
+                .into_iter()
+                .chain(digest.variables()),
+            &Options::new(),
+        )?;
+
+        self.digest = H::Var::new_digest_function_output(&cs, channel.digest)?;
+        Ok(())
+    }
+
+    fn mix_felt(&mut self, felt: &QM31Var) -> Result<()> {
+        let limbs = [
+            &felt.first.real,
+            &felt.first.imag,
+            &felt.second.real,
+            &felt.second.imag,
+        ];
+        let mut cs = self.digest.cs();
+        for limb in limbs.iter() {
+            cs = cs.and(&limb.cs());
+        }
+
+        let mut channel = HasherChannel::<H>::from_digest(self.digest.digest().to_vec());
+        channel.mix_m31s(&limbs.iter().map(|limb| limb.value).collect::<Vec<_>>());
+
+        cs.insert_script_complex(
+            mix_m31s_gadget::<H>,
+            self.digest
+                .variables()
+                .into_iter()
+                .chain(limbs.iter().map(|limb| limb.variable)),
+            &Options::new().with_u32("n", limbs.len() as u32),
+        )?;
+
+        self.digest = H::Var::new_digest_function_output(&cs, channel.digest)?;
+        Ok(())
+    }
+
+    fn draw_felt(&mut self) -> Result<QM31Var> {
+        let limbs = self.draw_limbs()?;
+        Ok(QM31Var {
+            first: CM31Var {
+                real: limbs[0].clone(),
+                imag: limbs[1].clone(),
+            },
+            second: CM31Var {
+                real: limbs[2].clone(),
+                imag: limbs[3].clone(),
+            },
+        })
+    }
+
+    fn draw_numbers(&mut self, n: usize, logn: usize) -> Result<Vec<M31Var>> {
+        assert!(logn < 31);
+
+        let mut res = vec![];
+        while res.len() < n {
+            for limb in self.draw_limbs()?.iter().take(n - res.len()) {
+                res.push(lowest_bits(limb, logn)?);
+            }
+        }
+        Ok(res)
+    }
+
+    fn verify_pow(&mut self, n_bits: u32, nonce: u64) -> Result<()> {
+        self.digest =
+            verify_pow_with_hasher::<H>(&self.digest, n_bits, nonce, NonceEncoding::U64Le)?;
+        Ok(())
+    }
+}
+
+/// The lowest `logn` bits of a limb, whose remaining bits are provided as a hint.
+fn lowest_bits(limb: &M31Var, logn: usize) -> Result<M31Var> {
+    let cs = limb.cs();
+
+    let low = M31Var::new_hint(&cs, M31::from(limb.value.0 & ((1 << logn) - 1)))?;
+    let high = M31Var::new_hint(&cs, M31::from(limb.value.0 >> logn))?;
+
+    cs.insert_script_complex(
+        lowest_bits_gadget,
+        [limb.variable, low.variable, high.variable],
+        &Options::new().with_u32("logn", logn as u32),
+    )?;
+
+    Ok(low)
+}
+
+/// A channel whose digest is computed with `H`, natively. See `HasherChannelVar` for how it
+/// works.
+pub struct HasherChannel<H: ScriptHasher> {
+    pub digest: Vec<u8>,
+    _hasher: PhantomData<H>,
+}
+
+impl<H: ScriptHasher> Default for HasherChannel<H> {
+    fn default() -> Self {
+        Self::from_digest(vec![0u8; H::DIGEST_LEN])
+    }
+}
+
+impl<H: ScriptHasher> HasherChannel<H> {
+    pub fn from_digest(digest: Vec<u8>) -> Self {
+        assert_eq!(digest.len(), H::DIGEST_LEN);
+        Self {
+            digest,
+            _hasher: PhantomData,
+        }
+    }
+
+    pub fn mix_digest(&mut self, digest: &[u8]) {
+        self.digest = H::hash(&[digest, self.digest.as_slice()].concat());
+    }
+
+    pub fn mix_m31s(&mut self, values: &[M31]) {
+        for &value in values.iter() {
+            self.digest = H::hash(&[m31_to_script_num(value), self.digest.clone()].concat());
+        }
+    }
+
+    pub fn mix_felts(&mut self, felts: &[QM31]) {
+        for felt in felts.iter() {
+            self.mix_m31s(&[felt.0 .0, felt.0 .1, felt.1 .0, felt.1 .1]);
+        }
+    }
+
+    pub fn mix_nonce(&mut self, nonce: u64) {
+        self.digest = H::mix_nonce(&self.digest, nonce);
+    }
+
+    fn draw_limbs(&mut self) -> Vec<M31> {
+        self.digest = H::hash(&self.digest);
+        self.digest
+            .chunks_exact(4)
+            .map(|chunk| {
+                let limb = u32::from_le_bytes(chunk.try_into().unwrap()) & 0x7fff_ffff;
+                M31::from(if limb == 0x7fff_ffff { 0 } else { limb })
+            })
+            .collect()
+    }
+
+    pub fn draw_felt(&mut self) -> QM31 {
+        let limbs = self.draw_limbs();
+        QM31(
+            CM31::from_m31(limbs[0], limbs[1]),
+            CM31::from_m31(limbs[2], limbs[3]),
+        )
+    }
+
+    pub fn draw_numbers(&mut self, n: usize, logn: usize) -> Vec<usize> {
+        let mut res = vec![];
+        while res.len() < n {
+            for limb in self.draw_limbs().iter().take(n - res.len()) {
+                res.push((limb.0 & ((1 << logn) - 1)) as usize);
+            }
+        }
+        res
+    }
+}
+
+fn mix_digest_gadget<H: ScriptHasher>(_: &mut Stack, _: &Options) -> Result<Script> {
+    Ok(script! {
+        // stack:
+        // - channel
+        // - digest
+        OP_SWAP OP_CAT
+        { H::hash_gadget() }
+    })
+}
+
+fn mix_m31s_gadget<H: ScriptHasher>(_: &mut Stack, options: &Options) -> Result<Script> {
+    let n = options.get_u32("n")?;
+    Ok(script! {
+        // stack:
+        // - channel
+        // - v_0
+        // - ...
+        // - v_{n-1}
+        { n } OP_ROLL
+        for i in (1..=n).rev() {
+            { i } OP_ROLL OP_SWAP OP_CAT
+            { H::hash_gadget() }
+        }
+    })
+}
+
+fn draw_limbs_gadget<H: ScriptHasher>(_: &mut Stack, _: &Options) -> Result<Script> {
+    let num_bytes = H::DIGEST_LEN;
+    let num_limbs = num_bytes / 4;
+
+    Ok(script! {
+        // stack:
+        // - channel
+        // - byte_0
+        // - ...
+        // - byte_{len-1}
+
+        // turn the bytes into a string, from the last one to the first, and keep a copy of the
+        // bytes in the altstack
+        OP_DUP OP_TOALTSTACK
+        byte_to_str
+        for _ in 1..num_bytes {
+            OP_SWAP
+            OP_DUP OP_TOALTSTACK
+            byte_to_str
+            OP_SWAP OP_CAT
+        }
+
+        // check that the string is the new channel
+        OP_SWAP { H::hash_gadget() }
+        OP_DUP OP_ROT OP_EQUALVERIFY
+
+        // stack:
+        // - new_channel
+        //
+        // altstack:
+        // - byte_{len-1}
+        // - ...
+        // - byte_0
+
+        for _ in 0..num_limbs {
+            OP_FROMALTSTACK OP_FROMALTSTACK OP_FROMALTSTACK OP_FROMALTSTACK
+
+            // drop the top bit
+            OP_DUP 128 OP_GREATERTHANOREQUAL OP_IF
+                128 OP_SUB
+            OP_ENDIF
+
+            for _ in 0..3 {
+                for _ in 0..8 {
+                    OP_DUP OP_ADD
+                }
+                OP_ADD
+            }
+
+            OP_DUP { 0x7fff_ffff } OP_EQUAL OP_IF
+                OP_DROP 0
+            OP_ENDIF
+        }
+
+        for _ in 0..num_bytes % 4 {
+            OP_FROMALTSTACK OP_DROP
+        }
+
+        // move the new channel to the top
+        { num_limbs } OP_ROLL
+    })
+}
+
+/// Check that the number is a byte and turn it into a single-byte string.
+fn byte_to_str() -> Script {
+    script! {
+        OP_DUP 0 256 OP_WITHIN OP_VERIFY
+
+        OP_DUP 0 OP_EQUAL OP_IF
+            OP_DROP OP_PUSHBYTES_1 OP_PUSHBYTES_0
+        OP_ELSE
+            OP_DUP 128 OP_EQUAL OP_IF
+                OP_DROP { vec![0x80u8] }
+            OP_ELSE
+                // a byte `b` above 128 is the script number `128 - b`, whose sign bit is set
+                OP_DUP 128 OP_GREATERTHAN OP_IF
+                    128 OP_SWAP OP_SUB
+                OP_ENDIF
+            OP_ENDIF
+        OP_ENDIF
+    }
+}
+
+fn lowest_bits_gadget(_: &mut Stack, options: &Options) -> Result<Script> {
+    let logn = options.get_u32("logn")?;
+    Ok(script! {
+        // stack:
+        // - limb
+        // - low
+        // - high
+        OP_OVER 0 { 1 << logn } OP_WITHIN OP_VERIFY
+        for _ in 0..logn {
+            OP_DUP OP_ADD
+        }
+        OP_ADD
+        OP_EQUALVERIFY
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::algorithms::channel::{ChannelVar, HasherChannel, HasherChannelVar};
+    use crate::algorithms::hasher::{DigestVar, Hash160ScriptHasher, ScriptHasher};
+    use crate::algorithms::pow::{grind, NonceEncoding};
+    use bitcoin_circle_stark::treepp::*;
+    use bitcoin_script_dsl::builtins::qm31::QM31Var;
+    use bitcoin_script_dsl::bvar::AllocVar;
+    use bitcoin_script_dsl::constraint_system::ConstraintSystem;
+    use bitcoin_script_dsl::test_program;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha20Rng;
+    use stwo_prover::core::fields::cm31::CM31;
+    use stwo_prover::core::fields::m31::M31;
+    use stwo_prover::core::fields::qm31::QM31;
+
+    fn check_channel<H: ScriptHasher>(prng: &mut ChaCha20Rng) {
+        let mut gen_m31 = || M31::from(prng.gen_range(0..(1u32 << 31) - 1));
+        let felt = QM31(
+            CM31::from_m31(gen_m31(), gen_m31()),
+            CM31::from_m31(M31::from(0), M31::from(0x80)),
+        );
+        let commitment = (0..H::DIGEST_LEN).map(|_| prng.gen()).collect::<Vec<u8>>();
+
+        let mut channel = HasherChannel::<H>::default();
+
+        let cs = ConstraintSystem::new_ref();
+        let mut channel_var = HasherChannelVar::<H>::new(&cs).unwrap();
+
+        channel.mix_digest(&commitment);
+        channel_var
+            .mix_digest(&H::Var::new_digest_hint(&cs, commitment).unwrap())
+            .unwrap();
+        assert_eq!(channel_var.digest.digest(), channel.digest);
+
+        let expected = channel.draw_felt();
+        let felt_var = channel_var.draw_felt().unwrap();
+        assert_eq!(
+            [
+                felt_var.first.real.value,
+                felt_var.first.imag.value,
+                felt_var.second.real.value,
+                felt_var.second.imag.value
+            ],
+            [expected.0 .0, expected.0 .1, expected.1 .0, expected.1 .1]
+        );
+
+        channel.mix_felts(&[felt]);
+        channel_var
+            .mix_felt(&QM31Var::new_hint(&cs, felt).unwrap())
+            .unwrap();
+        assert_eq!(channel_var.digest.digest(), channel.digest);
+
+        let nonce = grind::<H>(&channel.digest, 8, NonceEncoding::U64Le);
+        channel.mix_nonce(nonce);
+        channel_var.verify_pow(8, nonce).unwrap();
+        assert_eq!(channel_var.digest.digest(), channel.digest);
+
+        // more numbers than the limbs of one draw
+        let expected = channel.draw_numbers(8, 6);
+        let numbers_var = channel_var.draw_numbers(8, 6).unwrap();
+        for (number_var, &number) in numbers_var.iter().zip(expected.iter()) {
+            assert_eq!(number_var.value.0 as usize, number);
+            cs.set_program_output(number_var).unwrap();
+        }
+
+        test_program(
+            cs,
+            script! {
+                for number in expected {
+                    { number }
+                }
+            },
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_hasher_channel() {
+        let mut prng = ChaCha20Rng::seed_from_u64(0);
+        for _ in 0..4 {
+            check_channel::<Hash160ScriptHasher>(&mut prng);
+        }
+    }
+}
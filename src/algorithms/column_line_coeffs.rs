@@ -1,3 +1,4 @@
+use crate::algorithms::div::DivVar;
 use anyhow::Result;
 use bitcoin_script_dsl::builtins::cm31::CM31Var;
 use bitcoin_script_dsl::builtins::qm31::QM31Var;
@@ -20,14 +21,11 @@ pub fn column_line_coeffs(
     y: &QM31Var,
     evals: &[QM31Var],
 ) -> Result<Vec<(CM31Var, CM31Var)>> {
-    let y_second_inverse = y.second.inverse(&table);
-    let y_first_times_y_second_inv = &y.first * &y_second_inverse;
-
     let mut ab = vec![];
 
     for eval in evals.iter() {
-        let a = &eval.second * (table, &y_second_inverse);
-        let b = &(&eval.second * (table, &y_first_times_y_second_inv)) - &eval.first;
+        let a = eval.second.div(table, &y.second)?;
+        let b = &(&a * (table, &y.first)) - &eval.first;
         ab.push((a, b));
     }
 
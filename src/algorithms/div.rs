@@ -0,0 +1,259 @@
+use anyhow::{Error, Result};
+use bitcoin_circle_stark::treepp::*;
+use bitcoin_script_dsl::builtins::cm31::CM31Var;
+use bitcoin_script_dsl::builtins::m31::M31Var;
+use bitcoin_script_dsl::builtins::qm31::QM31Var;
+use bitcoin_script_dsl::builtins::table::TableVar;
+use bitcoin_script_dsl::bvar::{AllocVar, BVar};
+use bitcoin_script_dsl::options::Options;
+use bitcoin_script_dsl::stack::Stack;
+use num_traits::Zero;
+use stwo_prover::core::fields::cm31::CM31;
+use stwo_prover::core::fields::qm31::QM31;
+use stwo_prover::core::fields::FieldExpOps;
+
+/// Division through a hinted quotient.
+///
+/// Instead of computing `x · y^{-1}` in the script, the quotient `q` is provided as a hint and
+/// the script only checks `q · y == x`, which costs a single multiplication.
+///
+/// The divisor is also checked to be nonzero in the script. The stwo verifier treats the
+/// inversion of zero as an error, and without this check `q · 0 == 0` would hold for any `q`,
+/// leaving the quotient unconstrained.
+pub trait DivVar: Sized {
+    fn div(&self, table: &TableVar, rhs: &Self) -> Result<Self>;
+}
+
+impl DivVar for M31Var {
+    fn div(&self, table: &TableVar, rhs: &Self) -> Result<Self> {
+        if rhs.value.is_zero() {
+            return Err(Error::msg("Division by zero"));
+        }
+
+        let cs = self.cs().and(&rhs.cs()).and(&table.cs());
+        check_nonzero(rhs)?;
+
+        let quotient = M31Var::new_hint(&cs, self.value * rhs.value.inverse())?;
+        (&quotient * (table, rhs)).equalverify(self)?;
+
+        Ok(quotient)
+    }
+}
+
+impl DivVar for CM31Var {
+    fn div(&self, table: &TableVar, rhs: &Self) -> Result<Self> {
+        let rhs_value = cm31_value(rhs);
+        if rhs_value.is_zero() {
+            return Err(Error::msg("Division by zero"));
+        }
+
+        let cs = self.cs().and(&rhs.cs()).and(&table.cs());
+        check_nonzero(rhs)?;
+
+        let quotient = CM31Var::new_hint(&cs, cm31_value(self) * rhs_value.inverse())?;
+        (&quotient * (table, rhs)).equalverify(self)?;
+
+        Ok(quotient)
+    }
+}
+
+impl DivVar for QM31Var {
+    fn div(&self, table: &TableVar, rhs: &Self) -> Result<Self> {
+        let rhs_value = qm31_value(rhs);
+        if rhs_value.is_zero() {
+            return Err(Error::msg("Division by zero"));
+        }
+
+        let cs = self.cs().and(&rhs.cs()).and(&table.cs());
+        check_nonzero(rhs)?;
+
+        let quotient = QM31Var::new_hint(&cs, qm31_value(self) * rhs_value.inverse())?;
+        (&quotient * (table, rhs)).equalverify(self)?;
+
+        Ok(quotient)
+    }
+}
+
+pub(crate) fn cm31_value(v: &CM31Var) -> CM31 {
+    CM31::from_m31(v.real.value, v.imag.value)
+}
+
+pub(crate) fn qm31_value(v: &QM31Var) -> QM31 {
+    QM31(cm31_value(&v.first), cm31_value(&v.second))
+}
+
+fn check_nonzero<T: BVar>(v: &T) -> Result<()> {
+    let variables = v.variables();
+    let num_limbs = variables.len() as u32;
+
+    v.cs().insert_script_complex(
+        check_nonzero_gadget,
+        variables,
+        &Options::new().with_u32("num_limbs", num_limbs),
+    )
+}
+
+fn check_nonzero_gadget(_: &mut Stack, options: &Options) -> Result<Script> {
+    let num_limbs = options.get_u32("num_limbs")?;
+
+    Ok(script! {
+        // stack:
+        // - limbs of the divisor
+        //
+        // the divisor is nonzero if and only if any of its limbs is nonzero
+
+        for _ in 1..num_limbs {
+            OP_BOOLOR
+        }
+        OP_VERIFY
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::algorithms::div::DivVar;
+    use bitcoin_circle_stark::treepp::*;
+    use bitcoin_script_dsl::builtins::cm31::CM31Var;
+    use bitcoin_script_dsl::builtins::m31::M31Var;
+    use bitcoin_script_dsl::builtins::qm31::QM31Var;
+    use bitcoin_script_dsl::builtins::table::TableVar;
+    use bitcoin_script_dsl::bvar::AllocVar;
+    use bitcoin_script_dsl::compiler::Compiler;
+    use bitcoin_script_dsl::constraint_system::ConstraintSystem;
+    use bitcoin_script_dsl::test_program;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha20Rng;
+    use stwo_prover::core::fields::cm31::CM31;
+    use stwo_prover::core::fields::m31::M31;
+    use stwo_prover::core::fields::qm31::QM31;
+    use stwo_prover::core::fields::FieldExpOps;
+
+    #[test]
+    fn test_div() {
+        let mut prng = ChaCha20Rng::seed_from_u64(0);
+        let mut gen_m31 = || M31::from(prng.gen_range(1..(1u32 << 31) - 1));
+
+        for _ in 0..10 {
+            let cs = ConstraintSystem::new_ref();
+            let table = TableVar::new_constant(&cs, ()).unwrap();
+
+            let a = gen_m31();
+            let b = gen_m31();
+            let a_var = M31Var::new_program_input(&cs, a).unwrap();
+            let b_var = M31Var::new_program_input(&cs, b).unwrap();
+            let expected = M31Var::new_constant(&cs, a * b.inverse()).unwrap();
+            a_var
+                .div(&table, &b_var)
+                .unwrap()
+                .equalverify(&expected)
+                .unwrap();
+
+            let a = CM31::from_m31(gen_m31(), gen_m31());
+            let b = CM31::from_m31(gen_m31(), gen_m31());
+            let a_var = CM31Var::new_program_input(&cs, a).unwrap();
+            let b_var = CM31Var::new_program_input(&cs, b).unwrap();
+            let expected = CM31Var::new_constant(&cs, a * b.inverse()).unwrap();
+            a_var
+                .div(&table, &b_var)
+                .unwrap()
+                .equalverify(&expected)
+                .unwrap();
+
+            let a = QM31::from_m31(gen_m31(), gen_m31(), gen_m31(), gen_m31());
+            let b = QM31::from_m31(gen_m31(), gen_m31(), gen_m31(), gen_m31());
+            let a_var = QM31Var::new_program_input(&cs, a).unwrap();
+            let b_var = QM31Var::new_program_input(&cs, b).unwrap();
+            let expected = QM31Var::new_constant(&cs, a * b.inverse()).unwrap();
+            a_var
+                .div(&table, &b_var)
+                .unwrap()
+                .equalverify(&expected)
+                .unwrap();
+
+            test_program(cs, script! {}).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_div_by_zero() {
+        let cs = ConstraintSystem::new_ref();
+        let table = TableVar::new_constant(&cs, ()).unwrap();
+
+        let a_var = QM31Var::new_program_input(&cs, QM31::from_u32_unchecked(1, 2, 3, 4)).unwrap();
+        let zero_var =
+            QM31Var::new_program_input(&cs, QM31::from_u32_unchecked(0, 0, 0, 0)).unwrap();
+        assert!(a_var.div(&table, &zero_var).is_err());
+
+        let a_var = M31Var::new_program_input(&cs, M31::from(5)).unwrap();
+        let zero_var = M31Var::new_program_input(&cs, M31::from(0)).unwrap();
+        assert!(a_var.div(&table, &zero_var).is_err());
+    }
+
+    #[test]
+    fn test_inverses_against_batch_inversion() {
+        let mut prng = ChaCha20Rng::seed_from_u64(0);
+        let mut gen_qm31 = || {
+            QM31::from_m31(
+                M31::from(prng.gen_range(1..(1u32 << 31) - 1)),
+                M31::from(prng.gen_range(1..(1u32 << 31) - 1)),
+                M31::from(prng.gen_range(1..(1u32 << 31) - 1)),
+                M31::from(prng.gen_range(1..(1u32 << 31) - 1)),
+            )
+        };
+
+        // each hinted inverse is checked with one multiplication, while Montgomery's trick needs
+        // 3(n - 1) multiplications and one inversion for n elements, so the hinted inverses are
+        // always the shorter script
+        for n in 2..=8 {
+            let elems = (0..n).map(|_| gen_qm31()).collect::<Vec<_>>();
+
+            let build = |batch: bool| {
+                let cs = ConstraintSystem::new_ref();
+                let table = TableVar::new_constant(&cs, ()).unwrap();
+                let elem_vars = elems
+                    .iter()
+                    .map(|&elem| QM31Var::new_program_input(&cs, elem).unwrap())
+                    .collect::<Vec<_>>();
+
+                let inverse_vars = if batch {
+                    let mut prefix_products = vec![elem_vars[0].clone()];
+                    for elem_var in elem_vars.iter().skip(1) {
+                        let prod = prefix_products.last().unwrap() * (&table, elem_var);
+                        prefix_products.push(prod);
+                    }
+
+                    let mut cur_inverse = prefix_products.last().unwrap().inverse(&table);
+                    let mut res = vec![];
+                    for i in (1..n).rev() {
+                        res.push(&cur_inverse * (&table, &prefix_products[i - 1]));
+                        cur_inverse = &cur_inverse * (&table, &elem_vars[i]);
+                    }
+                    res.push(cur_inverse);
+                    res.reverse();
+                    res
+                } else {
+                    let one_var =
+                        QM31Var::new_constant(&cs, QM31::from_u32_unchecked(1, 0, 0, 0)).unwrap();
+                    elem_vars
+                        .iter()
+                        .map(|elem_var| one_var.div(&table, elem_var).unwrap())
+                        .collect::<Vec<_>>()
+                };
+
+                for (inverse_var, elem) in inverse_vars.iter().zip(elems.iter()) {
+                    inverse_var
+                        .equalverify(&QM31Var::new_constant(&cs, elem.inverse()).unwrap())
+                        .unwrap();
+                }
+                cs
+            };
+
+            test_program(build(false), script! {}).unwrap();
+            test_program(build(true), script! {}).unwrap();
+
+            let hinted = Compiler::compile(build(false)).unwrap();
+            let batch = Compiler::compile(build(true)).unwrap();
+            assert!(hinted.script.len() < batch.script.len());
+        }
+    }
+}
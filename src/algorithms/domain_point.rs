@@ -0,0 +1,199 @@
+use crate::algorithms::div::DivVar;
+use crate::algorithms::folding::skip_one_and_extract_bits;
+use crate::algorithms::precomputed_tree::PrecomputedVar;
+use anyhow::Result;
+use bitcoin_script_dsl::builtins::m31::M31Var;
+use bitcoin_script_dsl::builtins::table::TableVar;
+use bitcoin_script_dsl::bvar::{AllocVar, BVar};
+use num_traits::One;
+use stwo_prover::core::circle::CirclePoint;
+use stwo_prover::core::fields::m31::M31;
+use stwo_prover::core::poly::circle::CanonicCoset;
+
+/// Compute the circle point and the twiddles of a position in the script, as an alternative to
+/// `query_and_verify_precomputed_merkle_tree`, which opens them from the precomputed tree.
+///
+/// `pos` is a position in the commitment domain of log size `log_size + 1`, and the outputs are
+/// what the precomputed tree of log size `log_size` opens at `pos`:
+/// - the point is the one of the twin with the lowest bit 0, i.e., the point of the half coset at
+///   the bit-reversed `pos >> 1`. It starts from the initial point of the half coset, and each bit
+///   of `pos >> 1` selects whether a constant multiple of the step is added.
+/// - the twiddle of the circle-to-line fold is the inverse of the y coordinate of the point, and
+///   the twiddle of the `m`-th line fold is the inverse of the x coordinate of the point doubled
+///   `m` times, negated if the bit `m` of `pos >> 1` is 1, as the fold then pairs the point with
+///   its antipode.
+///
+/// The twiddles are ordered as in the tree, from the top layer, so the last one is the twiddle of
+/// the circle-to-line fold.
+///
+/// The position must be less than `2^(log_size + 1)`, which holds for the queries drawn from the
+/// channel, as otherwise the bits are not unique.
+///
+/// It takes no Merkle path from the hints, but the script is longer than the opening of the
+/// precomputed tree at every log size, which the test checks.
+pub fn compute_point_and_twiddles(
+    table: &TableVar,
+    pos: &M31Var,
+    log_size: usize,
+) -> Result<PrecomputedVar> {
+    let cs = pos.cs().and(&table.cs());
+    let half_coset = CanonicCoset::new(log_size as u32 + 1)
+        .circle_domain()
+        .half_coset;
+
+    // the bits of `pos >> 1`, from the lowest
+    let bits = skip_one_and_extract_bits(pos, log_size);
+
+    let mut x = M31Var::new_constant(&cs, half_coset.initial.x)?;
+    let mut y = M31Var::new_constant(&cs, half_coset.initial.y)?;
+    for (k, bit) in bits.iter().enumerate() {
+        // the bit `k` of `pos >> 1` is the bit `log_size - 1 - k` of the index in the half coset
+        let step = (half_coset.step_size * (1 << (log_size - 1 - k))).to_point();
+        let (added_x, added_y) = add_constant_point(table, &x, &y, step)?;
+        x = select(table, bit, &x, &added_x);
+        y = select(table, bit, &y, &added_y);
+    }
+
+    let one = M31Var::new_constant(&cs, M31::one())?;
+
+    let mut twiddles_var = vec![];
+    let mut doubled_x = x.clone();
+    for (m, bit) in bits.iter().enumerate().take(log_size - 1) {
+        if m > 0 {
+            // 2 · x^2 − 1
+            let square = &doubled_x * (table, &doubled_x);
+            doubled_x = &(&square + &square) - &one;
+        }
+
+        // 1 − 2 · bit is -1 if the fold pairs the point with its antipode
+        let sign = &one - &(bit + bit);
+        twiddles_var.push(one.div(table, &(&doubled_x * (table, &sign)))?);
+    }
+    twiddles_var.reverse();
+    twiddles_var.push(one.div(table, &y)?);
+
+    Ok(PrecomputedVar {
+        circle_point_x_var: x,
+        circle_point_y_var: y,
+        twiddles_var,
+    })
+}
+
+/// Where the verifier takes the circle points and the twiddles of the queries from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DomainPointSource {
+    /// Open them from the precomputed tree with `query_and_verify_precomputed_merkle_tree`.
+    #[default]
+    PrecomputedTree,
+    /// Compute them in the script with `compute_point_and_twiddles`.
+    InScript,
+}
+
+/// Add a constant point to the point `(x, y)`.
+fn add_constant_point(
+    table: &TableVar,
+    x: &M31Var,
+    y: &M31Var,
+    constant: CirclePoint<M31>,
+) -> Result<(M31Var, M31Var)> {
+    let cs = x.cs().and(&y.cs());
+
+    // new x: x0 · x1 − y0 · y1
+    // new y: x0 · y1 + y0 · x1
+    // use Karatsuba
+
+    let x1 = M31Var::new_constant(&cs, constant.x)?;
+    let y1 = M31Var::new_constant(&cs, constant.y)?;
+    let x1_plus_y1 = M31Var::new_constant(&cs, constant.x + constant.y)?;
+
+    let x0x1 = x * (table, &x1);
+    let y0y1 = y * (table, &y1);
+    let all_terms = &(x + y) * (table, &x1_plus_y1);
+
+    let new_x = &x0x1 - &y0y1;
+    let new_y = &(&all_terms - &x0x1) - &y0y1;
+
+    Ok((new_x, new_y))
+}
+
+/// `b` if the bit is 1, and `a` otherwise.
+fn select(table: &TableVar, bit: &M31Var, a: &M31Var, b: &M31Var) -> M31Var {
+    a + &(bit * (table, &(b - a)))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::algorithms::domain_point::compute_point_and_twiddles;
+    use crate::algorithms::precomputed_tree::query_and_verify_precomputed_merkle_tree;
+    use crate::algorithms::precomputed_tree_builder::{
+        precomputed_tree_builder, MIN_PRECOMPUTED_TREE_LOG_SIZE,
+    };
+    use bitcoin_circle_stark::treepp::*;
+    use bitcoin_script_dsl::builtins::m31::M31Var;
+    use bitcoin_script_dsl::builtins::table::TableVar;
+    use bitcoin_script_dsl::bvar::AllocVar;
+    use bitcoin_script_dsl::compiler::Compiler;
+    use bitcoin_script_dsl::constraint_system::ConstraintSystem;
+    use bitcoin_script_dsl::test_program;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha20Rng;
+    use stwo_prover::core::fields::m31::M31;
+
+    #[test]
+    fn test_compute_point_and_twiddles() {
+        let mut prng = ChaCha20Rng::seed_from_u64(0);
+
+        let mut last_sizes = None;
+        for log_size in MIN_PRECOMPUTED_TREE_LOG_SIZE..=15 {
+            let tree = precomputed_tree_builder().build_tree(log_size).unwrap();
+            let root = precomputed_tree_builder().root(log_size).unwrap();
+
+            let pos = prng.gen_range(0..(2usize << log_size));
+            let proof = tree.query(pos);
+
+            let build_in_script = || {
+                let cs = ConstraintSystem::new_ref();
+                let table = TableVar::new_constant(&cs, ()).unwrap();
+                let pos_var = M31Var::new_program_input(&cs, M31::from(pos as u32)).unwrap();
+
+                let res = compute_point_and_twiddles(&table, &pos_var, log_size).unwrap();
+                assert_eq!(res.twiddles_var.len(), proof.twiddles_elements.len());
+
+                res.circle_point_x_var
+                    .equalverify(&M31Var::new_constant(&cs, proof.circle_point.x).unwrap())
+                    .unwrap();
+                res.circle_point_y_var
+                    .equalverify(&M31Var::new_constant(&cs, proof.circle_point.y).unwrap())
+                    .unwrap();
+                for (twiddle_var, &twiddle) in
+                    res.twiddles_var.iter().zip(proof.twiddles_elements.iter())
+                {
+                    twiddle_var
+                        .equalverify(&M31Var::new_constant(&cs, twiddle).unwrap())
+                        .unwrap();
+                }
+                cs
+            };
+
+            let build_merkle_path = || {
+                let cs = ConstraintSystem::new_ref();
+                let pos_var = M31Var::new_program_input(&cs, M31::from(pos as u32)).unwrap();
+                query_and_verify_precomputed_merkle_tree(&root, &pos_var, &proof).unwrap();
+                cs
+            };
+
+            let in_script = Compiler::compile(build_in_script()).unwrap();
+            let merkle_path = Compiler::compile(build_merkle_path()).unwrap();
+            test_program(build_in_script(), script! {}).unwrap();
+
+            // the script of the computation is longer than the Merkle path, and both grow with
+            // every layer
+            assert!(in_script.script.len() > merkle_path.script.len());
+            if let Some((last_in_script_len, last_merkle_path_len)) = last_sizes {
+                assert!(in_script.script.len() > last_in_script_len);
+                assert!(merkle_path.script.len() > last_merkle_path_len);
+            }
+            last_sizes = Some((in_script.script.len(), merkle_path.script.len()));
+        }
+    }
+}
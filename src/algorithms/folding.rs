@@ -98,6 +98,28 @@ pub fn skip_one_and_extract_bits(pos: &M31Var, n: usize) -> Vec<M31Var> {
     hint_vars[1..=n].to_vec()
 }
 
+/// Verify that the value is equal to at least one of the candidates.
+pub fn verify_equal_to_any(value: &M31Var, candidates: &[M31Var]) {
+    assert!(!candidates.is_empty());
+
+    let mut cs = value.cs();
+    for candidate in candidates.iter() {
+        cs = cs.and(&candidate.cs());
+    }
+
+    let mut variables = vec![value.variable];
+    for candidate in candidates.iter() {
+        variables.push(candidate.variable);
+    }
+
+    cs.insert_script_complex(
+        verify_equal_to_any_gadget,
+        variables,
+        &Options::new().with_u32("n", candidates.len() as u32),
+    )
+    .unwrap();
+}
+
 fn decompose_positions_gadget(_: &mut Stack, options: &Options) -> Result<Script> {
     let n = options.get_u32("n")?;
 
@@ -140,6 +162,29 @@ fn skip_one_and_extract_bits_gadget(_: &mut Stack, options: &Options) -> Result<
     })
 }
 
+fn verify_equal_to_any_gadget(_: &mut Stack, options: &Options) -> Result<Script> {
+    let n = options.get_u32("n")?;
+
+    Ok(script! {
+        // stack:
+        // - value
+        // - n candidates
+
+        { n } OP_ROLL
+
+        for _ in 0..n {
+            OP_SWAP OP_OVER OP_EQUAL OP_TOALTSTACK
+        }
+        OP_DROP
+
+        OP_FROMALTSTACK
+        for _ in 1..n {
+            OP_FROMALTSTACK OP_BOOLOR
+        }
+        OP_VERIFY
+    })
+}
+
 fn check_0_or_1() -> Script {
     script! {
         OP_DUP 0 OP_GREATERTHANOREQUAL OP_VERIFY
@@ -149,7 +194,9 @@ fn check_0_or_1() -> Script {
 
 #[cfg(test)]
 mod test {
-    use crate::algorithms::folding::{decompose_positions, skip_one_and_extract_bits};
+    use crate::algorithms::folding::{
+        decompose_positions, skip_one_and_extract_bits, verify_equal_to_any,
+    };
     use bitcoin_circle_stark::treepp::*;
     use bitcoin_script_dsl::builtins::m31::M31Var;
     use bitcoin_script_dsl::bvar::AllocVar;
@@ -220,4 +267,27 @@ mod test {
             .unwrap()
         }
     }
+
+    #[test]
+    fn test_verify_equal_to_any() {
+        let mut prng = ChaCha20Rng::seed_from_u64(0);
+
+        for n in 1..8 {
+            let cs = ConstraintSystem::new_ref();
+
+            let candidates = (0..n)
+                .map(|_| prng.gen_range(0..=1023))
+                .collect::<Vec<u32>>();
+            let value = candidates[prng.gen_range(0..n)];
+
+            let value_var = M31Var::new_program_input(&cs, M31::from(value)).unwrap();
+            let candidate_vars = candidates
+                .iter()
+                .map(|&candidate| M31Var::new_hint(&cs, M31::from(candidate)).unwrap())
+                .collect::<Vec<_>>();
+            verify_equal_to_any(&value_var, &candidate_vars);
+
+            test_program(cs, script! {}).unwrap();
+        }
+    }
 }
@@ -0,0 +1,326 @@
+use crate::algorithms::pow::trailing_zero_bits;
+use anyhow::Result;
+use bitcoin::hashes::{hash160, Hash};
+use bitcoin::script::write_scriptint;
+use bitcoin_circle_stark::channel::Sha256ChannelGadget;
+use bitcoin_circle_stark::treepp::*;
+use bitcoin_circle_stark::utils::hash;
+use bitcoin_script_dsl::builtins::hash::HashVar;
+use bitcoin_script_dsl::builtins::m31::M31Var;
+use bitcoin_script_dsl::builtins::str::StrVar;
+use bitcoin_script_dsl::bvar::{AllocVar, BVar};
+use bitcoin_script_dsl::constraint_system::{ConstraintSystem, ConstraintSystemRef};
+use bitcoin_script_dsl::options::Options;
+use bitcoin_script_dsl::stack::Stack;
+use sha2::{Digest, Sha256};
+use stwo_prover::core::channel::{Channel, Sha256Channel};
+use stwo_prover::core::fields::m31::M31;
+use stwo_prover::core::vcs::sha256_hash::Sha256Hash;
+
+/// A variable that holds a digest.
+///
+/// The DSL has its own variable for SHA-256 digests, `HashVar`, which the LDM and the channel
+/// of the DSL work with. The digests of other hash functions are held in a `StrVar`.
+pub trait DigestVar: BVar + AllocVar + Clone {
+    fn new_digest_hint(cs: &ConstraintSystemRef, digest: Vec<u8>) -> Result<Self>;
+
+    fn new_digest_constant(cs: &ConstraintSystemRef, digest: Vec<u8>) -> Result<Self>;
+
+    fn new_digest_function_output(cs: &ConstraintSystemRef, digest: Vec<u8>) -> Result<Self>;
+
+    fn digest(&self) -> &[u8];
+}
+
+impl DigestVar for HashVar {
+    fn new_digest_hint(cs: &ConstraintSystemRef, digest: Vec<u8>) -> Result<Self> {
+        Ok(HashVar::new_hint(cs, digest)?)
+    }
+
+    fn new_digest_constant(cs: &ConstraintSystemRef, digest: Vec<u8>) -> Result<Self> {
+        Ok(HashVar::new_constant(cs, digest)?)
+    }
+
+    fn new_digest_function_output(cs: &ConstraintSystemRef, digest: Vec<u8>) -> Result<Self> {
+        Ok(HashVar::new_function_output(cs, digest)?)
+    }
+
+    fn digest(&self) -> &[u8] {
+        &self.value
+    }
+}
+
+impl DigestVar for StrVar {
+    fn new_digest_hint(cs: &ConstraintSystemRef, digest: Vec<u8>) -> Result<Self> {
+        Ok(StrVar::new_hint(cs, digest)?)
+    }
+
+    fn new_digest_constant(cs: &ConstraintSystemRef, digest: Vec<u8>) -> Result<Self> {
+        Ok(StrVar::new_constant(cs, digest)?)
+    }
+
+    fn new_digest_function_output(cs: &ConstraintSystemRef, digest: Vec<u8>) -> Result<Self> {
+        Ok(StrVar::new_function_output(cs, digest)?)
+    }
+
+    fn digest(&self) -> &[u8] {
+        &self.value
+    }
+}
+
+/// A hash function that is available both natively and as a Bitcoin script.
+///
+/// The Merkle trees, the proof of work, and the Fiat-Shamir channel (see `channel`) are generic
+/// over this trait, so that the verifier can be instantiated with a hash function other than
+/// SHA-256, as long as the proof is generated under the same one.
+pub trait ScriptHasher: Sized {
+    /// The name of the hash function.
+    const NAME: &'static str;
+
+    /// The length of the digest in bytes.
+    const DIGEST_LEN: usize;
+
+    /// The variable that holds a digest in the script.
+    type Var: DigestVar;
+
+    /// Hash the data natively.
+    fn hash(data: &[u8]) -> Vec<u8>;
+
+    /// The script that hashes the top stack element.
+    fn hash_gadget() -> Script;
+
+    /// Mix a nonce into a channel digest natively.
+    fn mix_nonce(digest: &[u8], nonce: u64) -> Vec<u8>;
+
+    /// The script that mixes a nonce into a channel digest.
+    ///
+    /// stack:
+    /// - nonce
+    /// - digest
+    fn mix_nonce_gadget() -> Script;
+
+    /// Mix a nonce that is already encoded into bytes into a channel digest natively, by hashing
+    /// the digest followed by the nonce.
+    ///
+    /// This is for nonce encodings other than the one of `mix_nonce`.
+    fn mix_nonce_bytes(digest: &[u8], nonce: &[u8]) -> Vec<u8> {
+        Self::hash(&[digest, nonce].concat())
+    }
+
+    /// The script that mixes a nonce that is already encoded into bytes into a channel digest.
+    ///
+    /// stack:
+    /// - nonce
+    /// - digest
+    fn mix_nonce_bytes_gadget() -> Script {
+        script! {
+            OP_SWAP OP_CAT
+            { Self::hash_gadget() }
+        }
+    }
+
+    /// The number of zero bits at the end of a channel digest, which is what a proof of work is
+    /// measured by, as `pow::trailing_zero_bits` counts them.
+    fn trailing_zeros(digest: &[u8]) -> u32 {
+        trailing_zero_bits(digest)
+    }
+
+    /// Hash the values of a leaf natively.
+    ///
+    /// The values are hashed one by one from the last to the first, each as a script number in
+    /// front of the hash so far, i.e., `H(v_0 || H(v_1 || ... H(v_{n-1})))`. Since the hash so
+    /// far has a fixed length, the encoding is unambiguous, and the script does not need to
+    /// serialize the values into a fixed width.
+    fn hash_leaf(values: &[M31]) -> Vec<u8> {
+        let (last, rest) = values.split_last().expect("a leaf has at least one value");
+        let mut cur = Self::hash(&m31_to_script_num(*last));
+        for value in rest.iter().rev() {
+            cur = Self::hash(&[m31_to_script_num(*value), cur].concat());
+        }
+        cur
+    }
+
+    /// Hash the values of a leaf in the script, as `hash_leaf`.
+    fn hash_leaf_var(values: &[M31Var]) -> Result<Self::Var> {
+        assert!(!values.is_empty());
+
+        let mut cs = values[0].cs();
+        for value in values.iter().skip(1) {
+            cs = cs.and(&value.cs());
+        }
+
+        cs.insert_script_complex(
+            hash_leaf_gadget::<Self>,
+            values.iter().map(|value| value.variable),
+            &Options::new().with_u32("n", values.len() as u32),
+        )?;
+
+        let digest = Self::hash_leaf(&values.iter().map(|v| v.value).collect::<Vec<_>>());
+        Self::Var::new_digest_function_output(&cs, digest)
+    }
+}
+
+fn hash_leaf_gadget<H: ScriptHasher>(_: &mut Stack, options: &Options) -> Result<Script> {
+    let n = options.get_u32("n")?;
+    Ok(script! {
+        // stack:
+        // - v_0
+        // - ...
+        // - v_{n-1}
+        { H::hash_gadget() }
+        for _ in 1..n {
+            OP_CAT { H::hash_gadget() }
+        }
+    })
+}
+
+/// The minimal encoding of an M31 element as a script number, which is how it is on the stack.
+pub fn m31_to_script_num(v: M31) -> Vec<u8> {
+    let mut out = [0u8; 8];
+    let len = write_scriptint(&mut out, v.0 as i64);
+    out[0..len].to_vec()
+}
+
+/// SHA-256, which is what stwo's `Sha256Channel` and `Sha256MerkleHasher` use.
+pub struct Sha256ScriptHasher;
+
+impl ScriptHasher for Sha256ScriptHasher {
+    const NAME: &'static str = "SHA-256";
+    const DIGEST_LEN: usize = 32;
+
+    type Var = HashVar;
+
+    fn hash(data: &[u8]) -> Vec<u8> {
+        Sha256::digest(data).to_vec()
+    }
+
+    fn hash_gadget() -> Script {
+        script! {
+            hash
+        }
+    }
+
+    fn mix_nonce(digest: &[u8], nonce: u64) -> Vec<u8> {
+        let mut channel = Sha256Channel::default();
+        channel.update_digest(Sha256Hash::from(digest.to_vec()));
+        channel.mix_nonce(nonce);
+        channel.digest().as_ref().to_vec()
+    }
+
+    fn mix_nonce_gadget() -> Script {
+        Sha256ChannelGadget::mix_nonce()
+    }
+
+    /// stwo's definition, `Sha256Channel::trailing_zeros`.
+    fn trailing_zeros(digest: &[u8]) -> u32 {
+        let mut channel = Sha256Channel::default();
+        channel.update_digest(Sha256Hash::from(digest.to_vec()));
+        channel.trailing_zeros()
+    }
+
+    /// The leaves are hashed by `HashVar`, which matches how `Sha256MerkleHasher` hashes the
+    /// column values of a leaf.
+    fn hash_leaf(values: &[M31]) -> Vec<u8> {
+        let cs = ConstraintSystem::new_ref();
+        let vars = values
+            .iter()
+            .map(|&v| M31Var::new_constant(&cs, v).unwrap())
+            .collect::<Vec<_>>();
+        HashVar::from(vars.as_slice()).value
+    }
+
+    fn hash_leaf_var(values: &[M31Var]) -> Result<HashVar> {
+        Ok(HashVar::from(values))
+    }
+}
+
+/// HASH160, i.e., RIPEMD-160 after SHA-256, which is a single opcode and has a 20-byte digest.
+///
+/// stwo does not come with a HASH160 Merkle hasher or channel, and the plonk prover of the stwo
+/// fork that this crate depends on is fixed to `Sha256MerkleChannel`, so proofs cannot be
+/// generated under it yet. The native `hash_leaf` and `channel::HasherChannel` define what such
+/// a prover has to compute.
+pub struct Hash160ScriptHasher;
+
+impl ScriptHasher for Hash160ScriptHasher {
+    const NAME: &'static str = "HASH160";
+    const DIGEST_LEN: usize = 20;
+
+    type Var = StrVar;
+
+    fn hash(data: &[u8]) -> Vec<u8> {
+        hash160::Hash::hash(data).to_byte_array().to_vec()
+    }
+
+    fn hash_gadget() -> Script {
+        script! {
+            OP_HASH160
+        }
+    }
+
+    fn mix_nonce(digest: &[u8], nonce: u64) -> Vec<u8> {
+        Self::hash(&[digest, nonce.to_le_bytes().as_slice()].concat())
+    }
+
+    fn mix_nonce_gadget() -> Script {
+        script! {
+            OP_SWAP OP_CAT OP_HASH160
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::algorithms::hasher::{
+        m31_to_script_num, DigestVar, Hash160ScriptHasher, ScriptHasher, Sha256ScriptHasher,
+    };
+    use bitcoin_circle_stark::treepp::*;
+    use bitcoin_script_dsl::builtins::m31::M31Var;
+    use bitcoin_script_dsl::bvar::AllocVar;
+    use bitcoin_script_dsl::constraint_system::ConstraintSystem;
+    use bitcoin_script_dsl::test_program;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha20Rng;
+    use stwo_prover::core::fields::m31::M31;
+
+    fn check_hash_leaf<H: ScriptHasher>(values: &[M31]) {
+        let cs = ConstraintSystem::new_ref();
+        let vars = values
+            .iter()
+            .map(|&v| M31Var::new_program_input(&cs, v).unwrap())
+            .collect::<Vec<_>>();
+
+        let digest_var = H::hash_leaf_var(&vars).unwrap();
+        assert_eq!(digest_var.digest(), H::hash_leaf(values));
+        assert_eq!(digest_var.digest().len(), H::DIGEST_LEN);
+
+        test_program(cs, script! {}).unwrap();
+    }
+
+    #[test]
+    fn test_hash_leaf() {
+        let mut prng = ChaCha20Rng::seed_from_u64(0);
+
+        // the values whose encoding is empty or needs a padding byte
+        let mut values = vec![M31::from(0), M31::from(0x80), M31::from(0x7fff_fffe)];
+        for _ in 0..5 {
+            values.push(M31::from(prng.gen_range(0..(1u32 << 31) - 1)));
+        }
+
+        for n in 1..=values.len() {
+            check_hash_leaf::<Sha256ScriptHasher>(&values[0..n]);
+            check_hash_leaf::<Hash160ScriptHasher>(&values[0..n]);
+        }
+    }
+
+    #[test]
+    fn test_m31_to_script_num() {
+        assert_eq!(m31_to_script_num(M31::from(0)), Vec::<u8>::new());
+        assert_eq!(m31_to_script_num(M31::from(1)), vec![1]);
+        assert_eq!(m31_to_script_num(M31::from(0x80)), vec![0x80, 0]);
+        assert_eq!(m31_to_script_num(M31::from(0x1234)), vec![0x34, 0x12]);
+        assert_eq!(
+            m31_to_script_num(M31::from(0x7fff_fffe)),
+            vec![0xfe, 0xff, 0xff, 0x7f]
+        );
+    }
+}
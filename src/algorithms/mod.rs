@@ -4,6 +4,10 @@ pub mod twin_tree;
 
 pub mod precomputed_tree;
 
+pub mod precomputed_tree_builder;
+
+pub mod domain_point;
+
 pub mod point;
 
 pub mod pair_vanishing;
@@ -13,3 +17,11 @@ pub mod column_line_coeffs;
 pub mod folding;
 
 pub mod quotient;
+
+pub mod div;
+
+pub mod hasher;
+
+pub mod channel;
+
+pub mod winternitz;
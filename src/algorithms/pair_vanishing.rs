@@ -1,19 +1,37 @@
+use crate::algorithms::div::DivVar;
 use crate::algorithms::point::SecureCirclePointVar;
+use anyhow::Result;
 use bitcoin_script_dsl::builtins::cm31::CM31Var;
 use bitcoin_script_dsl::builtins::table::TableVar;
 
 pub fn prepare_pair_vanishing(
     point: &SecureCirclePointVar,
     table: &TableVar,
-) -> (CM31Var, CM31Var) {
+) -> Result<(CM31Var, CM31Var)> {
+    Ok(
+        prepare_pair_vanishing_batch(std::slice::from_ref(point), table)?
+            .pop()
+            .unwrap(),
+    )
+}
+
+/// Prepare the pair vanishing for several points.
+pub fn prepare_pair_vanishing_batch(
+    points: &[SecureCirclePointVar],
+    table: &TableVar,
+) -> Result<Vec<(CM31Var, CM31Var)>> {
     // note: there are some overlapping regarding the extraction of `y_imag` and `y_real` between
     // this function and `column_line_coeffs` and they can be combined.
 
-    let y_second_inv = point.y.second.inverse(table);
-    let x_second_div_y_second = &point.x.second * (table, &y_second_inv);
+    let mut res = vec![];
+    for point in points.iter() {
+        let x_second_div_y_second = point.x.second.div(table, &point.y.second)?;
+
+        let mut cross_term = &x_second_div_y_second * (table, &point.y.first);
+        cross_term = &cross_term - &point.x.first;
 
-    let mut cross_term = &x_second_div_y_second * (table, &point.y.first);
-    cross_term = &cross_term - &point.x.first;
+        res.push((x_second_div_y_second, cross_term));
+    }
 
-    (x_second_div_y_second, cross_term)
+    Ok(res)
 }
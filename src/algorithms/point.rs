@@ -1,30 +1,180 @@
-use bitcoin_script_dsl::builtins::hash::HashVar;
+use crate::algorithms::channel::ChannelVar;
+use crate::algorithms::div::DivVar;
+use anyhow::Result;
 use bitcoin_script_dsl::builtins::m31::M31Var;
 use bitcoin_script_dsl::builtins::qm31::QM31Var;
 use bitcoin_script_dsl::builtins::table::TableVar;
 use bitcoin_script_dsl::bvar::{AllocVar, BVar};
-use stwo_prover::core::circle::CirclePoint;
+use num_traits::{One, Zero};
+use std::ops::Neg;
+use stwo_prover::core::circle::{CirclePoint, Coset};
 use stwo_prover::core::fields::m31::M31;
+use stwo_prover::core::fields::qm31::QM31;
 
+#[derive(Clone)]
 pub struct SecureCirclePointVar {
     pub x: QM31Var,
     pub y: QM31Var,
 }
 
-pub fn get_oods_point(hash: &mut HashVar, table: &TableVar) -> SecureCirclePointVar {
-    let t = hash.draw_felt();
+impl SecureCirclePointVar {
+    /// Add two points on the circle.
+    pub fn add(&self, table: &TableVar, rhs: &SecureCirclePointVar) -> SecureCirclePointVar {
+        // new x: x0 · x1 − y0 · y1
+        // new y: x0 · y1 + y0 · x1
+        // use Karatsuba
+
+        let x0x1 = &self.x * (table, &rhs.x);
+        let y0y1 = &self.y * (table, &rhs.y);
+
+        let x0_plus_y0 = &self.x + &self.y;
+        let x1_plus_y1 = &rhs.x + &rhs.y;
+
+        let all_terms = &x0_plus_y0 * (table, &x1_plus_y1);
+        let mut cross_terms = &all_terms - &x0x1;
+        cross_terms = &cross_terms - &y0y1;
+
+        let x = &x0x1 - &y0y1;
+        let y = cross_terms;
+
+        SecureCirclePointVar { x, y }
+    }
+
+    /// Add a constant point over M31 to this point.
+    pub fn add_constant(
+        &self,
+        table: &TableVar,
+        constant: CirclePoint<M31>,
+    ) -> SecureCirclePointVar {
+        add_constant_m31_point(self, table, constant)
+    }
+
+    /// Shift this point by `offset` steps of `step`, i.e., add `step.mul_signed(offset)`.
+    ///
+    /// This is how mask points are derived from the OODS point.
+    pub fn shift_by(
+        &self,
+        table: &TableVar,
+        step: CirclePoint<M31>,
+        offset: isize,
+    ) -> SecureCirclePointVar {
+        self.add_constant(table, step.mul_signed(offset))
+    }
+
+    /// Double the point.
+    pub fn double(&self, table: &TableVar) -> SecureCirclePointVar {
+        // new x: 2 · x^2 − 1
+        // new y: 2 · x · y
+        let x = Self::double_x(&self.x, table);
+
+        let xy = &self.x * (table, &self.y);
+        let y = &xy + &xy;
+
+        SecureCirclePointVar { x, y }
+    }
+
+    /// Double the point `n` times.
+    pub fn repeated_double(&self, table: &TableVar, n: u32) -> SecureCirclePointVar {
+        let mut res = self.clone();
+        for _ in 0..n {
+            res = res.double(table);
+        }
+        res
+    }
+
+    /// Compute the x coordinate of the doubled point from the x coordinate of the point.
+    pub fn double_x(x: &QM31Var, table: &TableVar) -> QM31Var {
+        let mut res = x * (table, x);
+        res = &res + &res;
+        res.sub1()
+    }
+
+    /// Compute the x coordinate of the point doubled `n` times.
+    pub fn repeated_double_x(x: &QM31Var, table: &TableVar, n: u32) -> QM31Var {
+        let mut res = x.clone();
+        for _ in 0..n {
+            res = Self::double_x(&res, table);
+        }
+        res
+    }
+
+    /// The conjugate of the point, `(x, -y)`, which is also its inverse in the circle group.
+    pub fn conjugate(&self) -> SecureCirclePointVar {
+        SecureCirclePointVar {
+            x: self.x.clone(),
+            y: -&self.y,
+        }
+    }
+
+    /// The antipode of the point, `(-x, -y)`.
+    pub fn antipode(&self) -> SecureCirclePointVar {
+        SecureCirclePointVar {
+            x: -&self.x,
+            y: -&self.y,
+        }
+    }
+
+    /// Multiply the point by a constant signed scalar, through double-and-add.
+    pub fn mul_signed(&self, table: &TableVar, off: isize) -> SecureCirclePointVar {
+        if off == 0 {
+            let cs = self.x.cs().and(&self.y.cs());
+            return SecureCirclePointVar {
+                x: QM31Var::new_constant(&cs, QM31::one()).unwrap(),
+                y: QM31Var::new_constant(&cs, QM31::zero()).unwrap(),
+            };
+        }
+
+        let base = if off > 0 {
+            self.clone()
+        } else {
+            self.conjugate()
+        };
+        let scalar = off.unsigned_abs();
+
+        let mut res = base.clone();
+        for i in (0..(usize::BITS - 1 - scalar.leading_zeros())).rev() {
+            res = res.double(table);
+            if (scalar >> i) & 1 == 1 {
+                res = res.add(table, &base);
+            }
+        }
+        res
+    }
+
+    /// Evaluate the vanishing polynomial of the coset at this point.
+    ///
+    /// It follows stwo's `coset_vanishing`: the point is rotated so that the coset becomes a
+    /// canonic coset, and then it is doubled `log_size - 1` times, only keeping track of x.
+    pub fn coset_vanishing(&self, table: &TableVar, coset: Coset) -> QM31Var {
+        let shift = -coset.initial + coset.step_size.half().to_point();
+        let x = add_constant_m31_point_x_only(self, table, shift);
+        Self::repeated_double_x(&x, table, coset.log_size - 1)
+    }
+}
+
+impl Neg for &SecureCirclePointVar {
+    type Output = SecureCirclePointVar;
+
+    fn neg(self) -> Self::Output {
+        self.conjugate()
+    }
+}
+
+pub fn get_oods_point<C: ChannelVar>(
+    channel: &mut C,
+    table: &TableVar,
+) -> Result<SecureCirclePointVar> {
+    let t = channel.draw_felt()?;
     let t_doubled = &t + &t;
     let t_squared = &t * (table, &t);
 
     let t_squared_plus_1 = t_squared.add1();
     let t_squared_minus_1 = t_squared.sub1();
 
-    let t_squared_plus_1_inverse = t_squared_plus_1.inverse(table);
+    let x = (-&t_squared_minus_1).div(table, &t_squared_plus_1)?;
+    let y = t_doubled.div(table, &t_squared_plus_1)?;
 
-    let x = &(-&t_squared_minus_1) * (table, &t_squared_plus_1_inverse);
-    let y = &t_doubled * (table, &t_squared_plus_1_inverse);
-
-    SecureCirclePointVar { x, y }
+    Ok(SecureCirclePointVar { x, y })
 }
 
 pub fn add_constant_m31_point_x_only(
@@ -77,3 +227,86 @@ pub fn add_constant_m31_point(
 
     SecureCirclePointVar { x, y }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::algorithms::point::SecureCirclePointVar;
+    use bitcoin_circle_stark::treepp::*;
+    use bitcoin_script_dsl::builtins::qm31::QM31Var;
+    use bitcoin_script_dsl::builtins::table::TableVar;
+    use bitcoin_script_dsl::bvar::AllocVar;
+    use bitcoin_script_dsl::constraint_system::{ConstraintSystem, ConstraintSystemRef};
+    use bitcoin_script_dsl::test_program;
+    use stwo_prover::core::channel::{Channel, Sha256Channel};
+    use stwo_prover::core::circle::CirclePoint;
+    use stwo_prover::core::constraints::coset_vanishing;
+    use stwo_prover::core::fields::qm31::SecureField;
+    use stwo_prover::core::poly::circle::CanonicCoset;
+
+    fn alloc_point(cs: &ConstraintSystemRef, p: CirclePoint<SecureField>) -> SecureCirclePointVar {
+        SecureCirclePointVar {
+            x: QM31Var::new_program_input(cs, p.x).unwrap(),
+            y: QM31Var::new_program_input(cs, p.y).unwrap(),
+        }
+    }
+
+    fn check_point(
+        cs: &ConstraintSystemRef,
+        point_var: &SecureCirclePointVar,
+        expected: CirclePoint<SecureField>,
+    ) {
+        point_var
+            .x
+            .equalverify(&QM31Var::new_constant(cs, expected.x).unwrap())
+            .unwrap();
+        point_var
+            .y
+            .equalverify(&QM31Var::new_constant(cs, expected.y).unwrap())
+            .unwrap();
+    }
+
+    #[test]
+    fn test_point_operations() {
+        let mut channel = Sha256Channel::default();
+
+        for i in 0..5 {
+            channel.mix_nonce(i);
+            let a = CirclePoint::<SecureField>::get_random_point(&mut channel);
+            let b = CirclePoint::<SecureField>::get_random_point(&mut channel);
+
+            let cs = ConstraintSystem::new_ref();
+            let table = TableVar::new_constant(&cs, ()).unwrap();
+
+            let a_var = alloc_point(&cs, a);
+            let b_var = alloc_point(&cs, b);
+
+            check_point(&cs, &a_var.add(&table, &b_var), a + b);
+            check_point(&cs, &a_var.double(&table), a.double());
+            check_point(&cs, &a_var.repeated_double(&table, 3), a.repeated_double(3));
+            check_point(&cs, &a_var.conjugate(), a.conjugate());
+            check_point(&cs, &-&a_var, -a);
+            check_point(&cs, &a_var.antipode(), a.antipode());
+
+            for off in [-5, -1, 0, 1, 2, 7] {
+                check_point(&cs, &a_var.mul_signed(&table, off), a.mul_signed(off));
+            }
+
+            let trace_step = CanonicCoset::new(5).step();
+            for off in [-2, -1, 1] {
+                check_point(
+                    &cs,
+                    &a_var.shift_by(&table, trace_step, off),
+                    a + trace_step.mul_signed(off).into_ef(),
+                );
+            }
+
+            let coset = CanonicCoset::new(5).coset;
+            a_var
+                .coset_vanishing(&table, coset)
+                .equalverify(&QM31Var::new_constant(&cs, coset_vanishing(coset, a)).unwrap())
+                .unwrap();
+
+            test_program(cs, script! {}).unwrap();
+        }
+    }
+}
@@ -1,58 +1,197 @@
+use crate::algorithms::hasher::{DigestVar, ScriptHasher, Sha256ScriptHasher};
 use anyhow::Error;
-use bitcoin_circle_stark::channel::Sha256ChannelGadget;
-use bitcoin_circle_stark::pow::PoWHint;
 use bitcoin_circle_stark::treepp::*;
 use bitcoin_script_dsl::builtins::hash::HashVar;
 use bitcoin_script_dsl::builtins::str::StrVar;
 use bitcoin_script_dsl::bvar::{AllocVar, BVar};
+use bitcoin_script_dsl::constraint_system::ConstraintSystemRef;
 use bitcoin_script_dsl::options::Options;
 use bitcoin_script_dsl::stack::Stack;
-use stwo_prover::core::channel::{Channel, Sha256Channel};
-use stwo_prover::core::vcs::sha256_hash::Sha256Hash;
 
+/// How the nonce is encoded when it is mixed into the channel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NonceEncoding {
+    /// 8 bytes in little-endian, mixed by `Channel::mix_nonce`, which is what stwo uses.
+    U64Le,
+    /// 4 bytes in little-endian, mixed by hashing the digest followed by the nonce.
+    U32Le,
+}
+
+impl NonceEncoding {
+    /// The number of bytes of the encoded nonce.
+    pub fn num_bytes(&self) -> usize {
+        match self {
+            NonceEncoding::U64Le => 8,
+            NonceEncoding::U32Le => 4,
+        }
+    }
+
+    fn from_len(len: usize) -> anyhow::Result<Self> {
+        match len {
+            8 => Ok(NonceEncoding::U64Le),
+            4 => Ok(NonceEncoding::U32Le),
+            _ => Err(Error::msg("Unsupported nonce length")),
+        }
+    }
+
+    /// The largest nonce that can be encoded.
+    pub fn max_nonce(&self) -> u64 {
+        match self {
+            NonceEncoding::U64Le => u64::MAX,
+            NonceEncoding::U32Le => u32::MAX as u64,
+        }
+    }
+
+    pub fn encode(&self, nonce: u64) -> Vec<u8> {
+        assert!(nonce <= self.max_nonce());
+        nonce.to_le_bytes()[0..self.num_bytes()].to_vec()
+    }
+
+    /// Mix the nonce into a channel digest computed with `H` natively.
+    pub fn mix<H: ScriptHasher>(&self, digest: &[u8], nonce: u64) -> Vec<u8> {
+        match self {
+            NonceEncoding::U64Le => H::mix_nonce(digest, nonce),
+            NonceEncoding::U32Le => H::mix_nonce_bytes(digest, &self.encode(nonce)),
+        }
+    }
+
+    fn mix_gadget<H: ScriptHasher>(&self) -> Script {
+        match self {
+            NonceEncoding::U64Le => H::mix_nonce_gadget(),
+            NonceEncoding::U32Le => H::mix_nonce_bytes_gadget(),
+        }
+    }
+}
+
+/// The number of zero bits at the end of the digest, as the script checks it, i.e., the zero bytes
+/// at the end, followed by the leading zero bits of the byte before them.
+pub fn trailing_zero_bits(digest: &[u8]) -> u32 {
+    let num_zero_bytes = digest.iter().rev().take_while(|&&v| v == 0).count();
+    let rest = digest.len() - num_zero_bytes;
+
+    num_zero_bytes as u32 * 8
+        + if rest > 0 {
+            digest[rest - 1].leading_zeros()
+        } else {
+            0
+        }
+}
+
+/// Whether the digest of a channel with `H` satisfies a proof of work of `n_bits`.
+pub fn satisfies_pow<H: ScriptHasher>(digest: &[u8], n_bits: u32) -> bool {
+    H::trailing_zeros(digest) >= n_bits
+}
+
+/// Find the smallest nonce that passes the proof of work at the given point of the transcript,
+/// which is what the prover does when it grinds.
+pub fn grind<H: ScriptHasher>(digest: &[u8], n_bits: u32, encoding: NonceEncoding) -> u64 {
+    (0..=encoding.max_nonce())
+        .find(|&nonce| satisfies_pow::<H>(&encoding.mix::<H>(digest, nonce), n_bits))
+        .expect("no nonce satisfies the proof of work")
+}
+
+/// Verify the proof of work of stwo, where the nonce is mixed into the channel with
+/// `Channel::mix_nonce`.
 pub fn verify_pow(hash: &mut HashVar, n_bits: u32, nonce: u64) -> anyhow::Result<()> {
-    let old_channel_digest = hash.value.to_vec();
-    let pow_hint = PoWHint::new(
-        Sha256Hash::from(old_channel_digest.as_slice()),
+    verify_pow_with_encoding(hash, n_bits, nonce, NonceEncoding::U64Le)
+}
+
+/// Verify a grinding step at the current point of the transcript, e.g., after the interaction
+/// commitment, and update the channel with the nonce.
+pub fn verify_pow_with_encoding(
+    hash: &mut HashVar,
+    n_bits: u32,
+    nonce: u64,
+    encoding: NonceEncoding,
+) -> anyhow::Result<()> {
+    let cs = hash.cs();
+    let new_digest = insert_pow_check::<Sha256ScriptHasher>(
+        &hash.value,
+        hash.variables(),
+        &cs,
+        n_bits,
         nonce,
+        encoding,
+    )?;
+    *hash = HashVar::new_function_output(&cs, new_digest)?;
+    Ok(())
+}
+
+/// Verify the proof of work on a channel whose digest is computed with `H`, and return the new
+/// digest.
+///
+/// The new digest must end with `n_bits` zero bits, which is how the script checks it.
+pub fn verify_pow_with_hasher<H: ScriptHasher>(
+    digest: &H::Var,
+    n_bits: u32,
+    nonce: u64,
+    encoding: NonceEncoding,
+) -> anyhow::Result<H::Var> {
+    let cs = digest.cs();
+    let new_digest = insert_pow_check::<H>(
+        digest.digest(),
+        digest.variables(),
+        &cs,
         n_bits,
-    );
+        nonce,
+        encoding,
+    )?;
+    H::Var::new_digest_function_output(&cs, new_digest)
+}
 
-    let mut channel = Sha256Channel::default();
-    channel.update_digest(Sha256Hash::from(old_channel_digest));
-    channel.mix_nonce(nonce);
-    if channel.trailing_zeros() < n_bits {
+fn insert_pow_check<H: ScriptHasher>(
+    digest: &[u8],
+    digest_variables: Vec<usize>,
+    cs: &ConstraintSystemRef,
+    n_bits: u32,
+    nonce: u64,
+    encoding: NonceEncoding,
+) -> anyhow::Result<Vec<u8>> {
+    assert!(n_bits > 0);
+
+    if nonce > encoding.max_nonce() {
+        return Err(Error::msg("The nonce does not fit in the encoding"));
+    }
+
+    let new_digest = encoding.mix::<H>(digest, nonce);
+    if H::trailing_zeros(&new_digest) < n_bits {
         return Err(Error::msg("The proof of work requirement is not satisfied"));
     }
 
-    let cs = hash.cs();
+    let num_prefix_bytes = H::DIGEST_LEN - ((n_bits + 7) / 8) as usize;
+    let msb = if n_bits % 8 != 0 {
+        new_digest[num_prefix_bytes]
+    } else {
+        0
+    };
 
-    let nonce_var = StrVar::new_hint(&cs, pow_hint.nonce.to_le_bytes().to_vec())?;
-    let prefix_var = StrVar::new_hint(&cs, pow_hint.prefix)?;
-    let msb_var = StrVar::new_hint(&cs, vec![pow_hint.msb.unwrap_or_default()])?;
+    let nonce_var = StrVar::new_hint(cs, encoding.encode(nonce))?;
+    let prefix_var = StrVar::new_hint(cs, new_digest[0..num_prefix_bytes].to_vec())?;
+    let msb_var = StrVar::new_hint(cs, vec![msb])?;
     // if msb is not required, still push a stack element to make sure that the max stack consumption
     // is data-independent
 
     cs.insert_script_complex(
-        verify_pow_gadget,
-        hash.variables()
+        verify_pow_gadget::<H>,
+        digest_variables
             .iter()
             .chain(nonce_var.variables().iter())
             .chain(prefix_var.variables().iter())
             .chain(msb_var.variables().iter())
             .copied(),
-        &Options::new().with_u32("n_bits", n_bits),
+        &Options::new()
+            .with_u32("n_bits", n_bits)
+            .with_u32("nonce_len", encoding.num_bytes() as u32),
     )?;
 
-    *hash = HashVar::new_function_output(&cs, channel.digest().as_ref().to_vec())?;
-
-    Ok(())
+    Ok(new_digest)
 }
 
-fn verify_pow_gadget(_: &mut Stack, options: &Options) -> anyhow::Result<Script> {
+fn verify_pow_gadget<H: ScriptHasher>(_: &mut Stack, options: &Options) -> anyhow::Result<Script> {
     let n_bits = options.get_u32("n_bits")?;
     assert!(n_bits > 0);
     let n_bits = n_bits as usize;
+    let encoding = NonceEncoding::from_len(options.get_u32("nonce_len")? as usize)?;
 
     // NOTE: nonce should not be assumed to be a constant in the script.
     Ok(script! {
@@ -66,11 +205,11 @@ fn verify_pow_gadget(_: &mut Stack, options: &Options) -> anyhow::Result<Script>
         2 OP_ROLL
 
         // check the length of the nonce
-        OP_SIZE 8 OP_EQUALVERIFY
+        OP_SIZE { encoding.num_bytes() } OP_EQUALVERIFY
 
         // mix the nonce
         3 OP_ROLL
-        { Sha256ChannelGadget::mix_nonce() }
+        { encoding.mix_gadget::<H>() }
 
         // stack:
         // - prefix
@@ -81,7 +220,7 @@ fn verify_pow_gadget(_: &mut Stack, options: &Options) -> anyhow::Result<Script>
         2 OP_ROLL
 
         // check the length of the prefix
-        OP_SIZE { 32 - ((n_bits  + 7) / 8) } OP_EQUALVERIFY
+        OP_SIZE { H::DIGEST_LEN - ((n_bits  + 7) / 8) } OP_EQUALVERIFY
 
         // if msb is present, check the msb is small enough,
         // and if it is a zero, make it `0x00`
@@ -114,3 +253,166 @@ fn verify_pow_gadget(_: &mut Stack, options: &Options) -> anyhow::Result<Script>
         // drop the dummy msb element if it is not needed
     })
 }
+
+#[cfg(test)]
+mod test {
+    use crate::algorithms::hasher::{DigestVar, ScriptHasher, Sha256ScriptHasher};
+    use crate::algorithms::pow::{
+        grind, satisfies_pow, trailing_zero_bits, verify_pow_with_encoding, verify_pow_with_hasher,
+        NonceEncoding,
+    };
+    use bitcoin_circle_stark::treepp::*;
+    use bitcoin_script_dsl::builtins::hash::HashVar;
+    use bitcoin_script_dsl::builtins::str::StrVar;
+    use bitcoin_script_dsl::bvar::AllocVar;
+    use bitcoin_script_dsl::constraint_system::ConstraintSystem;
+    use bitcoin_script_dsl::test_program;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha20Rng;
+    use sha2::{Digest, Sha256};
+
+    /// A hasher whose new digest ends with the nonce, so that a proof of work of any number of
+    /// bits can be found without grinding.
+    struct NonceSuffixHasher;
+
+    impl ScriptHasher for NonceSuffixHasher {
+        const NAME: &'static str = "nonce-suffix";
+        const DIGEST_LEN: usize = 40;
+
+        type Var = StrVar;
+
+        fn hash(data: &[u8]) -> Vec<u8> {
+            Sha256::digest(data).to_vec()
+        }
+
+        fn hash_gadget() -> Script {
+            script! {
+                OP_SHA256
+            }
+        }
+
+        fn mix_nonce(digest: &[u8], nonce: u64) -> Vec<u8> {
+            [
+                Self::hash(digest).as_slice(),
+                nonce.to_le_bytes().as_slice(),
+            ]
+            .concat()
+        }
+
+        fn mix_nonce_gadget() -> Script {
+            script! {
+                OP_SHA256 OP_SWAP OP_CAT
+            }
+        }
+
+        fn mix_nonce_bytes(digest: &[u8], nonce: &[u8]) -> Vec<u8> {
+            [Self::hash(digest).as_slice(), nonce, nonce].concat()
+        }
+
+        fn mix_nonce_bytes_gadget() -> Script {
+            script! {
+                OP_SHA256 OP_SWAP OP_DUP OP_CAT OP_CAT
+            }
+        }
+    }
+
+    fn check<H: ScriptHasher>(digest: Vec<u8>, n_bits: u32, nonce: u64, encoding: NonceEncoding) {
+        let cs = ConstraintSystem::new_ref();
+        let digest_var = H::Var::new_digest_hint(&cs, digest).unwrap();
+
+        let new_digest_var =
+            verify_pow_with_hasher::<H>(&digest_var, n_bits, nonce, encoding).unwrap();
+        assert_eq!(new_digest_var.digest().len(), H::DIGEST_LEN);
+        assert!(satisfies_pow::<H>(new_digest_var.digest(), n_bits));
+
+        test_program(cs, script! {}).unwrap();
+    }
+
+    #[test]
+    fn test_pow_all_bit_counts() {
+        let mut prng = ChaCha20Rng::seed_from_u64(0);
+
+        for n_bits in 1..=32 {
+            for encoding in [NonceEncoding::U64Le, NonceEncoding::U32Le] {
+                let digest = (0..32).map(|_| prng.gen()).collect::<Vec<u8>>();
+
+                // the top `n_bits` bits of the nonce end up at the end of the digest
+                let nonce = prng.gen_range(0..1u64 << (encoding.num_bytes() as u32 * 8 - n_bits));
+                check::<NonceSuffixHasher>(digest, n_bits, nonce, encoding);
+            }
+        }
+    }
+
+    #[test]
+    fn test_trailing_zeros_match_stwo() {
+        let mut prng = ChaCha20Rng::seed_from_u64(0);
+
+        // what the script checks is what `Sha256Channel::trailing_zeros` counts
+        for n_bits in 0..=64 {
+            for _ in 0..8 {
+                let mut digest = (0..32).map(|_| prng.gen()).collect::<Vec<u8>>();
+                for bit in 0..n_bits {
+                    digest[31 - bit / 8] &= !(0x80 >> (bit % 8));
+                }
+
+                assert!(trailing_zero_bits(&digest) >= n_bits as u32);
+                assert_eq!(
+                    Sha256ScriptHasher::trailing_zeros(&digest),
+                    trailing_zero_bits(&digest)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_pow_sha256() {
+        let mut prng = ChaCha20Rng::seed_from_u64(0);
+
+        for n_bits in 1..=20 {
+            for encoding in [NonceEncoding::U64Le, NonceEncoding::U32Le] {
+                let digest = (0..32).map(|_| prng.gen()).collect::<Vec<u8>>();
+                let nonce = grind::<Sha256ScriptHasher>(&digest, n_bits, encoding);
+                check::<Sha256ScriptHasher>(digest.clone(), n_bits, nonce, encoding);
+
+                // a nonce with too little work is rejected
+                let bad_nonce = (0..)
+                    .find(|&nonce| {
+                        !satisfies_pow::<Sha256ScriptHasher>(
+                            &encoding.mix::<Sha256ScriptHasher>(&digest, nonce),
+                            n_bits,
+                        )
+                    })
+                    .unwrap();
+                let cs = ConstraintSystem::new_ref();
+                let digest_var = HashVar::new_hint(&cs, digest).unwrap();
+                assert!(verify_pow_with_hasher::<Sha256ScriptHasher>(
+                    &digest_var,
+                    n_bits,
+                    bad_nonce,
+                    encoding
+                )
+                .is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn test_pow_in_the_channel() {
+        let mut prng = ChaCha20Rng::seed_from_u64(0);
+
+        // a grinding step in the middle of the transcript, after mixing a commitment
+        let cs = ConstraintSystem::new_ref();
+        let mut channel_var = HashVar::new_constant(&cs, vec![0u8; 32]).unwrap();
+        let commitment_var =
+            HashVar::new_hint(&cs, (0..32).map(|_| prng.gen()).collect::<Vec<u8>>()).unwrap();
+        channel_var = &channel_var + &commitment_var;
+
+        let nonce = grind::<Sha256ScriptHasher>(&channel_var.value, 10, NonceEncoding::U64Le);
+        verify_pow_with_encoding(&mut channel_var, 10, nonce, NonceEncoding::U64Le).unwrap();
+        assert!(satisfies_pow::<Sha256ScriptHasher>(&channel_var.value, 10));
+
+        let _ = channel_var.draw_felt();
+
+        test_program(cs, script! {}).unwrap();
+    }
+}
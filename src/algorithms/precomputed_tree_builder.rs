@@ -0,0 +1,325 @@
+use anyhow::{Error, Result};
+use bitcoin_circle_stark::precomputed_merkle_tree::{
+    get_precomputed_merkle_tree_roots, PrecomputedMerkleTree, PRECOMPUTED_MERKLE_TREE_ROOTS,
+};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// The smallest log size of a precomputed tree.
+pub const MIN_PRECOMPUTED_TREE_LOG_SIZE: usize = 5;
+
+/// The largest log size of a precomputed tree.
+///
+/// The hints need the whole tree in memory, which has 2^(log size + 1) hashes, i.e., 256 MiB at
+/// this log size. Larger trees are rejected rather than built: at log size 28, the tree would
+/// take 16 GiB.
+pub const MAX_PRECOMPUTED_TREE_LOG_SIZE: usize = 22;
+
+/// Builds the precomputed trees of the circle points and the twiddles, and keeps them.
+///
+/// The scripts only need the root of a tree, while the hints need the whole tree. Building a
+/// large tree takes a while, so the trees are kept in memory once built, and the roots are also
+/// cached on disk if a cache directory is set. The trees themselves are not written to disk, since
+/// a tree read from the disk could only be trusted after building it again.
+///
+/// A new tree is checked against the roots that come with `bitcoin_circle_stark` whenever they
+/// cover the log size, and against the cached root if there is one, so a corrupted cache fails
+/// the next build.
+pub struct PrecomputedTreeBuilder {
+    cache_dir: Option<PathBuf>,
+    roots: Mutex<BTreeMap<usize, [u8; 32]>>,
+    trees: Mutex<BTreeMap<usize, Arc<PrecomputedMerkleTree>>>,
+}
+
+impl PrecomputedTreeBuilder {
+    /// A builder that only caches in memory.
+    pub fn new() -> Self {
+        Self {
+            cache_dir: None,
+            roots: Mutex::new(BTreeMap::new()),
+            trees: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// A builder that also caches the roots in the directory.
+    pub fn with_cache_dir(cache_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            cache_dir: Some(cache_dir.into()),
+            ..Self::new()
+        }
+    }
+
+    /// Build the tree of the log size, check its root, and cache the root.
+    pub fn build_tree(&self, log_size: usize) -> Result<PrecomputedMerkleTree> {
+        check_log_size(log_size)?;
+
+        let tree = PrecomputedMerkleTree::new(log_size);
+        let root = root_of(&tree)?;
+
+        self.check_known_root(log_size, &root)?;
+        self.check_cached_root(log_size, &root)?;
+        self.store_root(log_size, root)?;
+
+        Ok(tree)
+    }
+
+    /// The tree of the log size, which is built only if it is not in memory yet.
+    pub fn tree(&self, log_size: usize) -> Result<Arc<PrecomputedMerkleTree>> {
+        if let Some(tree) = self.trees.lock().unwrap().get(&log_size) {
+            return Ok(tree.clone());
+        }
+
+        let tree = Arc::new(self.build_tree(log_size)?);
+        self.trees.lock().unwrap().insert(log_size, tree.clone());
+        Ok(tree)
+    }
+
+    /// The root of the tree of the log size, which is built only if it is not cached.
+    pub fn root(&self, log_size: usize) -> Result<[u8; 32]> {
+        check_log_size(log_size)?;
+
+        if let Some(root) = self.roots.lock().unwrap().get(&log_size) {
+            return Ok(*root);
+        }
+
+        if let Some(root) = known_roots().get(&log_size) {
+            self.roots.lock().unwrap().insert(log_size, *root);
+            return Ok(*root);
+        }
+
+        if let Some(root) = self.load_root(log_size)? {
+            self.roots.lock().unwrap().insert(log_size, root);
+            return Ok(root);
+        }
+
+        let tree = self.build_tree(log_size)?;
+        root_of(&tree)
+    }
+
+    /// The root of the tree over a domain of the size, which must be a power of two.
+    pub fn root_for_domain_size(&self, domain_size: usize) -> Result<[u8; 32]> {
+        self.root(log_size_of_domain(domain_size)?)
+    }
+
+    /// Rebuild the tree of the log size and check that it matches the cached root.
+    pub fn verify_cached_root(&self, log_size: usize) -> Result<()> {
+        let cached = self.root(log_size)?;
+        let root = root_of(&PrecomputedMerkleTree::new(log_size))?;
+        if cached != root {
+            return Err(Error::msg(format!(
+                "The cached root of the precomputed tree of log size {} is wrong",
+                log_size
+            )));
+        }
+        Ok(())
+    }
+
+    fn check_known_root(&self, log_size: usize, root: &[u8; 32]) -> Result<()> {
+        match known_roots().get(&log_size) {
+            Some(known) if known != root => Err(Error::msg(format!(
+                "The precomputed tree of log size {} does not match the known root",
+                log_size
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    fn check_cached_root(&self, log_size: usize, root: &[u8; 32]) -> Result<()> {
+        let cached = match self.roots.lock().unwrap().get(&log_size) {
+            Some(cached) => Some(*cached),
+            None => self.load_root(log_size)?,
+        };
+        match cached {
+            Some(cached) if cached != *root => Err(Error::msg(format!(
+                "The cached root of the precomputed tree of log size {} is wrong",
+                log_size
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    fn cache_path(&self, log_size: usize) -> Option<PathBuf> {
+        self.cache_dir
+            .as_ref()
+            .map(|dir| dir.join(format!("precomputed-tree-root-{}.txt", log_size)))
+    }
+
+    fn load_root(&self, log_size: usize) -> Result<Option<[u8; 32]>> {
+        let Some(path) = self.cache_path(log_size) else {
+            return Ok(None);
+        };
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let root = hex::decode(std::fs::read_to_string(&path)?.trim())?;
+        let root: [u8; 32] = root.try_into().map_err(|_| {
+            Error::msg(format!(
+                "The cached root in {} is not 32 bytes",
+                path.display()
+            ))
+        })?;
+        Ok(Some(root))
+    }
+
+    fn store_root(&self, log_size: usize, root: [u8; 32]) -> Result<()> {
+        self.roots.lock().unwrap().insert(log_size, root);
+
+        if let Some(path) = self.cache_path(log_size) {
+            std::fs::create_dir_all(path.parent().unwrap())?;
+            std::fs::write(path, hex::encode(root))?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for PrecomputedTreeBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+static PRECOMPUTED_TREE_BUILDER: OnceLock<PrecomputedTreeBuilder> = OnceLock::new();
+
+/// The environment variable with the directory in which the shared builder caches the roots.
+pub const PRECOMPUTED_TREE_CACHE_DIR_VAR: &str = "PRECOMPUTED_TREE_CACHE_DIR";
+
+/// The builder that the hints and the steps share, which caches the roots in the directory in
+/// `PRECOMPUTED_TREE_CACHE_DIR_VAR` when it is set, and only in memory otherwise.
+pub fn precomputed_tree_builder() -> &'static PrecomputedTreeBuilder {
+    PRECOMPUTED_TREE_BUILDER.get_or_init(|| {
+        match std::env::var_os(PRECOMPUTED_TREE_CACHE_DIR_VAR) {
+            Some(cache_dir) => PrecomputedTreeBuilder::with_cache_dir(cache_dir),
+            None => PrecomputedTreeBuilder::new(),
+        }
+    })
+}
+
+/// The root of the precomputed tree of the log size.
+pub fn precomputed_tree_root(log_size: usize) -> Result<[u8; 32]> {
+    precomputed_tree_builder().root(log_size)
+}
+
+/// The log size of a domain, which must be a power of two within the supported range.
+pub fn log_size_of_domain(domain_size: usize) -> Result<usize> {
+    if !domain_size.is_power_of_two() {
+        return Err(Error::msg("The domain size is not a power of two"));
+    }
+    let log_size = domain_size.trailing_zeros() as usize;
+    check_log_size(log_size)?;
+    Ok(log_size)
+}
+
+fn check_log_size(log_size: usize) -> Result<()> {
+    if !(MIN_PRECOMPUTED_TREE_LOG_SIZE..=MAX_PRECOMPUTED_TREE_LOG_SIZE).contains(&log_size) {
+        return Err(Error::msg(format!(
+            "The log size {} of the precomputed tree is not in [{}, {}]",
+            log_size, MIN_PRECOMPUTED_TREE_LOG_SIZE, MAX_PRECOMPUTED_TREE_LOG_SIZE
+        )));
+    }
+    Ok(())
+}
+
+fn known_roots() -> &'static std::collections::HashMap<usize, [u8; 32]> {
+    PRECOMPUTED_MERKLE_TREE_ROOTS.get_or_init(get_precomputed_merkle_tree_roots)
+}
+
+/// The root is the only node in the layer of a single node.
+fn root_of(tree: &PrecomputedMerkleTree) -> Result<[u8; 32]> {
+    tree.layers
+        .iter()
+        .find(|layer| layer.len() == 1)
+        .map(|layer| layer[0])
+        .ok_or_else(|| Error::msg("The precomputed tree has no root"))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::algorithms::precomputed_tree_builder::{
+        log_size_of_domain, PrecomputedTreeBuilder, MAX_PRECOMPUTED_TREE_LOG_SIZE,
+        MIN_PRECOMPUTED_TREE_LOG_SIZE,
+    };
+    use bitcoin_circle_stark::precomputed_merkle_tree::PrecomputedMerkleTree;
+    use std::sync::Arc;
+
+    fn check_opening(builder: &PrecomputedTreeBuilder, log_size: usize) {
+        let tree = builder.build_tree(log_size).unwrap();
+        let root = builder.root(log_size).unwrap();
+
+        let pos = (2 << log_size) - 6;
+        let proof = tree.query(pos);
+        assert!(PrecomputedMerkleTree::verify(
+            root,
+            proof.siblings.len(),
+            &proof,
+            pos
+        ));
+    }
+
+    #[test]
+    fn test_precomputed_tree_builder() {
+        let cache_dir = std::env::temp_dir().join(format!(
+            "precomputed-tree-builder-test-{}",
+            std::process::id()
+        ));
+
+        let builder = PrecomputedTreeBuilder::with_cache_dir(&cache_dir);
+        for log_size in MIN_PRECOMPUTED_TREE_LOG_SIZE..=8 {
+            let tree = builder.build_tree(log_size).unwrap();
+            let root = builder.root(log_size).unwrap();
+
+            let pos = 6;
+            let proof = tree.query(pos);
+            assert!(PrecomputedMerkleTree::verify(
+                root,
+                proof.siblings.len(),
+                &proof,
+                pos
+            ));
+
+            assert_eq!(builder.root_for_domain_size(1 << log_size).unwrap(), root);
+        }
+
+        // a new builder reads the roots from the disk
+        let builder = PrecomputedTreeBuilder::with_cache_dir(&cache_dir);
+        for log_size in MIN_PRECOMPUTED_TREE_LOG_SIZE..=8 {
+            builder.verify_cached_root(log_size).unwrap();
+        }
+
+        // a corrupted root on the disk fails the verification and the next build
+        let path = cache_dir.join("precomputed-tree-root-7.txt");
+        std::fs::write(&path, hex::encode([0u8; 32])).unwrap();
+        let builder = PrecomputedTreeBuilder::with_cache_dir(&cache_dir);
+        assert!(builder.verify_cached_root(7).is_err());
+        assert!(builder.build_tree(7).is_err());
+        assert!(builder.tree(7).is_err());
+
+        // a tree is built once and then kept in memory
+        let builder = PrecomputedTreeBuilder::new();
+        let tree = builder.tree(8).unwrap();
+        assert!(Arc::ptr_eq(&tree, &builder.tree(8).unwrap()));
+
+        // the log size of the verifier, for which the roots that come with `bitcoin_circle_stark`
+        // are checked when they cover the log size
+        for log_size in [15, 16] {
+            check_opening(&builder, log_size);
+        }
+
+        assert!(log_size_of_domain(1 << (MIN_PRECOMPUTED_TREE_LOG_SIZE - 1)).is_err());
+        assert!(log_size_of_domain(1 << (MAX_PRECOMPUTED_TREE_LOG_SIZE + 1)).is_err());
+        assert!(log_size_of_domain(1 << 28).is_err());
+        assert!(log_size_of_domain(48).is_err());
+
+        std::fs::remove_dir_all(&cache_dir).unwrap();
+    }
+
+    #[test]
+    #[ignore = "builds a tree of 256 MiB"]
+    fn test_precomputed_tree_builder_largest() {
+        check_opening(
+            &PrecomputedTreeBuilder::new(),
+            MAX_PRECOMPUTED_TREE_LOG_SIZE,
+        );
+    }
+}
@@ -1,6 +1,11 @@
+use anyhow::Result;
 use bitcoin_script_dsl::builtins::cm31::CM31Var;
 use bitcoin_script_dsl::builtins::m31::M31Var;
+use bitcoin_script_dsl::builtins::qm31::QM31Var;
 use bitcoin_script_dsl::builtins::table::TableVar;
+use bitcoin_script_dsl::bvar::{AllocVar, BVar};
+use num_traits::Zero;
+use stwo_prover::core::fields::cm31::CM31;
 
 pub fn apply_twin(
     table: &TableVar,
@@ -25,14 +30,73 @@ pub fn denominator_inverse_from_prepared(
     z_x: &M31Var,
     z_y: &M31Var,
 ) -> (CM31Var, CM31Var) {
-    let cross_term_plus_z_x = cross_term + z_x;
-    let x_second_div_y_second_times_z_y = x_second_div_y_second * (table, z_y);
+    denominator_inverses_from_prepared(
+        table,
+        &[(x_second_div_y_second.clone(), cross_term.clone())],
+        z_x,
+        z_y,
+    )
+    .pop()
+    .unwrap()
+}
+
+/// Compute the denominator inverses of several sample points (given their prepared pair
+/// vanishing) at the queried point and its conjugate.
+///
+/// Each inverse is a hint checked with a single multiplication, so Montgomery's trick, which
+/// trades the inversions for `3(n - 1)` multiplications, would only make the script larger.
+pub fn denominator_inverses_from_prepared(
+    table: &TableVar,
+    prepared: &[(CM31Var, CM31Var)],
+    z_x: &M31Var,
+    z_y: &M31Var,
+) -> Vec<(CM31Var, CM31Var)> {
+    let mut inverses = vec![];
+    for (x_second_div_y_second, cross_term) in prepared.iter() {
+        let cross_term_plus_z_x = cross_term + z_x;
+        let x_second_div_y_second_times_z_y = x_second_div_y_second * (table, z_y);
+
+        let result_for_z = &cross_term_plus_z_x - &x_second_div_y_second_times_z_y;
+        let result_for_conjugated_z = &cross_term_plus_z_x + &x_second_div_y_second_times_z_y;
+
+        inverses.push((
+            result_for_z.inverse(table),
+            result_for_conjugated_z.inverse(table),
+        ));
+    }
+
+    inverses
+}
+
+/// Combine the numerators of consecutive columns with powers of alpha, where the last column
+/// gets alpha^0, the one before it gets alpha^1, and so on.
+///
+/// `alpha_powers[i]` is expected to be alpha^(i + 1).
+pub fn combine_numerators(
+    table: &TableVar,
+    alpha_powers: &[QM31Var],
+    numerators: &[CM31Var],
+) -> Result<QM31Var> {
+    assert!(!numerators.is_empty());
+    assert!(alpha_powers.len() + 1 >= numerators.len());
 
-    let result_for_z = &cross_term_plus_z_x - &x_second_div_y_second_times_z_y;
-    let result_for_conjugated_z = &cross_term_plus_z_x + &x_second_div_y_second_times_z_y;
+    let n = numerators.len();
 
-    let inverse_result_for_z = result_for_z.inverse(table);
-    let inverse_result_for_conjugated_z = result_for_conjugated_z.inverse(table);
+    let mut sum: Option<QM31Var> = None;
+    for (i, numerator) in numerators.iter().enumerate().take(n - 1) {
+        let term = &alpha_powers[n - 2 - i] * (table, numerator);
+        sum = Some(match sum {
+            None => term,
+            Some(sum) => &sum + &term,
+        });
+    }
 
-    (inverse_result_for_z, inverse_result_for_conjugated_z)
+    let last = &numerators[n - 1];
+    Ok(match sum {
+        None => QM31Var {
+            first: last.clone(),
+            second: CM31Var::new_constant(&last.cs(), CM31::zero())?,
+        },
+        Some(sum) => &sum + last,
+    })
 }
@@ -1,17 +1,23 @@
+use crate::algorithms::hasher::{DigestVar, ScriptHasher};
 use anyhow::Error;
 use anyhow::Result;
 use bitcoin_circle_stark::merkle_tree::MerkleTreeTwinProof;
 use bitcoin_circle_stark::treepp::*;
-use bitcoin_circle_stark::utils::{hash, limb_to_be_bits_toaltstack_except_lowest_1bit};
-use bitcoin_script_dsl::builtins::hash::HashVar;
+use bitcoin_circle_stark::utils::limb_to_be_bits_toaltstack_except_lowest_1bit;
 use bitcoin_script_dsl::builtins::m31::M31Var;
 use bitcoin_script_dsl::bvar::{AllocVar, BVar};
 use bitcoin_script_dsl::options::Options;
 use bitcoin_script_dsl::stack::Stack;
+use stwo_prover::core::fields::m31::M31;
 use stwo_prover::core::vcs::sha256_hash::Sha256Hash;
 
-pub fn query_and_verify_merkle_twin_tree(
-    root_hash_var: &HashVar,
+/// Query a twin tree whose nodes are hashed with `H`, with a proof converted from stwo's
+/// decommitment.
+///
+/// The proof is first verified by stwo, which commits under SHA-256, so only `H` that matches
+/// the hasher of the prover can accept it.
+pub fn query_and_verify_merkle_twin_tree<H: ScriptHasher>(
+    root_hash_var: &H::Var,
     pos_var: &M31Var,
     proof: &MerkleTreeTwinProof,
 ) -> Result<(Vec<M31Var>, Vec<M31Var>)> {
@@ -20,34 +26,78 @@ pub fn query_and_verify_merkle_twin_tree(
         pos -= 1;
     }
 
-    let proof_is_valid = proof.verify(
-        &Sha256Hash::from(root_hash_var.value.as_slice()),
-        proof.path.siblings.len() + 1,
-        pos as usize,
-    );
-    if !proof_is_valid {
+    let root_hash = root_hash_var.digest();
+    if root_hash.len() != 32
+        || !proof.verify(
+            &Sha256Hash::from(root_hash),
+            proof.path.siblings.len() + 1,
+            pos as usize,
+        )
+    {
+        return Err(Error::msg("Merkle tree proof is invalid"));
+    }
+
+    let siblings = proof
+        .path
+        .siblings
+        .iter()
+        .map(|sibling| sibling.as_ref().to_vec())
+        .collect::<Vec<_>>();
+    query_and_verify_merkle_twin_tree_with_siblings::<H>(
+        root_hash_var,
+        pos_var,
+        &proof.left,
+        &proof.right,
+        &siblings,
+    )
+}
+
+/// Query a twin tree whose nodes are hashed with `H`.
+///
+/// The leaves are hashed by `H::hash_leaf`, and the hash of each leaf is hashed again before
+/// the two are combined, as in stwo's twin trees. The siblings are ordered from the bottom of the
+/// tree to the top.
+pub fn query_and_verify_merkle_twin_tree_with_siblings<H: ScriptHasher>(
+    root_hash_var: &H::Var,
+    pos_var: &M31Var,
+    left: &[M31],
+    right: &[M31],
+    siblings: &[Vec<u8>],
+) -> Result<(Vec<M31Var>, Vec<M31Var>)> {
+    let mut cur = H::hash(&[H::hash(&H::hash_leaf(left)), H::hash(&H::hash_leaf(right))].concat());
+    for (i, sibling) in siblings.iter().enumerate() {
+        if sibling.len() != H::DIGEST_LEN {
+            return Err(Error::msg("Merkle tree proof is invalid"));
+        }
+        cur = if (pos_var.value.0 >> (i + 1)) & 1 == 0 {
+            H::hash(&[cur.as_slice(), sibling].concat())
+        } else {
+            H::hash(&[sibling, cur.as_slice()].concat())
+        };
+    }
+    if cur != root_hash_var.digest() {
         return Err(Error::msg("Merkle tree proof is invalid"));
     }
 
     let cs = root_hash_var.cs().and(&pos_var.cs());
 
     let mut left_var = vec![];
-    for &elem in proof.left.iter() {
+    for &elem in left.iter() {
         left_var.push(M31Var::new_hint(&cs, elem)?);
     }
 
-    let left_hash = HashVar::from(left_var.as_slice());
+    let left_hash = H::hash_leaf_var(&left_var)?;
 
     let mut right_var = vec![];
-    for &elem in proof.right.iter() {
+    for &elem in right.iter() {
         right_var.push(M31Var::new_hint(&cs, elem)?);
     }
 
-    let right_hash = HashVar::from(right_var.as_slice());
+    let right_hash = H::hash_leaf_var(&right_var)?;
 
     let mut path_var = vec![];
-    for elem in proof.path.siblings.iter().rev() {
-        path_var.push(HashVar::new_hint(&cs, elem.as_ref().to_vec())?);
+    for elem in siblings.iter().rev() {
+        path_var.push(H::Var::new_digest_hint(&cs, elem.clone())?);
     }
 
     let mut variables = root_hash_var.variables();
@@ -59,18 +109,25 @@ pub fn query_and_verify_merkle_twin_tree(
     variables.push(pos_var.variable);
 
     cs.insert_script_complex(
-        query_and_verify_merkle_twin_tree_gadget,
+        query_and_verify_merkle_twin_tree_gadget::<H>,
         variables,
-        &Options::new().with_u32("logn", (proof.path.siblings.len() + 1) as u32),
+        &Options::new().with_u32("logn", (siblings.len() + 1) as u32),
     )?;
 
     Ok((left_var, right_var))
 }
 
-fn query_and_verify_merkle_twin_tree_gadget(_: &mut Stack, options: &Options) -> Result<Script> {
+fn query_and_verify_merkle_twin_tree_gadget<H: ScriptHasher>(
+    _: &mut Stack,
+    options: &Options,
+) -> Result<Script> {
     let logn = options.get_u32("logn")?;
+    Ok(merkle_twin_tree_path_script::<H>(logn))
+}
 
-    Ok(script! {
+/// The script that verifies a twin tree path.
+pub fn merkle_twin_tree_path_script<H: ScriptHasher>(logn: u32) -> Script {
+    script! {
         // stack:
         // - root_hash
         // - merkle_path
@@ -80,12 +137,12 @@ fn query_and_verify_merkle_twin_tree_gadget(_: &mut Stack, options: &Options) ->
 
         { limb_to_be_bits_toaltstack_except_lowest_1bit(logn) }
         // hash the right_hash again
-        hash
+        { H::hash_gadget() }
         // hash the left_hash again
-        OP_SWAP hash
+        OP_SWAP { H::hash_gadget() }
 
         // combine left_hash and right_hash
-        OP_SWAP OP_CAT hash
+        OP_SWAP OP_CAT { H::hash_gadget() }
 
         // stack:
         // - root_hash
@@ -94,9 +151,148 @@ fn query_and_verify_merkle_twin_tree_gadget(_: &mut Stack, options: &Options) ->
 
         for _ in 0..(logn - 1) {
             OP_FROMALTSTACK OP_NOTIF OP_SWAP OP_ENDIF
-            OP_CAT hash
+            OP_CAT { H::hash_gadget() }
         }
 
         OP_EQUALVERIFY
-    })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::algorithms::hasher::{
+        DigestVar, Hash160ScriptHasher, ScriptHasher, Sha256ScriptHasher,
+    };
+    use crate::algorithms::twin_tree::{
+        merkle_twin_tree_path_script, query_and_verify_merkle_twin_tree_with_siblings,
+    };
+    use bitcoin_circle_stark::treepp::*;
+    use bitcoin_script_dsl::builtins::m31::M31Var;
+    use bitcoin_script_dsl::bvar::AllocVar;
+    use bitcoin_script_dsl::compiler::Compiler;
+    use bitcoin_script_dsl::constraint_system::{ConstraintSystem, ConstraintSystemRef, Element};
+    use bitcoin_script_dsl::test_program;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha20Rng;
+    use stwo_prover::core::fields::m31::M31;
+
+    const LOGN: usize = 6;
+    const NUM_COLUMNS: usize = 4;
+
+    /// Build a random tree with `H`, and query it at a random position.
+    fn build_with_hasher<H: ScriptHasher>(prng: &mut ChaCha20Rng) -> ConstraintSystemRef {
+        let leaves = (0..1 << LOGN)
+            .map(|_| {
+                (0..NUM_COLUMNS)
+                    .map(|_| M31::from(prng.gen_range(0..(1u32 << 31) - 1)))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        let mut layers = vec![leaves
+            .chunks_exact(2)
+            .map(|pair| {
+                H::hash(
+                    &[
+                        H::hash(&H::hash_leaf(&pair[0])),
+                        H::hash(&H::hash_leaf(&pair[1])),
+                    ]
+                    .concat(),
+                )
+            })
+            .collect::<Vec<_>>()];
+        while layers.last().unwrap().len() > 1 {
+            let layer = layers
+                .last()
+                .unwrap()
+                .chunks_exact(2)
+                .map(|pair| H::hash(&[pair[0].as_slice(), pair[1].as_slice()].concat()))
+                .collect::<Vec<_>>();
+            layers.push(layer);
+        }
+        let root = layers.last().unwrap()[0].clone();
+
+        let pos = prng.gen_range(0..1 << LOGN);
+        let siblings = layers[0..LOGN - 1]
+            .iter()
+            .enumerate()
+            .map(|(i, layer)| layer[(pos >> (i + 1)) ^ 1].clone())
+            .collect::<Vec<_>>();
+
+        let cs = ConstraintSystem::new_ref();
+        let root_var = H::Var::new_digest_constant(&cs, root).unwrap();
+        let pos_var = M31Var::new_program_input(&cs, M31::from(pos as u32)).unwrap();
+
+        let (left_var, right_var) = query_and_verify_merkle_twin_tree_with_siblings::<H>(
+            &root_var,
+            &pos_var,
+            &leaves[pos & !1],
+            &leaves[pos | 1],
+            &siblings,
+        )
+        .unwrap();
+        assert_eq!(left_var.len(), NUM_COLUMNS);
+        assert_eq!(right_var.len(), NUM_COLUMNS);
+
+        // a wrong sibling is rejected
+        let mut bad_siblings = siblings.clone();
+        bad_siblings[0][0] ^= 1;
+        let bad_cs = ConstraintSystem::new_ref();
+        let bad_root_var =
+            H::Var::new_digest_constant(&bad_cs, root_var.digest().to_vec()).unwrap();
+        let bad_pos_var = M31Var::new_program_input(&bad_cs, M31::from(pos as u32)).unwrap();
+        assert!(query_and_verify_merkle_twin_tree_with_siblings::<H>(
+            &bad_root_var,
+            &bad_pos_var,
+            &leaves[pos & !1],
+            &leaves[pos | 1],
+            &bad_siblings,
+        )
+        .is_err());
+
+        cs
+    }
+
+    /// The number of bytes of the hints that are strings, i.e., the digests.
+    fn digest_witness_len(cs: ConstraintSystemRef) -> usize {
+        Compiler::compile(cs)
+            .unwrap()
+            .hint
+            .iter()
+            .map(|elem| match elem {
+                Element::Str(v) => v.len(),
+                Element::Num(_) => 0,
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_twin_tree_with_hashers() {
+        let mut prng = ChaCha20Rng::seed_from_u64(0);
+
+        test_program(
+            build_with_hasher::<Sha256ScriptHasher>(&mut prng),
+            script! {},
+        )
+        .unwrap();
+        test_program(
+            build_with_hasher::<Hash160ScriptHasher>(&mut prng),
+            script! {},
+        )
+        .unwrap();
+
+        // Both hashes are a single opcode, so the path costs the same script. What HASH160 saves
+        // is 12 bytes of witness per sibling, and it hashes the leaves with its own gadget.
+        for logn in [6, 20] {
+            assert_eq!(
+                merkle_twin_tree_path_script::<Sha256ScriptHasher>(logn).len(),
+                merkle_twin_tree_path_script::<Hash160ScriptHasher>(logn).len()
+            );
+        }
+        assert_eq!(
+            digest_witness_len(build_with_hasher::<Sha256ScriptHasher>(&mut prng))
+                - digest_witness_len(build_with_hasher::<Hash160ScriptHasher>(&mut prng)),
+            (LOGN - 1) * (Sha256ScriptHasher::DIGEST_LEN - Hash160ScriptHasher::DIGEST_LEN)
+        );
+    }
 }
@@ -0,0 +1,266 @@
+use bitcoin::hashes::{hash160, Hash};
+use bitcoin_circle_stark::treepp::*;
+use sha2::{Digest, Sha256};
+
+/// Number of bits in a digit.
+pub const LOG_DIGIT_BASE: usize = 4;
+
+/// The largest digit.
+pub const MAX_DIGIT: u32 = (1 << LOG_DIGIT_BASE) - 1;
+
+/// The secret key of a Winternitz one-time signature on a message of `n_bytes` bytes.
+///
+/// The message is split into 4-bit digits, each signed by a hash chain, and followed by a
+/// checksum of the digits so that a signature cannot be turned into one on a larger digit.
+#[derive(Clone, Debug)]
+pub struct WinternitzSecretKey {
+    pub n_bytes: usize,
+    pub preimages: Vec<Vec<u8>>,
+}
+
+/// The public key of a Winternitz one-time signature, which is the ends of the hash chains.
+#[derive(Clone, Debug)]
+pub struct WinternitzPublicKey {
+    pub n_bytes: usize,
+    pub hashes: Vec<Vec<u8>>,
+}
+
+/// The number of digits of the checksum.
+pub fn num_checksum_digits(n_bytes: usize) -> usize {
+    let max_checksum = (n_bytes * 2) as u32 * MAX_DIGIT;
+
+    let mut num_digits = 1;
+    while max_checksum >> (num_digits * LOG_DIGIT_BASE) != 0 {
+        num_digits += 1;
+    }
+    num_digits
+}
+
+/// Split the message into digits, the higher half of each byte first, followed by the checksum
+/// in big-endian.
+pub fn message_to_digits(message: &[u8]) -> Vec<u32> {
+    let mut digits = vec![];
+    for &byte in message.iter() {
+        digits.push((byte >> 4) as u32);
+        digits.push((byte & 15) as u32);
+    }
+
+    let checksum: u32 = digits.iter().map(|&digit| MAX_DIGIT - digit).sum();
+    for i in (0..num_checksum_digits(message.len())).rev() {
+        digits.push((checksum >> (i * LOG_DIGIT_BASE)) & MAX_DIGIT);
+    }
+
+    digits
+}
+
+fn hash160_chain(data: &[u8], n: u32) -> Vec<u8> {
+    let mut cur = data.to_vec();
+    for _ in 0..n {
+        cur = hash160::Hash::hash(&cur).to_byte_array().to_vec();
+    }
+    cur
+}
+
+impl WinternitzSecretKey {
+    /// Derive the secret key deterministically from a seed.
+    pub fn from_seed(seed: &[u8], n_bytes: usize) -> Self {
+        let num_digits = n_bytes * 2 + num_checksum_digits(n_bytes);
+
+        let preimages = (0..num_digits)
+            .map(|i| {
+                let mut sha256 = Sha256::new();
+                Digest::update(&mut sha256, seed);
+                Digest::update(&mut sha256, (i as u32).to_le_bytes());
+                sha256.finalize()[0..20].to_vec()
+            })
+            .collect();
+
+        Self { n_bytes, preimages }
+    }
+
+    pub fn public_key(&self) -> WinternitzPublicKey {
+        WinternitzPublicKey {
+            n_bytes: self.n_bytes,
+            hashes: self
+                .preimages
+                .iter()
+                .map(|preimage| hash160_chain(preimage, MAX_DIGIT))
+                .collect(),
+        }
+    }
+
+    /// Sign the message, returning the signature and the digit for each digit, in the order they
+    /// are pushed to the stack.
+    pub fn sign(&self, message: &[u8]) -> Vec<Vec<u8>> {
+        assert_eq!(message.len(), self.n_bytes);
+
+        let mut witness = vec![];
+        for (preimage, digit) in self.preimages.iter().zip(message_to_digits(message)) {
+            witness.push(hash160_chain(preimage, digit));
+            witness.push(if digit == 0 {
+                vec![]
+            } else {
+                vec![digit as u8]
+            });
+        }
+        witness
+    }
+}
+
+impl WinternitzPublicKey {
+    /// The number of stack elements in a signature.
+    pub fn signature_len(&self) -> usize {
+        self.hashes.len() * 2
+    }
+
+    /// The script that verifies the signature and leaves the signed message on the stack.
+    ///
+    /// stack:
+    /// - signature of the first digit
+    /// - the first digit
+    /// - ...
+    /// - signature of the last digit
+    /// - the last digit
+    ///
+    /// Reassembling the message into a single element uses `OP_CAT`.
+    pub fn verify_script(&self) -> Script {
+        let num_checksum_digits = num_checksum_digits(self.n_bytes);
+
+        script! {
+            // verify the digits from the last one, which leaves the first digit on top of the
+            // altstack
+            for hash in self.hashes.iter().rev() {
+                OP_DUP 0 OP_GREATERTHANOREQUAL OP_VERIFY
+                OP_DUP { MAX_DIGIT } OP_LESSTHANOREQUAL OP_VERIFY
+                OP_DUP OP_TOALTSTACK
+
+                // complete the hash chain
+                { MAX_DIGIT } OP_SWAP OP_SUB
+                for _ in 0..MAX_DIGIT {
+                    OP_DUP 0 OP_GREATERTHAN
+                    OP_IF OP_SWAP OP_HASH160 OP_SWAP OP_1SUB OP_ENDIF
+                }
+                OP_DROP
+
+                { hash.clone() } OP_EQUALVERIFY
+            }
+
+            // stack:
+            // - message
+            // - checksum
+            OP_0 OP_0
+
+            for _ in 0..self.n_bytes {
+                OP_FROMALTSTACK
+                { Self::add_to_checksum() }
+                OP_DUP OP_ADD OP_DUP OP_ADD OP_DUP OP_ADD OP_DUP OP_ADD
+
+                OP_FROMALTSTACK
+                OP_SWAP OP_TOALTSTACK
+                { Self::add_to_checksum() }
+                OP_FROMALTSTACK OP_ADD
+
+                // stack:
+                // - message
+                // - checksum
+                // - byte
+
+                { Self::number_to_byte() }
+                OP_ROT OP_SWAP OP_CAT
+                OP_SWAP
+            }
+
+            // recover the checksum from the signed digits
+            OP_0
+            for _ in 0..num_checksum_digits {
+                OP_DUP OP_ADD OP_DUP OP_ADD OP_DUP OP_ADD OP_DUP OP_ADD
+                OP_FROMALTSTACK OP_ADD
+            }
+            OP_EQUALVERIFY
+        }
+    }
+
+    /// stack:
+    /// - checksum
+    /// - digit
+    ///
+    /// output:
+    /// - checksum + (MAX_DIGIT - digit)
+    /// - digit
+    fn add_to_checksum() -> Script {
+        script! {
+            OP_DUP OP_ROT OP_SWAP OP_SUB { MAX_DIGIT } OP_ADD
+            OP_SWAP
+        }
+    }
+
+    /// Convert a number in [0, 255] into a one-byte string.
+    fn number_to_byte() -> Script {
+        script! {
+            OP_DUP 0 OP_EQUAL
+            OP_IF
+                OP_DROP OP_PUSHBYTES_1 OP_PUSHBYTES_0
+            OP_ELSE
+                OP_DUP 128 OP_EQUAL
+                OP_IF
+                    OP_DROP { vec![0x80u8] }
+                OP_ELSE
+                    // a number in (128, 255] is encoded as the negative of its lower seven bits
+                    OP_DUP 128 OP_GREATERTHAN
+                    OP_IF 128 OP_SUB OP_NEGATE OP_ENDIF
+                OP_ENDIF
+            OP_ENDIF
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::algorithms::winternitz::WinternitzSecretKey;
+    use bitcoin_circle_stark::treepp::*;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha20Rng;
+
+    #[test]
+    fn test_winternitz() {
+        let mut prng = ChaCha20Rng::seed_from_u64(0);
+
+        let secret_key = WinternitzSecretKey::from_seed(b"winternitz", 32);
+        let public_key = secret_key.public_key();
+
+        let mut message = [0u8; 32];
+        prng.fill(&mut message);
+        // cover the bytes that need special handling
+        message[0] = 0x00;
+        message[1] = 0x80;
+        message[2] = 0xff;
+        message[3] = 0x7f;
+
+        let signature = secret_key.sign(&message);
+        assert_eq!(signature.len(), public_key.signature_len());
+
+        let script = script! {
+            for elem in signature.iter() {
+                { elem.clone() }
+            }
+            { public_key.verify_script() }
+            { message.to_vec() } OP_EQUAL
+        };
+        let exec_result = execute_script(script);
+        assert!(exec_result.success);
+
+        // claiming a larger digit without the matching hash fails the verification
+        let mut signature = secret_key.sign(&message);
+        signature[1] = vec![1u8];
+
+        let script = script! {
+            for elem in signature.iter() {
+                { elem.clone() }
+            }
+            { public_key.verify_script() }
+            OP_DROP OP_TRUE
+        };
+        let exec_result = execute_script(script);
+        assert!(!exec_result.success);
+    }
+}
@@ -0,0 +1,92 @@
+use bitcoin::consensus::deserialize;
+use bitcoin::Transaction;
+use clap::Parser;
+use colored::Colorize;
+use rust_bitcoin_m31_acc::broadcast::{Broadcaster, CoreRpc, WaitFor, MAX_UNCONFIRMED_CHAIN};
+use std::path::Path;
+use std::time::Duration;
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// Directory with the transactions, as written by the demo
+    #[arg(short, long, default_value = "./demo-fractal")]
+    dir: String,
+
+    /// The RPC interface of Bitcoin Core, as <host>:<port>
+    #[arg(short, long, default_value = "127.0.0.1:8332")]
+    rpc: String,
+
+    /// The RPC user
+    #[arg(short, long, requires = "password")]
+    user: Option<String>,
+
+    /// The RPC password
+    #[arg(short, long, requires = "user")]
+    password: Option<String>,
+
+    /// The number of the first transaction to submit
+    #[arg(short, long, default_value_t = 1)]
+    start: usize,
+
+    /// Wait for this many confirmations of a transaction before the next one, instead of only for
+    /// the mempool to accept it
+    #[arg(short, long)]
+    confirmations: Option<u32>,
+
+    /// The seconds between two lookups of the status of a transaction
+    #[arg(long, default_value_t = 10)]
+    poll_interval: u64,
+
+    /// The most transactions to have in the mempool at once, after which the next one waits for a
+    /// block, as the node takes no longer chain of unconfirmed transactions
+    #[arg(long, default_value_t = MAX_UNCONFIRMED_CHAIN)]
+    max_unconfirmed_chain: usize,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let mut txs = vec![];
+    loop {
+        let path = Path::new(&args.dir).join(format!("tx-{}.txt", args.start + txs.len()));
+        if !path.exists() {
+            break;
+        }
+        let bytes = hex::decode(std::fs::read_to_string(&path).unwrap().trim()).unwrap();
+        txs.push(deserialize::<Transaction>(&bytes).unwrap());
+    }
+    assert!(
+        !txs.is_empty(),
+        "there is no tx-{}.txt in {}",
+        args.start,
+        args.dir
+    );
+
+    let wait_for = match args.confirmations {
+        Some(n) => WaitFor::Confirmations(n),
+        None => WaitFor::Mempool,
+    };
+    let rpc = CoreRpc::new(args.rpc, args.user.zip(args.password));
+
+    let mut broadcaster = Broadcaster::new(rpc, wait_for);
+    broadcaster.poll_interval = Duration::from_secs(args.poll_interval);
+    broadcaster.max_unconfirmed_chain = args.max_unconfirmed_chain;
+
+    match broadcaster.broadcast_all(&txs) {
+        Ok(()) => println!(
+            "Transactions {} to {} have been broadcast.",
+            args.start,
+            args.start + txs.len() - 1
+        ),
+        Err(err) => {
+            let failed = args.start + broadcaster.next;
+            println!("Transaction {} has not gone through: {:#}", failed, err);
+            println!(
+                "Once the cause is fixed, e.g., by regenerating the transactions from a fee-bumped variant, call this tool again with {} to resume.",
+                format!("--start {}", failed).on_bright_green().black()
+            );
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use clap::Parser;
+use rust_bitcoin_m31_acc::dsl::plonk::debugger::{debug_step, TraceGranularity};
+use rust_bitcoin_m31_acc::dsl::plonk::split::compute_debug_information;
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// Index of the step to replay
+    #[arg(short, long)]
+    step: usize,
+
+    /// Print the stack and the altstack after every opcode instead of at every region
+    #[arg(short, long)]
+    per_opcode: bool,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let debug_information = compute_debug_information();
+    assert!(
+        args.step < debug_information.all_information.scripts.len(),
+        "there are only {} steps",
+        debug_information.all_information.scripts.len()
+    );
+
+    let granularity = if args.per_opcode {
+        TraceGranularity::Opcode
+    } else {
+        TraceGranularity::Region
+    };
+
+    let mut out = String::new();
+    let success = debug_step(&debug_information, args.step, granularity, &mut out);
+    print!("{}", out);
+
+    if !success {
+        std::process::exit(1);
+    }
+}
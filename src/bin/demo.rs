@@ -1,14 +1,15 @@
 use bitcoin::consensus::Encodable;
 use bitcoin::hashes::{sha256d, Hash};
-use bitcoin::opcodes::all::{OP_PUSHBYTES_36, OP_RETURN};
-use bitcoin::{Address, Network, OutPoint, ScriptBuf, Txid, WScriptHash};
+use bitcoin::{Address, Network, OutPoint, Transaction, Txid};
 use clap::Parser;
 use colored::Colorize;
-use covenants_gadgets::test::SimulationInstruction;
-use covenants_gadgets::{get_script_pub_key, get_tx, CovenantInput, CovenantProgram, DUST_AMOUNT};
+use covenants_gadgets::CovenantProgram;
+use rust_bitcoin_m31_acc::dsl::plonk::bootstrap::{bootstrap_psbt, INITIAL_RANDOMIZER};
 use rust_bitcoin_m31_acc::dsl::plonk::covenant::{
-    compute_all_information, PlonkVerifierProgram, PlonkVerifierState, PLONK_ALL_INFORMATION,
+    bumped_fee, compute_all_information, leaf_fees, PlonkVerifierCheckpoint, PlonkVerifierDeposit,
+    PlonkVerifierProgram, PLONK_ALL_INFORMATION,
 };
+use rust_bitcoin_m31_acc::dsl::plonk::taproot_export::export_taproot_tree;
 use std::io::Write;
 
 #[derive(Parser, Debug)]
@@ -21,47 +22,64 @@ struct Args {
     /// Txid
     #[arg(short, long)]
     initial_program_txid: Option<String>,
+
+    /// The UTXO, as <txid>:<vout>, that the PSBT starting the covenant spends
+    #[arg(long)]
+    funding_outpoint: Option<OutPoint>,
+
+    /// An extra funding UTXO that tops up the balance at a step, as
+    /// <step>:<txid>:<vout>:<amount in sats>:<script pub key in hex> (can be repeated)
+    #[arg(short, long)]
+    deposit: Vec<PlonkVerifierDeposit>,
+
+    /// Also generate, for every step, a variant whose fee is this percentage of the fee, which
+    /// can replace a stuck transaction or pay for it as its child (can be repeated)
+    #[arg(long)]
+    fee_bump: Vec<u64>,
+
+    /// Generate the transactions from the checkpoint of a confirmed fee-bumped variant, as
+    /// written into its .checkpoint file
+    #[arg(short, long)]
+    resume: Option<PlonkVerifierCheckpoint>,
 }
 
 fn main() {
     let args = Args::parse();
 
-    let mut fees = vec![114555, 210434, 103439, 101696, 93044, 81704, 92834];
+    let all_information = PLONK_ALL_INFORMATION.get_or_init(compute_all_information);
+    let num_steps = all_information.scripts.len();
 
-    for _ in 0..8 {
-        fees.extend_from_slice(&[100926, 97293, 97167, 86863, 77679, 86863, 88802, 40467]);
-    }
+    // the pcs that the transactions run from, where a skip leaf takes the place of the
+    // per-query steps of a query that has the same parent as an earlier query
+    let path = all_information.path();
+    let path_pcs = path
+        .iter()
+        .map(|&idx| all_information.leaf_pc(idx))
+        .collect::<Vec<_>>();
 
-    fees.push(49777);
+    // the program outputs of the bootstrap and of all the steps are locked to this tree
+    let tree = export_taproot_tree();
 
     let fee_rate = 1500; // 1 for signet, ~1500 for fractal
     let network = Network::Bitcoin;
 
+    let fees = leaf_fees(fee_rate);
+    assert_eq!(fees.len(), PlonkVerifierProgram::get_all_scripts().len());
+
+    let total_fee = path.iter().map(|&idx| fees[idx]).sum::<u64>();
     let amount =
-        (fees.iter().sum::<usize>() as u64 + 10000) / 7 * fee_rate + 330 * 74 + 400 * fee_rate;
+        total_fee + 10000 * fee_rate / 7 + 330 * (path_pcs.len() as u64 + 2) + 400 * fee_rate;
     let amount_display = (((amount as f64) / 1000.0 / 1000.0 / 100.0) * 10000.0).ceil() / 10000.0;
     let actual_amount = (amount_display * 100.0 * 1000.0 * 1000.0) as u64;
     let rest = actual_amount - 330 - 400 * fee_rate;
 
-    if args.funding_txid.is_none() || args.initial_program_txid.is_none() {
-        let script_pub_key = get_script_pub_key::<PlonkVerifierProgram>();
-
-        let program_address = Address::from_script(script_pub_key.as_script(), network).unwrap();
-
-        let init_state = PlonkVerifierProgram::new();
-        let hash = PlonkVerifierProgram::get_hash(&init_state);
+    if args.resume.is_none() && (args.funding_txid.is_none() || args.initial_program_txid.is_none())
+    {
+        let program_address =
+            Address::from_script(tree.script_pub_key.as_script(), network).unwrap();
 
-        let mut bytes = vec![OP_RETURN.to_u8(), OP_PUSHBYTES_36.to_u8()];
-        bytes.extend_from_slice(&hash);
-        bytes.extend_from_slice(&12u32.to_le_bytes());
-
-        let caboose_address = Address::from_script(
-            ScriptBuf::new_p2wsh(&WScriptHash::hash(&bytes)).as_script(),
-            network,
-        )
-        .unwrap();
-
-        let rest_display = (rest as f64) / 1000.0 / 1000.0 / 100.0;
+        let psbt = bootstrap_psbt(&tree, rest, args.funding_outpoint).unwrap();
+        std::fs::write("./demo-fractal/bootstrap.psbt", psbt.serialize()).unwrap();
 
         println!("================= INSTRUCTIONS =================");
         println!("To start with, prepare {} BTC into a UTXO transaction which would be used to fund the transaction fee for the entire demo-fibonacci.",
@@ -75,17 +93,26 @@ fn main() {
             amount_display
         );
         println!();
-        println!("According to that transaction, send BTC from that UTXO to the program and the state caboose with the initial state");
-        println!("> ./bitcoin-cli --datadir=signet createrawtransaction \"[{{\\\"txid\\\":\\\"{}\\\", \\\"vout\\\": {}}}]\" \"[{{\\\"{}\\\":{:.8}}}, {{\\\"{}\\\":0.0000033}}]\"",
-                 "[txid]".on_bright_green().black(),
-                 "[vout]".on_bright_green().black(), program_address, rest_display,
-                 caboose_address
+        println!("Call this tool with that UTXO to write a PSBT that sends it to the program and the state caboose with the initial state");
+        println!(
+            "> cargo run -- --funding-outpoint {}:{}",
+            "[txid]".on_bright_green().black(),
+            "[vout]".on_bright_green().black()
         );
         println!();
-        println!("Then, sign the transaction");
+        if args.funding_outpoint.is_none() {
+            println!("Alternatively, a wallet that adds inputs to a PSBT can fund ./demo-fractal/bootstrap.psbt directly, which has only the outputs.");
+            println!();
+        }
         println!(
-            "> ./bitcoin-cli --datadir=signet signrawtransactionwithwallet {}",
-            "[tx hex]".on_bright_green().black()
+            "Then, sign and finalize the PSBT in ./demo-fractal/bootstrap.psbt, which sends {} BTC to the program at {}",
+            (rest as f64) / 1000.0 / 1000.0 / 100.0,
+            program_address
+        );
+        println!("> ./bitcoin-cli --datadir=signet walletprocesspsbt $(base64 -w0 ./demo-fractal/bootstrap.psbt)");
+        println!(
+            "> ./bitcoin-cli --datadir=signet finalizepsbt {}",
+            "[signed psbt]".on_bright_green().black()
         );
         println!();
         println!("Send the signed transaction");
@@ -102,91 +129,101 @@ fn main() {
         );
         println!("================================================");
     } else {
-        let mut initial_program_txid = [0u8; 32];
-        initial_program_txid
-            .copy_from_slice(&hex::decode(args.initial_program_txid.unwrap()).unwrap());
-        initial_program_txid.reverse();
-
-        let mut funding_txid = [0u8; 32];
-        funding_txid.copy_from_slice(&hex::decode(args.funding_txid.unwrap()).unwrap());
-        funding_txid.reverse();
-
-        let mut old_state = PlonkVerifierProgram::new();
-        let mut old_randomizer = 12u32;
-        let mut old_balance = rest;
-        let mut old_txid =
-            Txid::from_raw_hash(*sha256d::Hash::from_bytes_ref(&initial_program_txid));
-
-        let mut old_tx_outpoint1 = OutPoint {
-            txid: Txid::from_raw_hash(*sha256d::Hash::from_bytes_ref(&funding_txid)),
-            vout: 0, // change this number if the funding tx is not the first output
-        };
-
-        let mut txs = Vec::new();
-
-        let get_instruction = |old_state: &PlonkVerifierState| {
-            let all_information = PLONK_ALL_INFORMATION.get_or_init(compute_all_information);
-
-            if old_state.pc < fees.len() {
-                Some(SimulationInstruction::<PlonkVerifierProgram> {
-                    program_index: old_state.pc,
-                    fee: (fees[old_state.pc] as f64 / 7.0 * (fee_rate as f64)).ceil() as usize,
-                    program_input: all_information.get_input(old_state.pc),
-                })
-            } else {
-                unimplemented!()
+        let mut checkpoint = match args.resume.clone() {
+            Some(checkpoint) => checkpoint,
+            None => {
+                let mut initial_program_txid = [0u8; 32];
+                initial_program_txid
+                    .copy_from_slice(&hex::decode(args.initial_program_txid.unwrap()).unwrap());
+                initial_program_txid.reverse();
+
+                let mut funding_txid = [0u8; 32];
+                funding_txid.copy_from_slice(&hex::decode(args.funding_txid.unwrap()).unwrap());
+                funding_txid.reverse();
+
+                PlonkVerifierCheckpoint {
+                    pc: 0,
+                    txid: Txid::from_raw_hash(*sha256d::Hash::from_bytes_ref(
+                        &initial_program_txid,
+                    )),
+                    randomizer: INITIAL_RANDOMIZER,
+                    balance: rest,
+                    input_outpoint1: OutPoint {
+                        txid: Txid::from_raw_hash(*sha256d::Hash::from_bytes_ref(&funding_txid)),
+                        vout: 0, // change this number if the funding tx is not the first output
+                    },
+                }
             }
         };
 
-        for _ in 0..72 {
-            let next = get_instruction(&old_state).unwrap();
-
-            let mut new_balance = old_balance;
-            new_balance -= next.fee as u64; // as for transaction fee
-            new_balance -= DUST_AMOUNT;
-
-            let info = CovenantInput {
-                old_randomizer,
-                old_balance,
-                old_txid,
-                input_outpoint1: old_tx_outpoint1,
-                input_outpoint2: None,
-                optional_deposit_input: None,
-                new_balance,
-            };
-
-            let new_state =
-                PlonkVerifierProgram::run(next.program_index, &old_state, &next.program_input)
-                    .unwrap();
-
-            let (tx_template, randomizer) = get_tx::<PlonkVerifierProgram>(
-                &info,
-                next.program_index,
-                &old_state,
-                &new_state,
-                &next.program_input,
+        for deposit in args.deposit.iter() {
+            assert!(
+                path_pcs.contains(&deposit.step),
+                "the deposit is at step {}, which is skipped or not one of the {} steps",
+                deposit.step,
+                num_steps
             );
-
-            txs.push(tx_template.tx.clone());
-
-            old_state = new_state;
-            old_randomizer = randomizer;
-            old_balance = new_balance;
-            old_txid = tx_template.tx.compute_txid();
-
-            old_tx_outpoint1 = tx_template.tx.input[0].previous_output;
         }
 
-        for (i, tx) in txs.iter().enumerate() {
+        let write_file = |name: String, content: &[u8]| {
+            // this directory is to Fractal mainnet
+            let mut fs = std::fs::File::create(format!("./demo-fractal/{}", name)).unwrap();
+            fs.write_all(content).unwrap();
+        };
+        let encode = |tx: &Transaction| {
             let mut bytes = vec![];
             tx.consensus_encode(&mut bytes).unwrap();
+            hex::encode(bytes)
+        };
 
-            // this directory is to Fractal mainnet
-            let mut fs = std::fs::File::create(format!("./demo-fractal/tx-{}.txt", i + 1)).unwrap();
-            fs.write_all(hex::encode(bytes).as_bytes()).unwrap();
+        // the transactions are numbered in the order they are broadcast
+        let first_tx = path_pcs
+            .iter()
+            .position(|&pc| pc == checkpoint.pc)
+            .expect("the checkpoint is not on the path of the proof");
+        let mut tx_idx = first_tx;
+        while checkpoint.pc < num_steps {
+            let step = checkpoint.pc;
+            let fee = fees[all_information.leaf_at(step)];
+
+            let deposit = args.deposit.iter().find(|deposit| deposit.step == step);
+
+            // the fee-bumped variants spend the same inputs, and come with the checkpoint to
+            // resume from if one of them is confirmed instead
+            for &bump in args.fee_bump.iter() {
+                let (tx, next) = checkpoint
+                    .advance(&tree, bumped_fee(fee, bump), deposit)
+                    .unwrap();
+                write_file(
+                    format!("tx-{}-fee-{}.txt", tx_idx + 1, bump),
+                    encode(&tx).as_bytes(),
+                );
+                write_file(
+                    format!("tx-{}-fee-{}.checkpoint", tx_idx + 1, bump),
+                    next.to_string().as_bytes(),
+                );
+            }
+
+            let (tx, next) = checkpoint.advance(&tree, fee, deposit).unwrap();
+            write_file(format!("tx-{}.txt", tx_idx + 1), encode(&tx).as_bytes());
+            checkpoint = next;
+            tx_idx += 1;
         }
 
         println!("================= INSTRUCTIONS =================");
-        println!("All 72 transactions have been generated and stored in the current directory.");
+        println!(
+            "All {} transactions have been generated and stored in the current directory.",
+            path_pcs.len() - first_tx
+        );
+        println!(
+            "Submit them in order with {}, which waits for each one before the next.",
+            "cargo run --release --bin broadcast -- --rpc <host>:<port> --user <user> --password <password>".on_bright_green().black()
+        );
+        if !args.fee_bump.is_empty() {
+            println!(
+                "If a transaction is stuck, send one of its fee-bumped variants to replace it, or send a fee-bumped variant of the next transaction to pay for both. Then, call this tool again with {} and the checkpoint of the variant to generate the rest.",
+                "--resume".on_bright_green().black()
+            );
+        }
     }
 }
@@ -0,0 +1,30 @@
+use clap::Parser;
+use rust_bitcoin_m31_acc::dsl::plonk::split::compute_all_disassemblies;
+use std::io::Write;
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// Directory to write the disassembly of each step into
+    #[arg(short, long, default_value = "./disassembly")]
+    output_dir: String,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let disassemblies = compute_all_disassemblies();
+
+    std::fs::create_dir_all(&args.output_dir).unwrap();
+
+    for (i, disassembly) in disassemblies.iter().enumerate() {
+        let mut fs = std::fs::File::create(format!("{}/step-{}.txt", args.output_dir, i)).unwrap();
+        fs.write_all(disassembly.as_bytes()).unwrap();
+    }
+
+    println!(
+        "The disassembly of the {} steps has been written to {}.",
+        disassemblies.len(),
+        args.output_dir
+    );
+}
@@ -0,0 +1,39 @@
+use clap::Parser;
+use rust_bitcoin_m31_acc::dsl::plonk::taproot_export::export_taproot_tree;
+use std::io::Write;
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// Directory to write the tapleaf scripts and the manifest into
+    #[arg(short, long, default_value = "./taproot-export")]
+    output_dir: String,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let export = export_taproot_tree();
+
+    std::fs::create_dir_all(&args.output_dir).unwrap();
+
+    for leaf in export.leaves.iter() {
+        let mut fs =
+            std::fs::File::create(format!("{}/leaf-{}.txt", args.output_dir, leaf.step)).unwrap();
+        fs.write_all(hex::encode(leaf.script.as_bytes()).as_bytes())
+            .unwrap();
+    }
+
+    let mut fs = std::fs::File::create(format!("{}/common-prefix.txt", args.output_dir)).unwrap();
+    fs.write_all(hex::encode(export.common_prefix.as_bytes()).as_bytes())
+        .unwrap();
+
+    let mut fs = std::fs::File::create(format!("{}/manifest.txt", args.output_dir)).unwrap();
+    fs.write_all(export.manifest().as_bytes()).unwrap();
+
+    println!(
+        "The {} tapleaf scripts and the manifest have been written to {}.",
+        export.leaves.len(),
+        args.output_dir
+    );
+}
@@ -0,0 +1,26 @@
+use clap::Parser;
+use rust_bitcoin_m31_acc::dsl::plonk::hints::Hints;
+use rust_bitcoin_m31_acc::dsl::plonk::ldm_liveness::LdmLiveness;
+use rust_bitcoin_m31_acc::dsl::plonk::ldm_schema::LdmSchema;
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// Print the liveness of the LDM entries after each step instead of the dependency graph
+    #[arg(short, long)]
+    liveness: bool,
+}
+
+/// Print the dependency graph of the steps, derived from the LDM schema, in the DOT format.
+fn main() {
+    let args = Args::parse();
+
+    let hints = Hints::instance();
+    let schema = LdmSchema::plonk(&hints);
+
+    if args.liveness {
+        print!("{}", LdmLiveness::from_schema(&schema).report(&schema));
+    } else {
+        print!("{}", schema.dependency_graph_dot());
+    }
+}
@@ -0,0 +1,311 @@
+use anyhow::Result;
+use bitcoin::opcodes::all::OP_DROP;
+use bitcoin::script::Instruction;
+use bitcoin_circle_stark::treepp::*;
+use bitcoin_script_dsl::constraint_system::ConstraintSystemRef;
+use bitcoin_script_dsl::options::Options;
+use bitcoin_script_dsl::stack::Stack;
+use std::cell::Cell;
+use std::fmt::Write;
+
+/// The code emitted by a call that is marked by `record_region`, at its position in the
+/// compiled script.
+#[derive(Clone, Debug)]
+pub struct RecordedRegion {
+    pub name: String,
+    pub script: Script,
+    pub span: (usize, usize),
+}
+
+thread_local! {
+    static RECORDING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Run `f`, typically the generation and the compilation of a step, with the calls marked by
+/// `record_region` inserting their markers.
+pub fn with_recording<T>(f: impl FnOnce() -> T) -> T {
+    let was_recording = RECORDING.with(|recording| recording.replace(true));
+    let res = f();
+    RECORDING.with(|recording| recording.set(was_recording));
+    res
+}
+
+/// The prefix of the data that marks where a region recorded by `record_region` begins or ends.
+const REGION_MARKER_PREFIX: &[u8] = b"recorded-region";
+
+/// Run `f`, which emits code into the constraint system, e.g., a gadget of `algorithms`, an LDM
+/// access, or a QM31 multiplication, and mark the code it emits as a region with the name if a
+/// recording is in progress.
+///
+/// The calls are marked where the steps make them, so the gadgets themselves are not aware of
+/// the disassembly. The code is marked by a push of a marker and an `OP_DROP` before and after
+/// it, which `strip_region_markers` removes from the compiled script. Outside a recording,
+/// nothing is inserted.
+pub fn record_region<T>(cs: &ConstraintSystemRef, name: &str, f: impl FnOnce() -> T) -> T {
+    if !RECORDING.with(|recording| recording.get()) {
+        return f();
+    }
+
+    insert_region_marker(cs, true, name);
+    let res = f();
+    insert_region_marker(cs, false, name);
+    res
+}
+
+fn insert_region_marker(cs: &ConstraintSystemRef, begin: bool, name: &str) {
+    let mut marker = REGION_MARKER_PREFIX.to_vec();
+    marker.push(begin as u8);
+    marker.extend_from_slice(name.as_bytes());
+
+    cs.insert_script_complex(
+        region_marker_gadget,
+        vec![],
+        &Options::new().with_binary("marker", marker),
+    )
+    .unwrap();
+}
+
+fn region_marker_gadget(_: &mut Stack, options: &Options) -> Result<Script> {
+    let marker = options.get_binary("marker")?;
+    Ok(script! {
+        { marker }
+        OP_DROP
+    })
+}
+
+/// Remove the markers of `record_region` from the compiled script, which leaves the script that
+/// is compiled without a recording, and return the regions at their positions in it.
+pub fn strip_region_markers(script: &Script) -> (Script, Vec<RecordedRegion>) {
+    let bytes = script.as_bytes();
+    let instructions = script
+        .instruction_indices()
+        .map(|(offset, instruction)| (offset, instruction.ok()))
+        .collect::<Vec<_>>();
+
+    let mut stripped = vec![];
+    let mut open: Vec<(String, usize)> = vec![];
+    let mut regions = vec![];
+
+    let mut i = 0;
+    while i < instructions.len() {
+        let (offset, instruction) = &instructions[i];
+        let next = instructions
+            .get(i + 1)
+            .map(|(offset, _)| *offset)
+            .unwrap_or(bytes.len());
+
+        let marker = match instruction {
+            Some(Instruction::PushBytes(data))
+                if data.as_bytes().starts_with(REGION_MARKER_PREFIX)
+                    && data.len() > REGION_MARKER_PREFIX.len()
+                    && matches!(instructions.get(i + 1), Some((_, Some(Instruction::Op(op)))) if *op == OP_DROP) =>
+            {
+                Some(&data.as_bytes()[REGION_MARKER_PREFIX.len()..])
+            }
+            _ => None,
+        };
+
+        match marker {
+            Some(marker) => {
+                let name = String::from_utf8_lossy(&marker[1..]).to_string();
+                if marker[0] == 1 {
+                    open.push((name, stripped.len()));
+                } else if let Some((name, start)) = open.pop() {
+                    regions.push(RecordedRegion {
+                        name,
+                        script: Script::from_bytes(stripped[start..].to_vec()),
+                        span: (start, stripped.len()),
+                    });
+                }
+                // skip the marker and the `OP_DROP`
+                i += 2;
+            }
+            None => {
+                stripped.extend_from_slice(&bytes[*offset..next]);
+                i += 1;
+            }
+        }
+    }
+
+    regions.sort_by_key(|region| region.span);
+    (Script::from_bytes(stripped), regions)
+}
+
+/// A region of the compiled script.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Region {
+    /// The code of a call that is marked by `record_region`.
+    Recorded(String),
+    /// Other code, i.e., the stack movements of the DSL crate and the calls that are not marked.
+    Unrecorded,
+}
+
+impl Region {
+    pub fn label(&self) -> String {
+        match self {
+            Region::Recorded(label) => label.clone(),
+            Region::Unrecorded => "stack movements and other code".to_string(),
+        }
+    }
+}
+
+/// Split the script into regions.
+///
+/// The recorded regions may nest, e.g., a gadget inside a recorded call of a step, and the
+/// innermost one wins. The code that is in no recorded region is left unrecorded.
+pub fn split_into_regions(
+    script: &Script,
+    recorded: &[RecordedRegion],
+) -> Vec<(usize, usize, Region)> {
+    let bytes = script.as_bytes();
+
+    // the label of every byte, where the innermost region wins
+    let mut labels = vec![Region::Unrecorded; bytes.len()];
+    let mut spans = recorded
+        .iter()
+        .map(|region| (region.span, region.name.clone()))
+        .collect::<Vec<_>>();
+    spans.sort_by_key(|((start, end), _)| (*start, std::cmp::Reverse(*end)));
+    for ((start, end), name) in spans.into_iter() {
+        for label_of_byte in labels[start..end.min(bytes.len())].iter_mut() {
+            *label_of_byte = Region::Recorded(name.clone());
+        }
+    }
+
+    // merge the bytes into regions, which only split at the instruction boundaries
+    let mut regions: Vec<(usize, usize, Region)> = vec![];
+    let offsets = script
+        .instruction_indices()
+        .map(|(offset, _)| offset)
+        .chain(std::iter::once(bytes.len()))
+        .collect::<Vec<_>>();
+    for window in offsets.windows(2) {
+        let (start, end) = (window[0], window[1]);
+        let region = labels[start].clone();
+        match regions.last_mut() {
+            Some(last) if last.2 == region && last.1 == start => last.1 = end,
+            _ => regions.push((start, end, region)),
+        }
+    }
+
+    regions
+}
+
+/// Disassemble the script, one instruction per line, with the regions marked.
+pub fn disassemble(script: &Script, recorded: &[RecordedRegion]) -> String {
+    let regions = split_into_regions(script, recorded);
+
+    let mut out = String::new();
+    let mut region_iter = regions.iter().peekable();
+
+    for (offset, instruction) in script.instruction_indices() {
+        while let Some((start, end, region)) = region_iter.peek() {
+            if *end <= offset {
+                region_iter.next();
+                continue;
+            }
+            if *start == offset {
+                writeln!(out, "; ---- {} [{} bytes]", region.label(), end - start).unwrap();
+            }
+            break;
+        }
+
+        let text = match instruction {
+            Ok(Instruction::Op(op)) => format!("{:?}", op),
+            Ok(Instruction::PushBytes(data)) if data.is_empty() => "OP_0".to_string(),
+            Ok(Instruction::PushBytes(data)) => format!("<{}>", hex::encode(data.as_bytes())),
+            Err(e) => format!("<invalid: {}>", e),
+        };
+        writeln!(out, "{:08x}: {}", offset, text).unwrap();
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use crate::dsl::disassemble::{
+        record_region, split_into_regions, strip_region_markers, with_recording, RecordedRegion,
+        Region,
+    };
+    use bitcoin_circle_stark::treepp::*;
+    use bitcoin_script_dsl::builtins::m31::M31Var;
+    use bitcoin_script_dsl::bvar::AllocVar;
+    use bitcoin_script_dsl::compiler::Compiler;
+    use bitcoin_script_dsl::constraint_system::ConstraintSystem;
+    use stwo_prover::core::fields::m31::M31;
+
+    #[test]
+    fn test_split_into_regions() {
+        let script = script! {
+            OP_DUP
+            OP_CAT OP_SHA256
+            OP_ADD OP_ADD OP_ADD
+            OP_DROP
+        };
+
+        let recorded = vec![
+            RecordedRegion {
+                name: "outer".to_string(),
+                script: script! { OP_CAT OP_SHA256 OP_ADD OP_ADD OP_ADD },
+                span: (1, 6),
+            },
+            RecordedRegion {
+                name: "inner".to_string(),
+                script: script! { OP_ADD OP_ADD OP_ADD },
+                span: (3, 6),
+            },
+        ];
+
+        let regions = split_into_regions(&script, &recorded);
+        let labels = regions
+            .iter()
+            .map(|(_, _, region)| region.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            labels,
+            vec![
+                Region::Unrecorded,
+                Region::Recorded("outer".to_string()),
+                Region::Recorded("inner".to_string()),
+                Region::Unrecorded,
+            ]
+        );
+        assert_eq!(regions[2].0, 3);
+        assert_eq!(regions[2].1, 6);
+    }
+
+    #[test]
+    fn test_record_region() {
+        let build = || {
+            let cs = ConstraintSystem::new_ref();
+            let a = M31Var::new_program_input(&cs, M31::from(3)).unwrap();
+            let b = M31Var::new_hint(&cs, M31::from(4)).unwrap();
+            let sum = record_region(&cs, "m31 add", || &a + &b);
+            sum.equalverify(&M31Var::new_constant(&cs, M31::from(7)).unwrap())
+                .unwrap();
+            cs
+        };
+
+        // nothing is inserted outside a recording
+        let script = Compiler::compile(build()).unwrap().script;
+        let (stripped, recorded) = strip_region_markers(&script);
+        assert_eq!(stripped, script);
+        assert!(recorded.is_empty());
+
+        // the markers are stripped, which leaves the script compiled without a recording
+        let marked = with_recording(|| Compiler::compile(build()).unwrap().script);
+        assert_ne!(marked, script);
+        let (stripped, recorded) = strip_region_markers(&marked);
+        assert_eq!(stripped, script);
+
+        assert_eq!(recorded.len(), 1);
+        assert_eq!(recorded[0].name, "m31 add");
+        let (start, end) = recorded[0].span;
+        assert!(start < end);
+        assert_eq!(
+            recorded[0].script.as_bytes(),
+            &script.as_bytes()[start..end]
+        );
+    }
+}
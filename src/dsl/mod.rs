@@ -1 +1,3 @@
+pub mod disassemble;
+
 pub mod plonk;
@@ -0,0 +1,123 @@
+use crate::dsl::plonk::covenant::{PlonkVerifierProgram, PlonkVerifierState};
+use crate::dsl::plonk::taproot_export::TaprootExport;
+use anyhow::Result;
+use bitcoin::absolute::LockTime;
+use bitcoin::hashes::Hash;
+use bitcoin::opcodes::all::{OP_PUSHBYTES_36, OP_RETURN};
+use bitcoin::psbt::Psbt;
+use bitcoin::transaction::Version;
+use bitcoin::{
+    Amount, OutPoint, ScriptBuf, Sequence, Transaction, TxIn, TxOut, WScriptHash, Witness,
+};
+use covenants_gadgets::{CovenantProgram, DUST_AMOUNT};
+
+/// The randomizer of the state caboose in the transaction that starts the covenant.
+pub const INITIAL_RANDOMIZER: u32 = 12;
+
+/// The script pub key of the state caboose, which commits to the hash of the state and the
+/// randomizer.
+pub fn caboose_script_pub_key(state: &PlonkVerifierState, randomizer: u32) -> ScriptBuf {
+    let mut bytes = vec![OP_RETURN.to_u8(), OP_PUSHBYTES_36.to_u8()];
+    bytes.extend_from_slice(&PlonkVerifierProgram::get_hash(state));
+    bytes.extend_from_slice(&randomizer.to_le_bytes());
+
+    ScriptBuf::new_p2wsh(&WScriptHash::hash(&bytes))
+}
+
+/// The outputs of the transaction that starts the covenant: the program with the balance, and the
+/// state caboose with the initial state.
+///
+/// The program output is locked to the tree, which is the same tree that the steps then spend
+/// from (see `PlonkVerifierCheckpoint::advance`).
+pub fn bootstrap_outputs(tree: &TaprootExport, balance: u64) -> Vec<TxOut> {
+    vec![
+        TxOut {
+            value: Amount::from_sat(balance),
+            script_pubkey: tree.script_pub_key.clone(),
+        },
+        TxOut {
+            value: Amount::from_sat(DUST_AMOUNT),
+            script_pubkey: caboose_script_pub_key(&PlonkVerifierProgram::new(), INITIAL_RANDOMIZER),
+        },
+    ]
+}
+
+/// A PSBT (BIP-174) of the transaction that starts the covenant, which a wallet funds, signs, and
+/// finalizes.
+///
+/// Without a funding UTXO, the PSBT only has the outputs, and the wallet adds the inputs and the
+/// change. With a funding UTXO, the PSBT spends it, and its amount beyond the outputs is the fee.
+/// The wallet may add the change after the outputs, but must keep their order, as the steps spend
+/// the program and the state caboose by their positions.
+pub fn bootstrap_psbt(
+    tree: &TaprootExport,
+    balance: u64,
+    funding_outpoint: Option<OutPoint>,
+) -> Result<Psbt> {
+    let tx = Transaction {
+        version: Version::TWO,
+        lock_time: LockTime::ZERO,
+        input: funding_outpoint
+            .into_iter()
+            .map(|previous_output| TxIn {
+                previous_output,
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                witness: Witness::new(),
+            })
+            .collect(),
+        output: bootstrap_outputs(tree, balance),
+    };
+
+    Ok(Psbt::from_unsigned_tx(tx)?)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::dsl::plonk::bootstrap::{bootstrap_psbt, INITIAL_RANDOMIZER};
+    use crate::dsl::plonk::covenant::{PlonkVerifierCheckpoint, PlonkVerifierProgram};
+    use crate::dsl::plonk::taproot_export::export_taproot_tree;
+    use bitcoin::hashes::Hash;
+    use bitcoin::psbt::Psbt;
+    use bitcoin::{OutPoint, Txid};
+    use covenants_gadgets::{get_script_pub_key, DUST_AMOUNT};
+
+    #[test]
+    fn test_bootstrap_psbt() {
+        let tree = export_taproot_tree();
+
+        let psbt = bootstrap_psbt(&tree, 1000000, None).unwrap();
+        assert!(psbt.unsigned_tx.input.is_empty());
+        assert_eq!(psbt.unsigned_tx.output.len(), 2);
+        assert_eq!(
+            psbt.unsigned_tx.output[0].script_pubkey,
+            get_script_pub_key::<PlonkVerifierProgram>()
+        );
+        assert_eq!(psbt.unsigned_tx.output[0].value.to_sat(), 1000000);
+        assert_eq!(psbt.unsigned_tx.output[1].value.to_sat(), DUST_AMOUNT);
+        assert_eq!(psbt.outputs.len(), 2);
+
+        let funding_outpoint = OutPoint {
+            txid: Txid::all_zeros(),
+            vout: 1,
+        };
+        let psbt = bootstrap_psbt(&tree, 1000000, Some(funding_outpoint)).unwrap();
+        assert_eq!(psbt.unsigned_tx.input[0].previous_output, funding_outpoint);
+        assert_eq!(psbt.inputs.len(), 1);
+        assert_eq!(Psbt::deserialize(&psbt.serialize()).unwrap(), psbt);
+
+        // the first step spends the outputs of the transaction, through the leaf of the same tree
+        let checkpoint = PlonkVerifierCheckpoint {
+            pc: 0,
+            txid: psbt.unsigned_tx.compute_txid(),
+            randomizer: INITIAL_RANDOMIZER,
+            balance: 1000000,
+            input_outpoint1: funding_outpoint,
+        };
+        let (tx, _) = checkpoint.advance(&tree, 20000, None).unwrap();
+        assert!(tx
+            .input
+            .iter()
+            .any(|input| input.previous_output.txid == psbt.unsigned_tx.compute_txid()));
+    }
+}
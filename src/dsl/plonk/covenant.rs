@@ -1,43 +1,26 @@
-use crate::dsl::plonk::hints::Hints;
-use anyhow::Result;
-use bitcoin::script::write_scriptint;
+use anyhow::{Error, Result};
+use bitcoin::hashes::Hash;
+use bitcoin::{Amount, OutPoint, ScriptBuf, Transaction, Txid};
 use bitcoin_circle_stark::treepp::*;
 use bitcoin_circle_stark::utils::hash;
 use bitcoin_script_dsl::builtins::table::utils::OP_HINT;
-use bitcoin_script_dsl::compiler::Compiler;
-use bitcoin_script_dsl::constraint_system::Element;
-use bitcoin_script_dsl::ldm::LDM;
 use bitcoin_scriptexec::utils::scriptint_vec;
 use covenants_gadgets::utils::stack_hash::StackHash;
-use covenants_gadgets::CovenantProgram;
+use covenants_gadgets::{get_tx, CovenantInput, CovenantProgram, DepositInput, DUST_AMOUNT};
 use sha2::digest::Update;
 use sha2::{Digest, Sha256};
 use std::collections::BTreeMap;
-use std::sync::OnceLock;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
 
-pub type Witness = Vec<Vec<u8>>;
+pub use crate::dsl::plonk::split::{
+    compute_all_information, PlonkAllInformation, PlonkVerifierInput, Witness,
+    PLONK_ALL_INFORMATION,
+};
+use crate::dsl::plonk::taproot_export::TaprootExport;
 
 pub struct PlonkVerifierProgram {}
 
-#[derive(Clone)]
-pub struct PlonkVerifierInput {
-    pub stack: Witness,
-    pub hints: Witness,
-}
-
-impl From<PlonkVerifierInput> for Script {
-    fn from(input: PlonkVerifierInput) -> Script {
-        script! {
-            for elem in input.stack {
-                { elem }
-            }
-            for elem in input.hints {
-                { elem }
-            }
-        }
-    }
-}
-
 /// The state of the Plonk split program.
 #[derive(Clone, Debug)]
 pub struct PlonkVerifierState {
@@ -58,157 +41,6 @@ impl From<PlonkVerifierState> for Script {
     }
 }
 
-pub struct PlonkAllInformation {
-    pub scripts: Vec<Script>,
-    pub witnesses: Vec<Witness>,
-    pub outputs: Vec<Witness>,
-}
-
-pub static PLONK_ALL_INFORMATION: OnceLock<PlonkAllInformation> = OnceLock::new();
-
-impl PlonkAllInformation {
-    pub fn get_input(&self, idx: usize) -> PlonkVerifierInput {
-        PlonkVerifierInput {
-            stack: if idx == 0 {
-                vec![]
-            } else {
-                self.outputs[idx - 1].clone()
-            },
-            hints: self.witnesses[idx].clone(),
-        }
-    }
-}
-
-pub fn compute_all_information() -> PlonkAllInformation {
-    let mut scripts = vec![];
-    let mut witnesses = vec![];
-
-    let hints = Hints::instance();
-    let mut ldm = LDM::new();
-
-    let num_to_str = |v: i32| {
-        let mut out = [0u8; 8];
-        let len = write_scriptint(&mut out, v as i64);
-        out[0..len].to_vec()
-    };
-
-    let mut outputs = vec![];
-
-    for f in [
-        super::part1_fiat_shamir1::generate_cs,
-        super::part2_fiat_shamir2_and_constraint_num::generate_cs,
-        super::part3_constraint_denom::generate_cs,
-        super::part4_pair_vanishing_and_alphas::generate_cs,
-        super::part5_column_line_coeffs1::generate_cs,
-        super::part6_column_line_coeffs2::generate_cs,
-        super::part7_column_line_coeffs3::generate_cs,
-    ] {
-        let cs = f(&hints, &mut ldm).unwrap();
-        let program = Compiler::compile(cs).unwrap();
-
-        scripts.push(program.script);
-
-        let mut witness = vec![];
-        for entry in program.hint.iter() {
-            match &entry {
-                Element::Num(v) => {
-                    witness.push(num_to_str(*v));
-                }
-                Element::Str(v) => {
-                    witness.push(v.clone());
-                }
-            }
-        }
-
-        witnesses.push(witness);
-        outputs.push(
-            convert_to_witness(script! {
-                { ldm.write_hash_var.as_ref().unwrap().value.clone() }
-                { ldm.read_hash_var.as_ref().unwrap().value.clone() }
-            })
-            .unwrap(),
-        );
-    }
-
-    for query_idx in 0..8 {
-        for f in [
-            super::per_query_part1_folding::generate_cs,
-            super::per_query_part2_num_trace::generate_cs,
-            super::per_query_part3_num_constant::generate_cs,
-            super::per_query_part4_num_composition::generate_cs,
-            super::per_query_part5_num_interaction_shifted::generate_cs,
-            super::per_query_part6_num_interaction1::generate_cs,
-            super::per_query_part7_num_interaction2::generate_cs,
-            super::per_query_part8_last_step::generate_cs,
-        ] {
-            let dsl = f(&hints, &mut ldm, query_idx).unwrap();
-            let program = Compiler::compile(dsl).unwrap();
-
-            scripts.push(program.script);
-
-            let mut witness = vec![];
-            for entry in program.hint.iter() {
-                match &entry {
-                    Element::Num(v) => {
-                        witness.push(num_to_str(*v));
-                    }
-                    Element::Str(v) => {
-                        witness.push(v.clone());
-                    }
-                }
-            }
-
-            witnesses.push(witness);
-
-            outputs.push(
-                convert_to_witness(script! {
-                    { ldm.write_hash_var.as_ref().unwrap().value.clone() }
-                    { ldm.read_hash_var.as_ref().unwrap().value.clone() }
-                })
-                .unwrap(),
-            );
-        }
-    }
-
-    for f in [super::part8_cleanup::generate_cs] {
-        let cs = f(&hints, &mut ldm).unwrap();
-        let program = Compiler::compile(cs).unwrap();
-
-        scripts.push(program.script);
-
-        let mut witness = vec![];
-        for entry in program.hint.iter() {
-            match &entry {
-                Element::Num(v) => {
-                    witness.push(num_to_str(*v));
-                }
-                Element::Str(v) => {
-                    witness.push(v.clone());
-                }
-            }
-        }
-
-        witnesses.push(witness);
-
-        outputs.push(
-            convert_to_witness(script! {
-                { ldm.write_hash_var.as_ref().unwrap().value.clone() }
-                { ldm.read_hash_var.as_ref().unwrap().value.clone() }
-            })
-            .unwrap(),
-        );
-    }
-
-    assert_eq!(scripts.len(), witnesses.len());
-    assert_eq!(scripts.len(), outputs.len());
-
-    PlonkAllInformation {
-        scripts,
-        witnesses,
-        outputs,
-    }
-}
-
 impl CovenantProgram for PlonkVerifierProgram {
     type State = PlonkVerifierState;
     type Input = PlonkVerifierInput;
@@ -235,45 +67,22 @@ impl CovenantProgram for PlonkVerifierProgram {
 
         let mut map = BTreeMap::new();
 
-        for script_idx in 0..(8 + 8 * 8) {
+        for script_idx in 0..all_information.scripts.len() {
             map.insert(
                 script_idx,
-                script! {
-                    // input:
-                    // - old pc
-                    // - old stack hash
-                    // - new pc
-                    // - new stack hash
-
-                    OP_SWAP { script_idx + 1 } OP_EQUALVERIFY
-                    OP_ROT { script_idx } OP_EQUALVERIFY
-
-                    if script_idx == 0 {
-                        OP_SWAP { vec![0u8; 32] } OP_EQUALVERIFY
-
-                        // stack:
-                        // - new stack hash
-                        OP_TOALTSTACK
-                    } else {
-                        // stack:
-                        // - old stack hash
-                        // - new stack hash
-                        OP_TOALTSTACK OP_TOALTSTACK
-
-                        { StackHash::hash_from_hint(2) }
-                        OP_FROMALTSTACK OP_EQUALVERIFY
-                    }
-
-                    { all_information.scripts[script_idx].clone() }
-
-                    OP_DEPTH
-                    { 2 }
-                    OP_EQUALVERIFY
-
-                    { StackHash::hash_drop(2) }
-                    OP_FROMALTSTACK OP_EQUALVERIFY
-                    OP_TRUE
-                },
+                leaf_script(
+                    script_idx,
+                    script_idx + 1,
+                    &all_information.scripts[script_idx],
+                ),
+            );
+        }
+
+        // the skip leaves are numbered after the steps
+        for (skip_idx, skip) in all_information.skips.iter().enumerate() {
+            map.insert(
+                all_information.scripts.len() + skip_idx,
+                leaf_script(skip.from_pc, skip.to_pc, &skip.script),
             );
         }
 
@@ -314,55 +123,447 @@ impl CovenantProgram for PlonkVerifierProgram {
     fn run(id: usize, _: &Self::State, _: &Self::Input) -> Result<Self::State> {
         let all_information = PLONK_ALL_INFORMATION.get_or_init(compute_all_information);
 
-        let final_stack = all_information.outputs[id].to_vec();
+        let pc = all_information.next_pc(id);
+        let final_stack = all_information.outputs[pc - 1].to_vec();
         let stack_hash = StackHash::compute(&final_stack);
         Ok(Self::State {
-            pc: id + 1,
+            pc,
             stack_hash,
             stack: final_stack,
         })
     }
 }
 
+/// The script of a leaf that runs the script from the old pc and moves to the new pc, which is
+/// the next pc for a step and the pc after the skipped steps for a skip leaf.
+fn leaf_script(old_pc: usize, new_pc: usize, script: &Script) -> Script {
+    script! {
+        // input:
+        // - old pc
+        // - old stack hash
+        // - new pc
+        // - new stack hash
+
+        OP_SWAP { new_pc } OP_EQUALVERIFY
+        OP_ROT { old_pc } OP_EQUALVERIFY
+
+        if old_pc == 0 {
+            OP_SWAP { vec![0u8; 32] } OP_EQUALVERIFY
+
+            // stack:
+            // - new stack hash
+            OP_TOALTSTACK
+        } else {
+            // stack:
+            // - old stack hash
+            // - new stack hash
+            OP_TOALTSTACK OP_TOALTSTACK
+
+            { StackHash::hash_from_hint(2) }
+            OP_FROMALTSTACK OP_EQUALVERIFY
+        }
+
+        { script.clone() }
+
+        OP_DEPTH
+        { 2 }
+        OP_EQUALVERIFY
+
+        { StackHash::hash_drop(2) }
+        OP_FROMALTSTACK OP_EQUALVERIFY
+        OP_TRUE
+    }
+}
+
+/// An extra funding UTXO that the transaction of a step spends, through the deposit path of the
+/// covenant, to top up the balance of the program.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlonkVerifierDeposit {
+    /// The step whose transaction spends the UTXO.
+    pub step: usize,
+    /// The UTXO.
+    pub outpoint: OutPoint,
+    /// The script pub key of the UTXO, which the transaction signs over.
+    pub script_pub_key: ScriptBuf,
+    /// The amount of the UTXO in sats, which is credited to the balance.
+    pub amount: u64,
+}
+
+impl PlonkVerifierDeposit {
+    /// The second input of the transaction and the deposit that `CovenantInput` takes.
+    pub fn to_covenant_input(&self) -> (Option<OutPoint>, Option<DepositInput>) {
+        (
+            Some(self.outpoint),
+            Some(DepositInput {
+                script_pub_key: self.script_pub_key.clone(),
+                amount: Amount::from_sat(self.amount),
+            }),
+        )
+    }
+}
+
+impl FromStr for PlonkVerifierDeposit {
+    type Err = Error;
+
+    /// Parse `<step>:<txid>:<vout>:<amount in sats>:<script pub key in hex>`.
+    fn from_str(s: &str) -> Result<Self> {
+        let parts = s.split(':').collect::<Vec<_>>();
+        if parts.len() != 5 {
+            return Err(Error::msg(
+                "A deposit is <step>:<txid>:<vout>:<amount in sats>:<script pub key in hex>",
+            ));
+        }
+
+        Ok(Self {
+            step: parts[0].parse()?,
+            outpoint: OutPoint {
+                txid: Txid::from_str(parts[1])?,
+                vout: parts[2].parse()?,
+            },
+            amount: parts[3].parse()?,
+            script_pub_key: ScriptBuf::from_bytes(hex::decode(parts[4])?),
+        })
+    }
+}
+
+/// The balance after a step that pays the fee and the dust of the state caboose, and takes in the
+/// deposit of the step if there is one.
+///
+/// It fails if the balance would run out, so the fees can be topped up with a deposit at or
+/// before the step.
+pub fn new_balance(
+    step: usize,
+    old_balance: u64,
+    fee: u64,
+    deposit: Option<&PlonkVerifierDeposit>,
+) -> Result<u64> {
+    let deposit_amount = deposit.map(|deposit| deposit.amount).unwrap_or_default();
+    (old_balance + deposit_amount)
+        .checked_sub(fee + DUST_AMOUNT)
+        .ok_or_else(|| {
+            Error::msg(format!(
+                "The balance of {} sats cannot pay for step {}, which needs a deposit of at least {} sats at or before it",
+                old_balance + deposit_amount,
+                step,
+                fee + DUST_AMOUNT - old_balance - deposit_amount
+            ))
+        })
+}
+
+/// Where the chain of transactions stands before a step, which is all it takes to generate the
+/// rest of the chain.
+///
+/// The transaction of a step can be replaced by a variant with a higher fee (see `bumped_fee`)
+/// while it is stuck in the mempool, or be bumped by a variant of the next step with a higher fee,
+/// which pays for both. Either way, the chain continues from the checkpoint of the variant that
+/// gets confirmed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlonkVerifierCheckpoint {
+    /// The step to run next.
+    pub pc: usize,
+    /// The transaction that holds the program UTXO.
+    pub txid: Txid,
+    /// The randomizer of the state caboose.
+    pub randomizer: u32,
+    /// The balance of the program UTXO.
+    pub balance: u64,
+    /// The first input of the transaction of the next step.
+    pub input_outpoint1: OutPoint,
+}
+
+impl PlonkVerifierCheckpoint {
+    /// The state of the program before the step.
+    pub fn state(&self) -> PlonkVerifierState {
+        state_before(self.pc)
+    }
+
+    /// Generate the transaction of the step with the fee, and the checkpoint after it.
+    ///
+    /// The transaction spends the program output of the tree through the leaf that the
+    /// verification takes at the pc, which is the skip leaf of a query that has the same parent as
+    /// an earlier query, and creates the next program output of the same tree.
+    pub fn advance(
+        &self,
+        tree: &TaprootExport,
+        fee: u64,
+        deposit: Option<&PlonkVerifierDeposit>,
+    ) -> Result<(Transaction, PlonkVerifierCheckpoint)> {
+        let all_information = PLONK_ALL_INFORMATION.get_or_init(compute_all_information);
+        if self.pc >= all_information.scripts.len() {
+            return Err(Error::msg("The program has no step left"));
+        }
+
+        let new_balance = new_balance(self.pc, self.balance, fee, deposit)?;
+        let (input_outpoint2, optional_deposit_input) = deposit
+            .map(|deposit| deposit.to_covenant_input())
+            .unwrap_or((None, None));
+
+        let info = CovenantInput {
+            old_randomizer: self.randomizer,
+            old_balance: self.balance,
+            old_txid: self.txid,
+            input_outpoint1: self.input_outpoint1,
+            input_outpoint2,
+            optional_deposit_input,
+            new_balance,
+        };
+
+        let leaf_idx = all_information.leaf_at(self.pc);
+        let old_state = self.state();
+        let program_input = all_information.get_input(leaf_idx);
+        let new_state = PlonkVerifierProgram::run(leaf_idx, &old_state, &program_input)?;
+
+        let (tx_template, randomizer) =
+            get_tx::<PlonkVerifierProgram>(&info, leaf_idx, &old_state, &new_state, &program_input);
+        let tx = tx_template.tx;
+
+        let leaf = tree
+            .leaf(leaf_idx)
+            .ok_or_else(|| Error::msg("The taproot tree has no leaf for the step"))?;
+        let spent_program = tx
+            .input
+            .iter()
+            .find(|input| input.previous_output == OutPoint::new(self.txid, 0))
+            .ok_or_else(|| Error::msg("The transaction of the step does not spend the program"))?;
+        if tx.output[0].script_pubkey != tree.script_pub_key
+            || spent_program.witness.taproot_control_block()
+                != Some(leaf.control_block.serialize().as_slice())
+        {
+            return Err(Error::msg(
+                "The transaction of the step is not for the taproot tree",
+            ));
+        }
+
+        let checkpoint = PlonkVerifierCheckpoint {
+            pc: new_state.pc,
+            txid: tx.compute_txid(),
+            randomizer,
+            balance: new_balance,
+            input_outpoint1: tx.input[0].previous_output,
+        };
+
+        Ok((tx, checkpoint))
+    }
+}
+
+impl Display for PlonkVerifierCheckpoint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}:{}:{}:{}",
+            self.pc,
+            self.txid,
+            self.randomizer,
+            self.balance,
+            self.input_outpoint1.txid,
+            self.input_outpoint1.vout
+        )
+    }
+}
+
+impl FromStr for PlonkVerifierCheckpoint {
+    type Err = Error;
+
+    /// Parse `<pc>:<txid>:<randomizer>:<balance>:<txid of input 1>:<vout of input 1>`, as written
+    /// by `Display`.
+    fn from_str(s: &str) -> Result<Self> {
+        let parts = s.trim().split(':').collect::<Vec<_>>();
+        if parts.len() != 6 {
+            return Err(Error::msg(
+                "A checkpoint is <pc>:<txid>:<randomizer>:<balance>:<txid of input 1>:<vout of input 1>",
+            ));
+        }
+
+        Ok(Self {
+            pc: parts[0].parse()?,
+            txid: Txid::from_str(parts[1])?,
+            randomizer: parts[2].parse()?,
+            balance: parts[3].parse()?,
+            input_outpoint1: OutPoint {
+                txid: Txid::from_str(parts[4])?,
+                vout: parts[5].parse()?,
+            },
+        })
+    }
+}
+
+/// The state of the program before the step at the pc.
+fn state_before(pc: usize) -> PlonkVerifierState {
+    if pc == 0 {
+        PlonkVerifierProgram::new()
+    } else {
+        let all_information = PLONK_ALL_INFORMATION.get_or_init(compute_all_information);
+        PlonkVerifierProgram::run(
+            pc - 1,
+            &PlonkVerifierProgram::new(),
+            &all_information.get_input(pc - 1),
+        )
+        .unwrap()
+    }
+}
+
+/// The number of virtual bytes that the fee of a leaf covers beyond the estimated transaction,
+/// for the amounts, which are encoded as script numbers of varying length.
+pub const LEAF_FEE_MARGIN_VBYTES: u64 = 16;
+
+/// The fee of the transaction of every leaf of `get_all_scripts`, by the index of the leaf, at
+/// `fee_rate` sat/vByte.
+///
+/// The fee is derived from the virtual size of the transaction of the leaf, which is generated
+/// with placeholder outpoints and amounts, so it follows the scripts and the witnesses of the
+/// steps as they change.
+pub fn leaf_fees(fee_rate: u64) -> Vec<u64> {
+    let all_information = PLONK_ALL_INFORMATION.get_or_init(compute_all_information);
+    let num_leaves = all_information.scripts.len() + all_information.skips.len();
+
+    (0..num_leaves)
+        .map(|leaf_idx| {
+            let old_state = state_before(all_information.leaf_pc(leaf_idx));
+            let program_input = all_information.get_input(leaf_idx);
+            let new_state =
+                PlonkVerifierProgram::run(leaf_idx, &old_state, &program_input).unwrap();
+
+            let info = CovenantInput {
+                old_randomizer: 0,
+                old_balance: Amount::MAX_MONEY.to_sat(),
+                old_txid: Txid::all_zeros(),
+                input_outpoint1: OutPoint::new(Txid::all_zeros(), 1),
+                input_outpoint2: None,
+                optional_deposit_input: None,
+                new_balance: Amount::MAX_MONEY.to_sat(),
+            };
+            let (tx_template, _) = get_tx::<PlonkVerifierProgram>(
+                &info,
+                leaf_idx,
+                &old_state,
+                &new_state,
+                &program_input,
+            );
+
+            (tx_template.tx.vsize() as u64 + LEAF_FEE_MARGIN_VBYTES) * fee_rate
+        })
+        .collect()
+}
+
+/// The fee of a variant that is bumped to `bump_percent` percent of the fee, rounded up.
+pub fn bumped_fee(fee: u64, bump_percent: u64) -> u64 {
+    (fee * bump_percent).div_ceil(100)
+}
+
 #[cfg(test)]
 mod test {
     use crate::dsl::plonk::covenant::{
-        compute_all_information, PlonkVerifierProgram, PlonkVerifierState, PLONK_ALL_INFORMATION,
+        bumped_fee, compute_all_information, leaf_fees, new_balance, PlonkVerifierCheckpoint,
+        PlonkVerifierDeposit, PlonkVerifierProgram, PlonkVerifierState, PLONK_ALL_INFORMATION,
     };
+    use crate::dsl::plonk::taproot_export::export_taproot_tree;
+    use bitcoin::hashes::Hash;
+    use bitcoin::{OutPoint, Txid};
     use covenants_gadgets::test::{simulation_test, SimulationInstruction};
+    use covenants_gadgets::{CovenantProgram, DUST_AMOUNT};
 
     #[test]
     fn test_integration() {
         // The integration assumes a fee rate of 7 sat/vByte.
         // Note that in many situations, the fee rate is only 2 sat/vByte.
 
-        let mut fees = vec![114555, 210434, 103439, 101696, 93044, 81704, 92834];
+        let all_information = PLONK_ALL_INFORMATION.get_or_init(compute_all_information);
 
-        for _ in 0..8 {
-            fees.extend_from_slice(&[100926, 97293, 97167, 86863, 77679, 86863, 88802, 40467]);
-        }
+        let fees = leaf_fees(7);
+        assert_eq!(fees.len(), PlonkVerifierProgram::get_all_scripts().len());
 
-        fees.push(49777);
+        let path = all_information.path();
 
         println!(
             "total fee assuming 7 sat/vByte: {}",
-            fees.iter().sum::<usize>()
+            path.iter().map(|&idx| fees[idx]).sum::<u64>()
         );
 
         let mut test_generator = |old_state: &PlonkVerifierState| {
-            let all_information = PLONK_ALL_INFORMATION.get_or_init(compute_all_information);
-
-            if old_state.pc < fees.len() {
+            if old_state.pc < all_information.scripts.len() {
+                let leaf_idx = all_information.leaf_at(old_state.pc);
                 Some(SimulationInstruction {
-                    program_index: old_state.pc,
-                    fee: fees[old_state.pc],
-                    program_input: all_information.get_input(old_state.pc),
+                    program_index: leaf_idx,
+                    fee: fees[leaf_idx] as usize,
+                    program_input: all_information.get_input(leaf_idx),
                 })
             } else {
                 unimplemented!()
             }
         };
 
-        simulation_test::<PlonkVerifierProgram>(72, &mut test_generator);
+        simulation_test::<PlonkVerifierProgram>(path.len(), &mut test_generator);
+    }
+
+    #[test]
+    fn test_deposit() {
+        let deposit = format!("3:{}:1:50000:0014{}", "11".repeat(32), "22".repeat(20))
+            .parse::<PlonkVerifierDeposit>()
+            .unwrap();
+        assert_eq!(deposit.step, 3);
+        assert_eq!(deposit.outpoint.vout, 1);
+        assert_eq!(deposit.amount, 50000);
+        assert_eq!(deposit.script_pub_key.len(), 22);
+
+        let (outpoint, deposit_input) = deposit.to_covenant_input();
+        assert_eq!(outpoint, Some(deposit.outpoint));
+        assert_eq!(deposit_input.unwrap().amount.to_sat(), 50000);
+
+        assert!("3:00:1".parse::<PlonkVerifierDeposit>().is_err());
+
+        // without the deposit, the balance runs out
+        let fee = 60000;
+        assert!(new_balance(3, 20000, fee, None).is_err());
+        assert_eq!(
+            new_balance(3, 20000, fee, Some(&deposit)).unwrap(),
+            20000 + 50000 - fee - DUST_AMOUNT
+        );
+    }
+
+    #[test]
+    fn test_fee_variants() {
+        let checkpoint = PlonkVerifierCheckpoint {
+            pc: 0,
+            txid: Txid::all_zeros(),
+            randomizer: 12,
+            balance: 1000000,
+            input_outpoint1: OutPoint {
+                txid: Txid::all_zeros(),
+                vout: 1,
+            },
+        };
+
+        let tree = export_taproot_tree();
+        let fee = 20000;
+        let (tx, next) = checkpoint.advance(&tree, fee, None).unwrap();
+        let (bumped_tx, bumped_next) = checkpoint
+            .advance(&tree, bumped_fee(fee, 150), None)
+            .unwrap();
+
+        // the bumped variant spends the same inputs, so it replaces the transaction
+        assert_eq!(bumped_fee(fee, 150), 30000);
+        assert_eq!(
+            tx.input
+                .iter()
+                .map(|input| input.previous_output)
+                .collect::<Vec<_>>(),
+            bumped_tx
+                .input
+                .iter()
+                .map(|input| input.previous_output)
+                .collect::<Vec<_>>()
+        );
+        assert_ne!(next.txid, bumped_next.txid);
+        assert_eq!(next.balance - bumped_next.balance, 10000);
+
+        // the chain continues from either checkpoint
+        assert_eq!(bumped_next.pc, 1);
+        assert_eq!(bumped_next.state().pc, 1);
+        let restored = bumped_next
+            .to_string()
+            .parse::<PlonkVerifierCheckpoint>()
+            .unwrap();
+        assert_eq!(restored, bumped_next);
+        restored.advance(&tree, fee, None).unwrap();
     }
 }
@@ -0,0 +1,309 @@
+use crate::dsl::disassemble::split_into_regions;
+use crate::dsl::plonk::ldm_trace::{find_issues, LdmAccessKind};
+use crate::dsl::plonk::split::PlonkDebugInformation;
+use bitcoin::hashes::Hash;
+use bitcoin::script::Instruction;
+use bitcoin::taproot::TapLeafHash;
+use bitcoin::transaction::Version;
+use bitcoin::Transaction;
+use bitcoin_circle_stark::treepp::*;
+use bitcoin_scriptexec::{Exec, ExecCtx, Options, TxTemplate};
+use std::fmt::Write;
+
+/// When the debugger prints the stack and the altstack.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TraceGranularity {
+    /// After every opcode.
+    Opcode,
+    /// At the start of every region, i.e., a recorded call or the code between them.
+    Region,
+}
+
+/// Replay a step of the split verifier with the input from `PlonkAllInformation::get_input`,
+/// write the trace into `out`, and return whether the step succeeds.
+///
+/// The step is the script that the covenant wraps in `get_all_scripts`, without the checks on
+/// the program counter and the stack hash, followed by a check that the step outputs the expected
+/// LDM write hash and read hash. If it fails, the trace ends with the failing opcode, the region
+/// it belongs to, and the LDM entries read in the step whose hints differ from the values that
+/// were written.
+pub fn debug_step(
+    debug_information: &PlonkDebugInformation,
+    idx: usize,
+    granularity: TraceGranularity,
+    out: &mut String,
+) -> bool {
+    let all_information = &debug_information.all_information;
+
+    // the step pulls its hints from the bottom of the stack, and finds the input state on the top,
+    // where the covenant leaves it after pulling it from the hints
+    let input = all_information.get_input(idx);
+    let input_script = script! {
+        for elem in input.hints {
+            { elem }
+        }
+        for elem in input.stack {
+            { elem }
+        }
+    };
+    let step_script = all_information.scripts[idx].clone();
+    let base = input_script.len();
+
+    // check the output state, i.e., the LDM write hash and read hash, as the next step would
+    let script = script! {
+        { input_script }
+        { step_script.clone() }
+        for elem in all_information.outputs[idx].iter().rev() {
+            { elem.clone() }
+            OP_EQUALVERIFY
+        }
+        OP_TRUE
+    };
+    let output_check_start = base + step_script.len();
+
+    let regions = split_into_regions(&step_script, &debug_information.recorded_regions[idx]);
+    let region_at = |pos: usize| {
+        if pos < base || pos >= output_check_start {
+            return None;
+        }
+        let i = regions.partition_point(|(_, end, _)| *end <= pos - base);
+        (i < regions.len() && regions[i].0 <= pos - base).then_some(i)
+    };
+
+    let mut exec = new_exec(script.clone(), vec![]);
+
+    writeln!(
+        out,
+        "step {}: {} bytes, {} input elements",
+        idx,
+        step_script.len(),
+        all_information.get_input(idx).stack.len() + all_information.witnesses[idx].len()
+    )
+    .unwrap();
+
+    let mut current_region = None;
+    let mut last_pos;
+    loop {
+        let pos = script.len() - exec.remaining_script().len();
+
+        if granularity == TraceGranularity::Region {
+            let region = region_at(pos);
+            if region.is_some() && region != current_region {
+                let (start, end, label) = &regions[region.unwrap()];
+                writeln!(out, "; ---- {} [{:08x}..{:08x}]", label.label(), start, end).unwrap();
+                write_stacks(out, &exec);
+                current_region = region;
+            }
+        }
+
+        last_pos = pos;
+        if exec.exec_next().is_err() {
+            break;
+        }
+
+        if granularity == TraceGranularity::Opcode && pos >= base {
+            writeln!(out, "{:08x}: {}", pos - base, instruction_at(&script, pos)).unwrap();
+            write_stacks(out, &exec);
+        }
+    }
+
+    let res = exec.result().unwrap();
+    if res.success {
+        writeln!(out, "step {} succeeds", idx).unwrap();
+        return true;
+    }
+
+    writeln!(out).unwrap();
+    writeln!(out, "step {} fails: {:?}", idx, res.error).unwrap();
+    if last_pos >= base && last_pos < output_check_start {
+        writeln!(
+            out,
+            "at {:08x}: {}",
+            last_pos - base,
+            instruction_at(&script, last_pos)
+        )
+        .unwrap();
+    } else {
+        writeln!(out, "in the input").unwrap();
+    }
+    if let Some(region) = region_at(last_pos) {
+        writeln!(out, "in: {}", regions[region].2.label()).unwrap();
+    } else if last_pos >= output_check_start {
+        writeln!(out, "in: the check of the LDM write hash and read hash").unwrap();
+    }
+    write_stacks(out, &exec);
+
+    let issues = find_issues(&debug_information.ldm_accesses)
+        .into_iter()
+        .filter(|issue| issue.involves_step(idx))
+        .collect::<Vec<_>>();
+    if !issues.is_empty() {
+        writeln!(out, "LDM entries that fail the hash check:").unwrap();
+        for issue in issues.iter() {
+            writeln!(out, "- {}", issue).unwrap();
+        }
+    }
+
+    let producer_of = |key: &str| {
+        debug_information
+            .ldm_accesses
+            .iter()
+            .find(|producer| producer.key == key && producer.kind == LdmAccessKind::Write)
+            .map(|producer| producer.step.to_string())
+            .unwrap_or("?".to_string())
+    };
+
+    // compare the hints of the reads with the values that were written
+    let witness = &all_information.witnesses[idx];
+    let read_hints = &debug_information.read_hints[idx];
+    let tampered = read_hints
+        .iter()
+        .filter(|read_hint| read_hint.is_tampered(witness))
+        .collect::<Vec<_>>();
+    if !tampered.is_empty() {
+        writeln!(
+            out,
+            "LDM entries whose hints differ from the values that were written:"
+        )
+        .unwrap();
+        for read_hint in tampered.iter() {
+            writeln!(
+                out,
+                "- `{}`, written in step {}",
+                read_hint.key,
+                producer_of(&read_hint.key)
+            )
+            .unwrap();
+        }
+    } else if issues.is_empty() {
+        writeln!(
+            out,
+            "the hints of the LDM entries read in this step are honest"
+        )
+        .unwrap();
+    }
+
+    let unlocated = read_hints
+        .iter()
+        .filter(|read_hint| read_hint.positions.is_empty())
+        .collect::<Vec<_>>();
+    if !unlocated.is_empty() {
+        writeln!(out, "LDM entries whose hints could not be located:").unwrap();
+        for read_hint in unlocated.iter() {
+            writeln!(
+                out,
+                "- `{}`, written in step {}",
+                read_hint.key,
+                producer_of(&read_hint.key)
+            )
+            .unwrap();
+        }
+    }
+
+    false
+}
+
+/// Create an executor of a tapscript with the witness elements, outside of any transaction.
+pub(crate) fn new_exec(script: Script, witness: Vec<Vec<u8>>) -> Exec {
+    Exec::new(
+        ExecCtx::Tapscript,
+        Options::default(),
+        TxTemplate {
+            tx: Transaction {
+                version: Version::TWO,
+                lock_time: bitcoin::locktime::absolute::LockTime::ZERO,
+                input: vec![],
+                output: vec![],
+            },
+            prevouts: vec![],
+            input_idx: 0,
+            taproot_annex_scriptleaf: Some((TapLeafHash::all_zeros(), None)),
+        },
+        script,
+        witness,
+    )
+    .expect("error creating exec")
+}
+
+fn instruction_at(script: &Script, pos: usize) -> String {
+    match bitcoin::Script::from_bytes(&script.as_bytes()[pos..])
+        .instructions()
+        .next()
+    {
+        Some(Ok(Instruction::Op(op))) => format!("{:?}", op),
+        Some(Ok(Instruction::PushBytes(data))) => format!("<{}>", hex::encode(data.as_bytes())),
+        _ => "<invalid>".to_string(),
+    }
+}
+
+fn write_stacks(out: &mut String, exec: &Exec) {
+    let format = |items: Vec<Vec<u8>>| {
+        items
+            .iter()
+            .map(|item| {
+                if item.is_empty() {
+                    "<>".to_string()
+                } else {
+                    hex::encode(item)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+
+    writeln!(
+        out,
+        "  stack: {}",
+        format(exec.stack().iter_str().collect())
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "  altstack: {}",
+        format(exec.altstack().iter_str().collect())
+    )
+    .unwrap();
+}
+
+#[cfg(test)]
+mod test {
+    use crate::dsl::plonk::debugger::{debug_step, TraceGranularity};
+    use crate::dsl::plonk::split::compute_debug_information;
+
+    #[test]
+    fn test_debug_step() {
+        let mut debug_information = compute_debug_information();
+
+        let mut out = String::new();
+        assert!(debug_step(
+            &debug_information,
+            0,
+            TraceGranularity::Region,
+            &mut out
+        ));
+        assert!(out.contains("; ---- "));
+
+        // a wrong hint for an LDM entry makes the step fail, and the trace names the entry
+        let read_hint = debug_information.read_hints[1]
+            .iter()
+            .find(|read_hint| read_hint.key == "composition_commitment")
+            .unwrap()
+            .clone();
+        assert!(!read_hint.positions.is_empty());
+        debug_information.all_information.witnesses[1][read_hint.positions[0]][0] ^= 1;
+
+        let mut out = String::new();
+        assert!(!debug_step(
+            &debug_information,
+            1,
+            TraceGranularity::Region,
+            &mut out
+        ));
+        assert!(out.contains("step 1 fails"));
+        let report = out
+            .split("LDM entries whose hints differ from the values that were written:")
+            .nth(1)
+            .unwrap();
+        assert!(report.contains("- `composition_commitment`, written in step 0"));
+    }
+}
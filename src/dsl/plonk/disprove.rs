@@ -0,0 +1,1367 @@
+use crate::algorithms::winternitz::{WinternitzPublicKey, WinternitzSecretKey};
+use crate::dsl::plonk::debugger::new_exec;
+use crate::dsl::plonk::split::{PlonkAllInformation, Witness};
+use crate::dsl::plonk::taproot_export::nums_internal_key;
+use crate::dsl::plonk::winternitz_handoff::{
+    pull_and_verify, state_digest, state_digest_script, MAX_STACK_SIZE,
+};
+use anyhow::{Error, Result};
+use bitcoin::absolute::LockTime;
+use bitcoin::hashes::{hash160, Hash};
+use bitcoin::key::TapTweak;
+use bitcoin::opcodes::all::{
+    OP_0NOTEQUAL, OP_CHECKSIG, OP_CHECKSIGVERIFY, OP_ELSE, OP_ENDIF, OP_EQUAL, OP_EQUALVERIFY,
+    OP_IF, OP_NOTIF, OP_NUMEQUAL, OP_NUMEQUALVERIFY, OP_PUSHBYTES_0, OP_PUSHNUM_1, OP_VERIFY,
+};
+use bitcoin::opcodes::Opcode;
+use bitcoin::script::{read_scriptint, Instruction};
+use bitcoin::secp256k1::{Keypair, Message, Secp256k1, XOnlyPublicKey};
+use bitcoin::sighash::{Prevouts, SighashCache};
+use bitcoin::taproot::{LeafVersion, TapLeafHash, TaprootBuilder, TaprootSpendInfo};
+use bitcoin::transaction::Version;
+use bitcoin::{Amount, OutPoint, ScriptBuf, Sequence, TapSighashType, Transaction, TxIn, TxOut};
+use bitcoin_circle_stark::treepp::*;
+use bitcoin_script_dsl::builtins::table::utils::OP_HINT;
+use bitcoin_scriptexec::Exec;
+use stwo_prover::core::fields::m31::P;
+
+/// Length of the message that the operator signs for each step.
+pub const CLAIM_LEN: usize = 20;
+
+/// What the operator claims about a step: the hints that it runs with, and the digest of the state
+/// after it (see `winternitz_handoff::state_digest`), together with a Winternitz signature on both.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StepClaim {
+    pub state_digest: Vec<u8>,
+    pub hints: Witness,
+    pub signature: Witness,
+}
+
+impl StepClaim {
+    /// Sign the claim with the key of the step.
+    pub fn new(secret_key: &WinternitzSecretKey, state_digest: Vec<u8>, hints: Witness) -> Self {
+        let signature = secret_key.sign(&claim_message(&hints_commitment(&hints), &state_digest));
+        Self {
+            state_digest,
+            hints,
+            signature,
+        }
+    }
+}
+
+/// The one-time key that signs the claim on the step `step_idx`.
+pub fn claim_key(seed: &[u8], step_idx: usize) -> WinternitzSecretKey {
+    WinternitzSecretKey::from_seed(
+        &[seed, b"claim", &(step_idx as u32).to_le_bytes()].concat(),
+        CLAIM_LEN,
+    )
+}
+
+/// Generate the keys for the claims on all the steps.
+pub fn generate_claim_keys(seed: &[u8], num_steps: usize) -> Vec<WinternitzSecretKey> {
+    (0..num_steps)
+        .map(|step_idx| claim_key(seed, step_idx))
+        .collect()
+}
+
+/// The claims of an honest operator, i.e., with the hints and the outputs of the steps.
+pub fn honest_claims(
+    all_information: &PlonkAllInformation,
+    secret_keys: &[WinternitzSecretKey],
+) -> Vec<StepClaim> {
+    assert_eq!(all_information.scripts.len(), secret_keys.len());
+
+    secret_keys
+        .iter()
+        .enumerate()
+        .map(|(idx, secret_key)| {
+            StepClaim::new(
+                secret_key,
+                state_digest(&all_information.outputs[idx]),
+                all_information.witnesses[idx].clone(),
+            )
+        })
+        .collect()
+}
+
+/// The commitment to the hints of a step, which is `c_0 = HASH160(h_0)` and
+/// `c_k = HASH160(c_{k-1} || h_k)`, or `HASH160("")` if there is no hint.
+pub fn hints_commitment(hints: &[Vec<u8>]) -> Vec<u8> {
+    let hash = |data: &[u8]| hash160::Hash::hash(data).to_byte_array().to_vec();
+
+    match hints.split_first() {
+        None => hash(&[]),
+        Some((first, rest)) => rest.iter().fold(hash(first), |cur, hint| {
+            hash(&[cur.as_slice(), hint].concat())
+        }),
+    }
+}
+
+/// The script that computes the commitment to the `num_hints` elements at the bottom of the stack,
+/// without moving them, as `hints_commitment`.
+///
+/// output:
+/// - commitment
+pub fn hints_commitment_script(num_hints: usize) -> Script {
+    script! {
+        if num_hints == 0 {
+            OP_0 OP_HASH160
+        } else {
+            OP_DEPTH OP_1SUB OP_PICK OP_HASH160
+            for k in 1..num_hints {
+                OP_DEPTH OP_1SUB { k } OP_SUB OP_PICK
+                OP_CAT OP_HASH160
+            }
+        }
+    }
+}
+
+/// The message that the operator signs for a step, which is the HASH160 of the commitment to the
+/// hints followed by the digest of the state after the step.
+pub fn claim_message(hints_commitment: &[u8], state_digest: &[u8]) -> Vec<u8> {
+    hash160::Hash::hash(&[hints_commitment, state_digest].concat())
+        .to_byte_array()
+        .to_vec()
+}
+
+/// The script that computes the message of a claim.
+///
+/// stack:
+/// - hints commitment
+/// - state digest
+///
+/// output:
+/// - message
+pub fn claim_message_script() -> Script {
+    script! {
+        OP_CAT OP_HASH160
+    }
+}
+
+/// A step script whose checks leave a boolean instead of aborting (see `booleanize_checks`).
+pub struct BooleanizedStep {
+    pub script: Script,
+    /// The number of checks that have been turned into a boolean.
+    pub num_checks: usize,
+    /// The number of checks in the conditionals with more than one `OP_ELSE`, which still abort.
+    pub num_uncovered_checks: usize,
+}
+
+/// The opcode that leaves the boolean that a VERIFY-family opcode checks. For `OP_VERIFY`, it is
+/// `OP_0NOTEQUAL`, since `OP_IF` only takes a minimal boolean.
+fn boolean_form(op: Opcode) -> Option<Opcode> {
+    if op == OP_VERIFY {
+        Some(OP_0NOTEQUAL)
+    } else if op == OP_EQUALVERIFY {
+        Some(OP_EQUAL)
+    } else if op == OP_NUMEQUALVERIFY {
+        Some(OP_NUMEQUAL)
+    } else if op == OP_CHECKSIGVERIFY {
+        Some(OP_CHECKSIG)
+    } else {
+        None
+    }
+}
+
+/// A part of the script of a step, as `booleanize_checks` sees it.
+enum StepPart {
+    /// Opcodes and pushes that are not checks.
+    Bytes(Vec<u8>),
+    /// A VERIFY-family opcode.
+    Check(Opcode),
+    /// An `OP_IF` or an `OP_NOTIF` with the branches between its `OP_ELSE`s.
+    Conditional(Opcode, Vec<Vec<StepPart>>),
+}
+
+impl StepPart {
+    /// Split the instructions from `*i` on into parts, up to the `OP_ELSE` or the `OP_ENDIF` that
+    /// closes the enclosing conditional, or the end of the script.
+    fn parse(bytes: &[u8], instructions: &[(usize, Instruction)], i: &mut usize) -> Vec<Self> {
+        let mut parts = vec![];
+
+        while let Some((pos, instruction)) = instructions.get(*i) {
+            let end = instructions
+                .get(*i + 1)
+                .map_or(bytes.len(), |(next, _)| *next);
+            let op = match instruction {
+                Instruction::Op(op) => Some(*op),
+                Instruction::PushBytes(_) => None,
+            };
+
+            match op {
+                Some(op) if op == OP_ELSE || op == OP_ENDIF => break,
+                Some(op) if op == OP_IF || op == OP_NOTIF => {
+                    *i += 1;
+                    let mut branches = vec![Self::parse(bytes, instructions, i)];
+                    while instructions.get(*i).map(|(_, instruction)| instruction)
+                        == Some(&Instruction::Op(OP_ELSE))
+                    {
+                        *i += 1;
+                        branches.push(Self::parse(bytes, instructions, i));
+                    }
+                    assert_eq!(
+                        instructions.get(*i).map(|(_, instruction)| instruction),
+                        Some(&Instruction::Op(OP_ENDIF)),
+                        "the conditionals of a step are closed"
+                    );
+                    parts.push(StepPart::Conditional(op, branches));
+                }
+                Some(op) if boolean_form(op).is_some() => parts.push(StepPart::Check(op)),
+                _ => match parts.last_mut() {
+                    Some(StepPart::Bytes(out)) => out.extend_from_slice(&bytes[*pos..end]),
+                    _ => parts.push(StepPart::Bytes(bytes[*pos..end].to_vec())),
+                },
+            }
+            *i += 1;
+        }
+
+        parts
+    }
+
+    /// Whether `booleanize_checks` turns a check in the part into a boolean.
+    fn is_covered(&self) -> bool {
+        match self {
+            StepPart::Bytes(_) => false,
+            StepPart::Check(_) => true,
+            StepPart::Conditional(_, branches) => {
+                branches.len() <= 2 && branches.iter().flatten().any(StepPart::is_covered)
+            }
+        }
+    }
+}
+
+/// Writes the script of `booleanize_checks` and counts its checks.
+#[derive(Default)]
+struct Booleanizer {
+    out: Vec<u8>,
+    num_checks: usize,
+    num_uncovered_checks: usize,
+}
+
+impl Booleanizer {
+    /// Write a sequence of parts with its checks turned into guards on the rest of it. A branch
+    /// of a conditional (`is_branch`) leaves a boolean for whether it has passed its checks.
+    fn booleanize(&mut self, parts: &[StepPart], is_branch: bool) {
+        let mut num_guards = 0;
+
+        for part in parts.iter() {
+            match part {
+                StepPart::Bytes(bytes) => self.out.extend_from_slice(bytes),
+                StepPart::Check(op) => {
+                    self.out.push(boolean_form(*op).unwrap().to_u8());
+                    self.out.push(OP_IF.to_u8());
+                    self.num_checks += 1;
+                    num_guards += 1;
+                }
+                StepPart::Conditional(op, branches) if part.is_covered() => {
+                    self.out.push(op.to_u8());
+                    self.booleanize(&branches[0], true);
+                    self.out.push(OP_ELSE.to_u8());
+                    match branches.get(1) {
+                        Some(branch) => self.booleanize(branch, true),
+                        None => self.out.push(OP_PUSHNUM_1.to_u8()),
+                    }
+                    self.out.push(OP_ENDIF.to_u8());
+
+                    // the boolean of the branch that has run is checked as any other check
+                    self.out.push(OP_IF.to_u8());
+                    num_guards += 1;
+                }
+                StepPart::Conditional(..) => self.write(part),
+            }
+        }
+
+        if is_branch {
+            self.out.push(OP_PUSHNUM_1.to_u8());
+        }
+        for _ in 0..num_guards {
+            self.out.push(OP_ELSE.to_u8());
+            self.out.push(OP_PUSHBYTES_0.to_u8());
+            self.out.push(OP_ENDIF.to_u8());
+        }
+    }
+
+    /// Write a part as it is.
+    fn write(&mut self, part: &StepPart) {
+        match part {
+            StepPart::Bytes(bytes) => self.out.extend_from_slice(bytes),
+            StepPart::Check(op) => {
+                self.out.push(op.to_u8());
+                self.num_uncovered_checks += 1;
+            }
+            StepPart::Conditional(op, branches) => {
+                self.out.push(op.to_u8());
+                for (i, branch) in branches.iter().enumerate() {
+                    if i > 0 {
+                        self.out.push(OP_ELSE.to_u8());
+                    }
+                    for part in branch.iter() {
+                        self.write(part);
+                    }
+                }
+                self.out.push(OP_ENDIF.to_u8());
+            }
+        }
+    }
+}
+
+/// Rewrite the script of a step so that a failing check does not abort it.
+///
+/// Every VERIFY-family opcode becomes the opcode that leaves the boolean followed by `OP_IF`, so
+/// the rest of the step only runs if the check passes, and the script ends with an
+/// `OP_ELSE OP_0 OP_ENDIF` for each of them. A step that passes all its checks leaves its output
+/// as before, and one that fails a check leaves an empty element on the top of the stack, above
+/// whatever the step has computed so far.
+///
+/// The checks inside a conditional are closed in the same way at the end of their branch, which
+/// then leaves a boolean for whether it has passed them, and the `OP_ENDIF` of the conditional
+/// is followed by an `OP_IF` on that boolean, as for a check of its own. Only the checks in a
+/// conditional with more than one `OP_ELSE`, which the steps do not have, are kept and counted
+/// in `num_uncovered_checks`.
+///
+/// Failures that are not checks, such as a numeric opcode on a hint of more than 4 bytes, still
+/// abort the script, which is why the disprove leaves check the hints before the step (see
+/// `hints_check_script`).
+pub fn booleanize_checks(script: &Script) -> BooleanizedStep {
+    let instructions = script
+        .instruction_indices()
+        .map(|res| res.expect("the script of a step is well-formed"))
+        .collect::<Vec<_>>();
+
+    let mut i = 0;
+    let parts = StepPart::parse(script.as_bytes(), &instructions, &mut i);
+    assert_eq!(
+        i,
+        instructions.len(),
+        "the script of a step has no stray OP_ELSE or OP_ENDIF"
+    );
+
+    let mut booleanizer = Booleanizer::default();
+    booleanizer.booleanize(&parts, false);
+
+    BooleanizedStep {
+        script: Script::from_bytes(booleanizer.out),
+        num_checks: booleanizer.num_checks,
+        num_uncovered_checks: booleanizer.num_uncovered_checks,
+    }
+}
+
+/// The largest hint, in bytes, that the steps take as a number.
+const MAX_NUM_HINT_LEN: usize = 4;
+
+/// Whether a hint has the form of the honest hint `honest_hint`, as `hints_check_script` checks.
+pub fn has_hint_form(hint: &[u8], honest_hint: &[u8]) -> bool {
+    if honest_hint.len() <= MAX_NUM_HINT_LEN {
+        read_scriptint(hint).is_ok_and(|v| (0..P as i64).contains(&v))
+    } else {
+        hint.len() == honest_hint.len()
+    }
+}
+
+/// The script that checks that the `hints.len()` elements at the bottom of the stack have the
+/// form of the hints `hints` of an honest operator, without moving them, so that no opcode of the
+/// step can abort on them.
+///
+/// A hint of at most 4 bytes is an M31 element, which has to be a number of at most 4 bytes in
+/// `[0, P)`, and a longer one is a digest, which has to have the length of the honest one.
+///
+/// A number that is not minimally encoded still aborts the range check, but it only breaks the
+/// standardness rules, not the consensus ones, which do not require minimal numbers in tapscript.
+///
+/// output:
+/// - whether all the hints have their form
+pub fn hints_check_script(hints: &[Vec<u8>]) -> Script {
+    script! {
+        OP_TRUE
+        for (k, hint) in hints.iter().enumerate() {
+            OP_DEPTH OP_1SUB { k } OP_SUB OP_PICK
+            if hint.len() <= MAX_NUM_HINT_LEN {
+                OP_SIZE { MAX_NUM_HINT_LEN + 1 } OP_LESSTHAN
+                OP_IF
+                    0 { P as usize } OP_WITHIN
+                OP_ELSE
+                    OP_DROP OP_0
+                OP_ENDIF
+            } else {
+                OP_SIZE { hint.len() } OP_EQUAL OP_NIP
+            }
+            OP_BOOLAND
+        }
+    }
+}
+
+/// Drop everything on the stack.
+fn clear_stack() -> Script {
+    script! {
+        for _ in 0..MAX_STACK_SIZE / 2 {
+            OP_DEPTH 1 OP_GREATERTHAN
+            OP_IF OP_2DROP OP_ENDIF
+        }
+        OP_DEPTH
+        OP_IF OP_DROP OP_ENDIF
+    }
+}
+
+/// The disprove leaves of an optimistic deployment of the split verifier.
+///
+/// The operator does not run the steps on-chain. Instead, it posts a claim on every step in the
+/// assert transaction (see `get_assert_tx`): the hints of the step and the digest of the state
+/// after it, signed with a Winternitz key. The leaf of a step takes the signed claim on the step
+/// before it, which gives its input state, and the signed claim on the step itself, which gives
+/// its hints and its claimed output. It then checks the form of the hints (see
+/// `hints_check_script`) and runs the step with its checks turned into a boolean (see
+/// `booleanize_checks`), and succeeds if a hint does not have its form or a check fails, or if
+/// all of them pass and the output differs from the claimed one. A challenger spends the leaf of the first step that does
+/// not hold up (see `find_step_to_disprove`).
+///
+/// Since the hints are the ones that the operator has signed, a challenger cannot make a check
+/// fail with hints of its own, and an honest operator cannot be disproved.
+pub fn get_disprove_scripts(
+    all_information: &PlonkAllInformation,
+    public_keys: &[WinternitzPublicKey],
+) -> Vec<Script> {
+    assert_eq!(all_information.scripts.len(), public_keys.len());
+
+    (0..all_information.scripts.len())
+        .map(|script_idx| {
+            let step = booleanize_checks(&all_information.scripts[script_idx]);
+
+            script! {
+                // hint:
+                // - signature on the claim on the previous step (if not the first step)
+                // - hints commitment of the previous step (if not the first step)
+                // - input write hash and read hash (if not the first step)
+                // - signature on the claim on the step
+                // - claimed digest of the output state
+                // - hints of the step
+
+                if script_idx > 0 {
+                    { pull_and_verify(&public_keys[script_idx - 1]) }
+                    OP_TOALTSTACK
+                    OP_HINT
+                    OP_HINT OP_HINT
+                    OP_2DUP { state_digest_script() }
+                    OP_3 OP_ROLL OP_SWAP { claim_message_script() }
+                    OP_FROMALTSTACK OP_EQUALVERIFY
+                }
+
+                { pull_and_verify(&public_keys[script_idx]) }
+                OP_HINT
+                { hints_commitment_script(all_information.witnesses[script_idx].len()) }
+                OP_OVER { claim_message_script() }
+                OP_ROT OP_EQUALVERIFY
+                OP_TOALTSTACK
+
+                // altstack:
+                // - claimed digest of the output state
+
+                { hints_check_script(&all_information.witnesses[script_idx]) }
+                OP_IF
+                    { step.script }
+                OP_ELSE
+                    // a hint that the step could abort on fails the step
+                    OP_0
+                OP_ENDIF
+
+                OP_SIZE OP_0NOTEQUAL
+                OP_IF
+                    // the step passes all its checks, so its output has to differ
+                    { state_digest_script() }
+                    OP_FROMALTSTACK
+                    OP_EQUAL OP_NOT OP_VERIFY
+                    OP_DEPTH
+                    0
+                    OP_EQUALVERIFY
+                OP_ELSE
+                    // the step fails a check
+                    { clear_stack() }
+                OP_ENDIF
+                OP_TRUE
+            }
+        })
+        .collect()
+}
+
+/// The leaf of the disprove output that pays the operator once `blocks` blocks have passed since
+/// the last assert transaction without a challenger disproving a step.
+///
+/// hint:
+/// - Schnorr signature of the operator
+pub fn get_operator_payout_script(operator_key: &XOnlyPublicKey, blocks: u16) -> Script {
+    script! {
+        { blocks as usize }
+        OP_CSV
+        OP_DROP
+        { operator_key.serialize().to_vec() }
+        OP_CHECKSIG
+    }
+}
+
+/// Build the taproot output that commits to all the disprove leaves and to the payout leaf of the
+/// operator, with the NUMS point as the internal key. The payout leaf, which is the one that an
+/// honest operator spends, weighs as much as all the disprove leaves together, so that its
+/// control block is the shortest.
+pub fn get_disprove_spend_info(
+    disprove_scripts: &[Script],
+    payout_script: &Script,
+) -> TaprootSpendInfo {
+    let secp = Secp256k1::verification_only();
+
+    TaprootBuilder::with_huffman_tree(
+        disprove_scripts
+            .iter()
+            .map(|script| (1, script.clone()))
+            .chain([(disprove_scripts.len() as u32, payout_script.clone())]),
+    )
+    .unwrap()
+    .finalize(&secp, nums_internal_key())
+    .unwrap()
+}
+
+/// The witness that disproves the step `idx`, from the claims in the assert transaction and the
+/// input state of the step, whose digest is the claimed one.
+pub fn get_disprove_witness(claims: &[StepClaim], input_state: &[Vec<u8>], idx: usize) -> Witness {
+    let mut witness = vec![];
+
+    if idx > 0 {
+        let previous = &claims[idx - 1];
+        witness.extend(previous.signature.iter().cloned());
+        witness.push(hints_commitment(&previous.hints));
+        witness.extend(input_state.iter().cloned());
+    }
+
+    witness.extend(claims[idx].signature.iter().cloned());
+    witness.push(claims[idx].state_digest.clone());
+    witness.extend(claims[idx].hints.iter().cloned());
+
+    witness
+}
+
+fn execute(script: Script, witness: Witness) -> (bool, Exec) {
+    let mut exec = new_exec(script, witness);
+    loop {
+        if exec.exec_next().is_err() {
+            break;
+        }
+    }
+    (exec.result().unwrap().success, exec)
+}
+
+/// Run a step with the input state and the hints, and return its output state, or `None` if it
+/// fails.
+pub fn execute_step(
+    script: &Script,
+    input_state: &[Vec<u8>],
+    hints: &[Vec<u8>],
+) -> Option<Witness> {
+    let script = script! {
+        for elem in input_state.iter() {
+            { elem.clone() }
+        }
+        { script.clone() }
+        OP_TOALTSTACK OP_TOALTSTACK
+        OP_DEPTH
+        0
+        OP_EQUALVERIFY
+        OP_TRUE
+    };
+
+    let (success, exec) = execute(script, hints.to_vec());
+    if !success {
+        return None;
+    }
+
+    let mut output = exec.altstack().iter_str().collect::<Vec<Vec<u8>>>();
+    output.reverse();
+    Some(output)
+}
+
+/// Replay the claims of the operator from the first step, and return the first step whose hints
+/// do not have the form of the honest ones (see `has_hint_form`), that fails, or whose output is
+/// not the claimed one, together with its input state.
+pub fn find_step_to_disprove(
+    all_information: &PlonkAllInformation,
+    claims: &[StepClaim],
+) -> Option<(usize, Witness)> {
+    assert_eq!(all_information.scripts.len(), claims.len());
+
+    let mut input_state = vec![];
+    for (idx, claim) in claims.iter().enumerate() {
+        let honest_hints = &all_information.witnesses[idx];
+        if claim.hints.len() != honest_hints.len()
+            || !claim
+                .hints
+                .iter()
+                .zip(honest_hints.iter())
+                .all(|(hint, honest_hint)| has_hint_form(hint, honest_hint))
+        {
+            return Some((idx, input_state));
+        }
+
+        match execute_step(&all_information.scripts[idx], &input_state, &claim.hints) {
+            Some(output) if state_digest(&output) == claim.state_digest => input_state = output,
+            _ => return Some((idx, input_state)),
+        }
+    }
+    None
+}
+
+/// The leaf of the output that the assert transaction spends for the step, which checks the
+/// signature on the claim against the hints and the digest in the witness, so that the hints are
+/// published on-chain for the challengers.
+///
+/// hint:
+/// - signature on the claim
+/// - claimed digest of the output state
+/// - hints of the step
+/// - Schnorr signature of the operator
+pub fn get_commit_script(
+    public_key: &WinternitzPublicKey,
+    num_hints: usize,
+    operator_key: &XOnlyPublicKey,
+) -> Script {
+    script! {
+        { pull_and_verify(public_key) }
+        OP_HINT
+        { hints_commitment_script(num_hints) }
+        OP_SWAP { claim_message_script() }
+        OP_EQUALVERIFY
+
+        OP_TOALTSTACK
+        for _ in 0..num_hints / 2 {
+            OP_2DROP
+        }
+        if num_hints % 2 == 1 {
+            OP_DROP
+        }
+        OP_FROMALTSTACK
+        { operator_key.serialize().to_vec() }
+        OP_CHECKSIG
+    }
+}
+
+/// Build the taproot output with the commit leaf as its only leaf, and the NUMS point as the
+/// internal key.
+pub fn get_commit_spend_info(commit_script: &Script) -> TaprootSpendInfo {
+    let secp = Secp256k1::verification_only();
+
+    TaprootBuilder::new()
+        .add_leaf(0, commit_script.clone())
+        .unwrap()
+        .finalize(&secp, nums_internal_key())
+        .unwrap()
+}
+
+/// The largest weight of a standard transaction.
+pub const MAX_STANDARD_TX_WEIGHT: u64 = 400_000;
+
+/// Build and sign the assert transactions, which spend the commit outputs of the steps through
+/// their commit leaves, in order, and carry the total, minus a fee for each of them, to the
+/// disprove output.
+///
+/// The witnesses of the claims on all the steps can weigh more than a standard transaction, so
+/// the steps are split into transactions of at most `max_weight`, each with as many steps as it
+/// can take. Each transaction after the first spends the output of the one before it, with the key
+/// of the operator, as its first input, and the last one sends to the disprove output.
+pub fn get_assert_txs(
+    commit_prevouts: &[(OutPoint, TxOut)],
+    commit_scripts: &[Script],
+    claims: &[StepClaim],
+    disprove_spend_info: &TaprootSpendInfo,
+    fee: u64,
+    max_weight: u64,
+    operator_keypair: &Keypair,
+) -> Result<Vec<Transaction>> {
+    if commit_prevouts.len() != claims.len() || commit_scripts.len() != claims.len() {
+        return Err(Error::msg(
+            "The assert transaction needs a commit output and a commit leaf for every step",
+        ));
+    }
+
+    let secp = Secp256k1::new();
+    let chain_script_pubkey =
+        ScriptBuf::new_p2tr(&secp, operator_keypair.x_only_public_key().0, None);
+    let disprove_script_pubkey = ScriptBuf::new_p2tr_tweaked(disprove_spend_info.output_key());
+
+    let mut txs: Vec<Transaction> = vec![];
+    let mut start = 0;
+    while start < claims.len() {
+        let chain = txs.last().map(|tx| {
+            (
+                OutPoint {
+                    txid: tx.compute_txid(),
+                    vout: 0,
+                },
+                tx.output[0].clone(),
+            )
+        });
+        let sign = |end: usize| {
+            let script_pubkey = if end == claims.len() {
+                disprove_script_pubkey.clone()
+            } else {
+                chain_script_pubkey.clone()
+            };
+            sign_assert_tx(
+                chain.as_ref(),
+                &commit_prevouts[start..end],
+                &commit_scripts[start..end],
+                &claims[start..end],
+                script_pubkey,
+                fee,
+                operator_keypair,
+            )
+        };
+
+        let mut end = start + 1;
+        let mut tx = sign(end)?;
+        if tx.weight().to_wu() > max_weight {
+            return Err(Error::msg(format!(
+                "The claim on the step {} does not fit in an assert transaction",
+                start
+            )));
+        }
+        while end < claims.len() {
+            let next = sign(end + 1)?;
+            if next.weight().to_wu() > max_weight {
+                break;
+            }
+            tx = next;
+            end += 1;
+        }
+
+        txs.push(tx);
+        start = end;
+    }
+
+    Ok(txs)
+}
+
+/// Build and sign an assert transaction that spends the output of the previous assert transaction
+/// `chain`, if any, and the commit outputs of some of the steps, and sends the total, minus the
+/// fee, to `script_pubkey`.
+fn sign_assert_tx(
+    chain: Option<&(OutPoint, TxOut)>,
+    commit_prevouts: &[(OutPoint, TxOut)],
+    commit_scripts: &[Script],
+    claims: &[StepClaim],
+    script_pubkey: ScriptBuf,
+    fee: u64,
+    operator_keypair: &Keypair,
+) -> Result<Transaction> {
+    let prevouts = chain
+        .into_iter()
+        .chain(commit_prevouts.iter())
+        .collect::<Vec<_>>();
+
+    let total = prevouts
+        .iter()
+        .map(|(_, prevout)| prevout.value.to_sat())
+        .sum::<u64>();
+    let amount = total
+        .checked_sub(fee)
+        .ok_or_else(|| Error::msg("The commit outputs cannot pay the fee of the assert"))?;
+
+    let mut tx = Transaction {
+        version: Version::TWO,
+        lock_time: LockTime::ZERO,
+        input: prevouts
+            .iter()
+            .map(|(outpoint, _)| TxIn {
+                previous_output: *outpoint,
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence::MAX,
+                witness: bitcoin::Witness::new(),
+            })
+            .collect(),
+        output: vec![TxOut {
+            value: Amount::from_sat(amount),
+            script_pubkey,
+        }],
+    };
+
+    let secp = Secp256k1::new();
+    let prevouts = prevouts
+        .iter()
+        .map(|(_, prevout)| prevout.clone())
+        .collect::<Vec<_>>();
+
+    let mut witnesses = vec![];
+    {
+        let mut sighash_cache = SighashCache::new(&tx);
+
+        if chain.is_some() {
+            let sighash = sighash_cache.taproot_key_spend_signature_hash(
+                0,
+                &Prevouts::All(&prevouts),
+                TapSighashType::Default,
+            )?;
+            let signature = secp.sign_schnorr_no_aux_rand(
+                &Message::from_digest(sighash.to_byte_array()),
+                &operator_keypair.tap_tweak(&secp, None).to_keypair(),
+            );
+
+            let mut witness = bitcoin::Witness::new();
+            witness.push(signature.serialize());
+            witnesses.push(witness);
+        }
+
+        for (claim, commit_script) in claims.iter().zip(commit_scripts.iter()) {
+            let spend_info = get_commit_spend_info(commit_script);
+            let control_block = spend_info
+                .control_block(&(commit_script.clone(), LeafVersion::TapScript))
+                .unwrap();
+
+            let sighash = sighash_cache.taproot_script_spend_signature_hash(
+                witnesses.len(),
+                &Prevouts::All(&prevouts),
+                TapLeafHash::from_script(commit_script, LeafVersion::TapScript),
+                TapSighashType::Default,
+            )?;
+            let signature = secp.sign_schnorr_no_aux_rand(
+                &Message::from_digest(sighash.to_byte_array()),
+                operator_keypair,
+            );
+
+            let mut witness = bitcoin::Witness::new();
+            for elem in claim.signature.iter() {
+                witness.push(elem);
+            }
+            witness.push(&claim.state_digest);
+            for elem in claim.hints.iter() {
+                witness.push(elem);
+            }
+            witness.push(signature.serialize());
+            witness.push(commit_script.as_bytes());
+            witness.push(control_block.serialize());
+            witnesses.push(witness);
+        }
+    }
+
+    for (input, witness) in tx.input.iter_mut().zip(witnesses) {
+        input.witness = witness;
+    }
+
+    Ok(tx)
+}
+
+/// Read the claims on all the steps from the witnesses of the assert transactions, skipping the
+/// input that spends the previous assert transaction (see `get_assert_txs`).
+pub fn extract_claims(
+    assert_txs: &[Transaction],
+    public_keys: &[WinternitzPublicKey],
+) -> Result<Vec<StepClaim>> {
+    let inputs = assert_txs
+        .iter()
+        .enumerate()
+        .flat_map(|(i, tx)| tx.input.iter().skip(if i == 0 { 0 } else { 1 }))
+        .collect::<Vec<_>>();
+    if inputs.len() != public_keys.len() {
+        return Err(Error::msg(
+            "The assert transactions do not have an input for every step",
+        ));
+    }
+
+    let mut claims = vec![];
+    for (input, public_key) in inputs.into_iter().zip(public_keys.iter()) {
+        // signature, digest, hints, Schnorr signature, commit leaf, control block
+        let elements = input
+            .witness
+            .iter()
+            .map(|elem| elem.to_vec())
+            .collect::<Vec<_>>();
+        let signature_len = public_key.signature_len();
+        if elements.len() < signature_len + 4 {
+            return Err(Error::msg(
+                "The witness of the assert transaction is too short",
+            ));
+        }
+
+        claims.push(StepClaim {
+            state_digest: elements[signature_len].clone(),
+            hints: elements[signature_len + 1..elements.len() - 3].to_vec(),
+            signature: elements[0..signature_len].to_vec(),
+        });
+    }
+
+    Ok(claims)
+}
+
+/// Build the transaction that disproves the step `idx` by spending the disprove output of the
+/// last assert transaction, and sends its amount, minus the fee, to the destination.
+pub fn get_disprove_tx(
+    assert_tx: &Transaction,
+    disprove_scripts: &[Script],
+    disprove_spend_info: &TaprootSpendInfo,
+    idx: usize,
+    disprove_witness: &[Vec<u8>],
+    destination: ScriptBuf,
+    fee: u64,
+) -> Result<Transaction> {
+    let control_block = disprove_spend_info
+        .control_block(&(disprove_scripts[idx].clone(), LeafVersion::TapScript))
+        .ok_or_else(|| Error::msg("The disprove output has no leaf for the step"))?;
+
+    let amount = assert_tx.output[0]
+        .value
+        .to_sat()
+        .checked_sub(fee)
+        .ok_or_else(|| Error::msg("The disprove output cannot pay the fee of the disprove"))?;
+
+    let mut witness = bitcoin::Witness::new();
+    for elem in disprove_witness.iter() {
+        witness.push(elem);
+    }
+    witness.push(disprove_scripts[idx].as_bytes());
+    witness.push(control_block.serialize());
+
+    Ok(Transaction {
+        version: Version::TWO,
+        lock_time: LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint {
+                txid: assert_tx.compute_txid(),
+                vout: 0,
+            },
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::MAX,
+            witness,
+        }],
+        output: vec![TxOut {
+            value: Amount::from_sat(amount),
+            script_pubkey: destination,
+        }],
+    })
+}
+
+/// Build and sign the transaction that pays the operator from the disprove output of the last
+/// assert transaction through the payout leaf, which is only valid `blocks` blocks after the
+/// assert transaction, and sends its amount, minus the fee, to the destination.
+pub fn get_operator_payout_tx(
+    assert_tx: &Transaction,
+    payout_script: &Script,
+    disprove_spend_info: &TaprootSpendInfo,
+    blocks: u16,
+    destination: ScriptBuf,
+    fee: u64,
+    operator_keypair: &Keypair,
+) -> Result<Transaction> {
+    let control_block = disprove_spend_info
+        .control_block(&(payout_script.clone(), LeafVersion::TapScript))
+        .ok_or_else(|| Error::msg("The disprove output has no payout leaf"))?;
+
+    let amount = assert_tx.output[0]
+        .value
+        .to_sat()
+        .checked_sub(fee)
+        .ok_or_else(|| Error::msg("The disprove output cannot pay the fee of the payout"))?;
+
+    let mut tx = Transaction {
+        version: Version::TWO,
+        lock_time: LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint {
+                txid: assert_tx.compute_txid(),
+                vout: 0,
+            },
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::from_height(blocks),
+            witness: bitcoin::Witness::new(),
+        }],
+        output: vec![TxOut {
+            value: Amount::from_sat(amount),
+            script_pubkey: destination,
+        }],
+    };
+
+    let secp = Secp256k1::new();
+    let sighash = SighashCache::new(&tx).taproot_script_spend_signature_hash(
+        0,
+        &Prevouts::All(&[assert_tx.output[0].clone()]),
+        TapLeafHash::from_script(payout_script, LeafVersion::TapScript),
+        TapSighashType::Default,
+    )?;
+    let signature = secp.sign_schnorr_no_aux_rand(
+        &Message::from_digest(sighash.to_byte_array()),
+        operator_keypair,
+    );
+
+    let mut witness = bitcoin::Witness::new();
+    witness.push(signature.serialize());
+    witness.push(payout_script.as_bytes());
+    witness.push(control_block.serialize());
+    tx.input[0].witness = witness;
+
+    Ok(tx)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::dsl::plonk::disprove::{
+        booleanize_checks, execute, execute_step, extract_claims, find_step_to_disprove,
+        generate_claim_keys, get_assert_txs, get_commit_script, get_commit_spend_info,
+        get_disprove_scripts, get_disprove_spend_info, get_disprove_tx, get_disprove_witness,
+        get_operator_payout_script, get_operator_payout_tx, honest_claims, StepClaim,
+        MAX_STANDARD_TX_WEIGHT,
+    };
+    use crate::dsl::plonk::split::compute_debug_information;
+    use crate::dsl::plonk::winternitz_handoff::state_digest;
+    use bitcoin::hashes::Hash;
+    use bitcoin::key::TapTweak;
+    use bitcoin::secp256k1::schnorr::Signature;
+    use bitcoin::secp256k1::{Keypair, Message, Secp256k1};
+    use bitcoin::sighash::{Prevouts, SighashCache};
+    use bitcoin::taproot::{LeafVersion, TapLeafHash};
+    use bitcoin::{Amount, OutPoint, ScriptBuf, TapSighashType, Transaction, TxOut, Txid};
+    use bitcoin_circle_stark::treepp::*;
+    use bitcoin_scriptexec::{Exec, ExecCtx, Options, TxTemplate};
+
+    /// Run the leaf `script` of the input `input_idx` of `tx` with its witness, without the leaf
+    /// and the control block.
+    fn execute_input(
+        script: &Script,
+        tx: &Transaction,
+        prevouts: &[TxOut],
+        input_idx: usize,
+    ) -> bool {
+        let witness = tx.input[input_idx]
+            .witness
+            .iter()
+            .map(|elem| elem.to_vec())
+            .collect::<Vec<_>>();
+        let mut exec = Exec::new(
+            ExecCtx::Tapscript,
+            Options::default(),
+            TxTemplate {
+                tx: tx.clone(),
+                prevouts: prevouts.to_vec(),
+                input_idx,
+                taproot_annex_scriptleaf: Some((
+                    TapLeafHash::from_script(script, LeafVersion::TapScript),
+                    None,
+                )),
+            },
+            script.clone(),
+            witness[0..witness.len() - 2].to_vec(),
+        )
+        .unwrap();
+        loop {
+            if exec.exec_next().is_err() {
+                break;
+            }
+        }
+        exec.result().unwrap().success
+    }
+
+    #[test]
+    fn test_booleanize_checks() {
+        let step = |a: usize, b: usize| {
+            script! {
+                { a } 5 OP_EQUALVERIFY
+                OP_1 OP_IF { b } 7 OP_EQUALVERIFY OP_ENDIF
+                9
+            }
+        };
+
+        let booleanized = booleanize_checks(&step(5, 7));
+        assert_eq!(booleanized.num_checks, 2);
+        assert_eq!(booleanized.num_uncovered_checks, 0);
+
+        // a passing check leaves the output
+        let script = script! {
+            { booleanized.script }
+            9 OP_EQUAL
+        };
+        assert!(execute_script(script).success);
+
+        // a failing check leaves an empty element instead of aborting, in a conditional or not
+        for (a, b) in [(4, 7), (5, 6)] {
+            let script = script! {
+                { booleanize_checks(&step(a, b)).script }
+                OP_SIZE OP_0 OP_EQUAL OP_NIP
+            };
+            assert!(execute_script(script).success);
+        }
+
+        // the checks in a conditional with more than one OP_ELSE are kept
+        let booleanized = booleanize_checks(&script! {
+            OP_1 OP_IF 7 7 OP_EQUALVERIFY OP_ELSE OP_ELSE OP_ENDIF
+        });
+        assert_eq!(booleanized.num_checks, 0);
+        assert_eq!(booleanized.num_uncovered_checks, 1);
+    }
+
+    #[test]
+    fn test_disprove() {
+        let debug_information = compute_debug_information();
+        let all_information = &debug_information.all_information;
+        let num_steps = all_information.scripts.len();
+
+        // no check of a step aborts in the disprove leaves
+        for script in all_information.scripts.iter() {
+            assert_eq!(booleanize_checks(script).num_uncovered_checks, 0);
+        }
+
+        let secret_keys = generate_claim_keys(b"disprove", num_steps);
+        let public_keys = secret_keys
+            .iter()
+            .map(|key| key.public_key())
+            .collect::<Vec<_>>();
+
+        let secp = Secp256k1::new();
+        let operator_keypair = Keypair::from_seckey_slice(&secp, &[1u8; 32]).unwrap();
+        let operator_key = operator_keypair.x_only_public_key().0;
+
+        let blocks = 144;
+        let payout_script = get_operator_payout_script(&operator_key, blocks);
+        let disprove_scripts = get_disprove_scripts(all_information, &public_keys);
+        let spend_info = get_disprove_spend_info(&disprove_scripts, &payout_script);
+
+        let run = |claims: &[StepClaim], input_state: &[Vec<u8>], idx: usize| {
+            let witness = get_disprove_witness(claims, input_state, idx);
+            execute(disprove_scripts[idx].clone(), witness).0
+        };
+        let honest_input = |idx: usize| {
+            if idx == 0 {
+                vec![]
+            } else {
+                all_information.outputs[idx - 1].clone()
+            }
+        };
+
+        // an honest operator cannot be disproved
+        let claims = honest_claims(all_information, &secret_keys);
+        assert_eq!(find_step_to_disprove(all_information, &claims), None);
+        for idx in [0, 1, num_steps - 1] {
+            assert!(!run(&claims, &honest_input(idx), idx));
+        }
+
+        // nor with hints of the challenger, which do not match the signed ones
+        let read_hint = debug_information.read_hints[1]
+            .iter()
+            .find(|read_hint| read_hint.key == "composition_commitment")
+            .unwrap()
+            .clone();
+        let mut tampered_claims = claims.clone();
+        tampered_claims[1].hints[read_hint.positions[0]][0] ^= 1;
+        assert!(!run(&tampered_claims, &honest_input(1), 1));
+
+        // a wrong claimed output is disproved
+        let mut wrong_output = claims.clone();
+        wrong_output[2] = StepClaim::new(
+            &secret_keys[2],
+            vec![0u8; 20],
+            all_information.witnesses[2].clone(),
+        );
+        assert_eq!(
+            find_step_to_disprove(all_information, &wrong_output),
+            Some((2, honest_input(2)))
+        );
+        assert!(run(&wrong_output, &honest_input(2), 2));
+
+        // a number hint of more than 4 bytes, which the step could abort on, or out of the range
+        // of M31 is disproved, even with the honest output
+        let num_hint = all_information.witnesses[1]
+            .iter()
+            .position(|hint| !hint.is_empty() && hint.len() <= 4)
+            .unwrap();
+        for bad_hint in [vec![0xff; 5], vec![0xff, 0xff, 0xff, 0x7f]] {
+            let mut hints = all_information.witnesses[1].clone();
+            hints[num_hint] = bad_hint;
+            let mut bad_claims = claims.clone();
+            bad_claims[1] = StepClaim::new(&secret_keys[1], claims[1].state_digest.clone(), hints);
+            assert_eq!(
+                find_step_to_disprove(all_information, &bad_claims),
+                Some((1, honest_input(1)))
+            );
+            assert!(run(&bad_claims, &honest_input(1), 1));
+        }
+
+        // a wrong hint in step 1 makes a step fail, which is disproved, where the operator claims
+        // the states that its hints lead to
+        let mut dishonest_claims = vec![];
+        let mut input_state = vec![];
+        for idx in 0..num_steps {
+            let mut hints = all_information.witnesses[idx].clone();
+            if idx == 1 {
+                hints[read_hint.positions[0]][0] ^= 1;
+            }
+            let digest = match execute_step(&all_information.scripts[idx], &input_state, &hints) {
+                Some(output) => {
+                    let digest = state_digest(&output);
+                    input_state = output;
+                    digest
+                }
+                None => {
+                    input_state = all_information.outputs[idx].clone();
+                    state_digest(&input_state)
+                }
+            };
+            dishonest_claims.push(StepClaim::new(&secret_keys[idx], digest, hints));
+        }
+
+        let (idx, input_state) = find_step_to_disprove(all_information, &dishonest_claims).unwrap();
+        assert!(idx >= 1);
+        assert!(execute_step(
+            &all_information.scripts[idx],
+            &input_state,
+            &dishonest_claims[idx].hints
+        )
+        .is_none());
+        assert!(run(&dishonest_claims, &input_state, idx));
+
+        // the claims go on-chain in the assert transactions, and the challenger reads them back
+        let commit_scripts = (0..num_steps)
+            .map(|idx| {
+                get_commit_script(
+                    &public_keys[idx],
+                    all_information.witnesses[idx].len(),
+                    &operator_key,
+                )
+            })
+            .collect::<Vec<_>>();
+        let commit_prevouts = commit_scripts
+            .iter()
+            .enumerate()
+            .map(|(idx, commit_script)| {
+                let spend_info = get_commit_spend_info(commit_script);
+                (
+                    OutPoint {
+                        txid: Txid::all_zeros(),
+                        vout: idx as u32,
+                    },
+                    TxOut {
+                        value: Amount::from_sat(10_000),
+                        script_pubkey: ScriptBuf::new_p2tr_tweaked(spend_info.output_key()),
+                    },
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let get_txs = |max_weight: u64| {
+            get_assert_txs(
+                &commit_prevouts,
+                &commit_scripts,
+                &dishonest_claims,
+                &spend_info,
+                1_000,
+                max_weight,
+                &operator_keypair,
+            )
+            .unwrap()
+        };
+
+        for max_weight in [MAX_STANDARD_TX_WEIGHT, 100_000] {
+            let assert_txs = get_txs(max_weight);
+            assert_eq!(
+                extract_claims(&assert_txs, &public_keys).unwrap(),
+                dishonest_claims
+            );
+            assert_eq!(
+                assert_txs.last().unwrap().output[0].script_pubkey,
+                ScriptBuf::new_p2tr_tweaked(spend_info.output_key())
+            );
+
+            // every transaction is standard, spends the one before it with the key of the
+            // operator, and the commit leaves accept its witness
+            let mut step = 0;
+            for (i, tx) in assert_txs.iter().enumerate() {
+                assert!(tx.weight().to_wu() <= max_weight);
+
+                let mut prevouts = vec![];
+                if i > 0 {
+                    prevouts.push(assert_txs[i - 1].output[0].clone());
+                }
+                let num_commits = tx.input.len() - prevouts.len();
+                prevouts.extend(
+                    commit_prevouts[step..step + num_commits]
+                        .iter()
+                        .map(|(_, prevout)| prevout.clone()),
+                );
+
+                if i > 0 {
+                    assert_eq!(
+                        tx.input[0].previous_output.txid,
+                        assert_txs[i - 1].compute_txid()
+                    );
+                    let sighash = SighashCache::new(tx)
+                        .taproot_key_spend_signature_hash(
+                            0,
+                            &Prevouts::All(&prevouts),
+                            TapSighashType::Default,
+                        )
+                        .unwrap();
+                    let signature = Signature::from_slice(&tx.input[0].witness[0]).unwrap();
+                    secp.verify_schnorr(
+                        &signature,
+                        &Message::from_digest(sighash.to_byte_array()),
+                        &operator_key.tap_tweak(&secp, None).0.to_x_only_public_key(),
+                    )
+                    .unwrap();
+                }
+
+                for input_idx in tx.input.len() - num_commits..tx.input.len() {
+                    assert!(execute_input(
+                        &commit_scripts[step],
+                        tx,
+                        &prevouts,
+                        input_idx
+                    ));
+                    step += 1;
+                }
+            }
+            assert_eq!(step, num_steps);
+
+            if max_weight < MAX_STANDARD_TX_WEIGHT {
+                assert!(assert_txs.len() > 1);
+            }
+        }
+
+        // the challenger disproves the step from what is on-chain
+        let assert_txs = get_txs(MAX_STANDARD_TX_WEIGHT);
+        let assert_tx = assert_txs.last().unwrap();
+        let claims_on_chain = extract_claims(&assert_txs, &public_keys).unwrap();
+        let disprove_witness = get_disprove_witness(&claims_on_chain, &input_state, idx);
+        let disprove_tx = get_disprove_tx(
+            assert_tx,
+            &disprove_scripts,
+            &spend_info,
+            idx,
+            &disprove_witness,
+            ScriptBuf::new_p2tr(&secp, operator_key, None),
+            1_000,
+        )
+        .unwrap();
+        assert_eq!(
+            disprove_tx.input[0].previous_output.txid,
+            assert_tx.compute_txid()
+        );
+        assert_eq!(
+            disprove_tx.input[0].witness.len(),
+            disprove_witness.len() + 2
+        );
+
+        // without a disprove, the operator takes the disprove output after the timelock, and not
+        // before
+        for (sequence, success) in [(blocks, true), (blocks - 1, false)] {
+            let payout_tx = get_operator_payout_tx(
+                assert_tx,
+                &payout_script,
+                &spend_info,
+                sequence,
+                ScriptBuf::new_p2tr(&secp, operator_key, None),
+                1_000,
+                &operator_keypair,
+            )
+            .unwrap();
+            assert_eq!(
+                execute_input(
+                    &payout_script,
+                    &payout_tx,
+                    &[assert_tx.output[0].clone()],
+                    0
+                ),
+                success
+            );
+        }
+    }
+}
@@ -1,21 +1,39 @@
+use crate::algorithms::domain_point::DomainPointSource;
 use crate::dsl::plonk::hints::fiat_shamir::FiatShamirHints;
 use crate::dsl::plonk::hints::fold::PerQueryFoldHints;
 use crate::dsl::plonk::hints::quotients::PerQueryQuotientHint;
+use crate::dsl::plonk::mask::{Mask, SampleBatchLayout};
+use bitcoin_script_dsl::builtins::hash::HashVar;
 use stwo_prover::core::channel::Sha256Channel;
 use stwo_prover::core::pcs::PcsConfig;
 use stwo_prover::examples::plonk::prove_fibonacci_plonk;
 
 pub const LOG_N_ROWS: u32 = 5;
 
+/// The channel of the verifier, which has to be the one that the proof is generated under, i.e.,
+/// stwo's `Sha256MerkleChannel`.
+pub type PlonkChannelVar = HashVar;
+
 mod fiat_shamir;
 mod fold;
 mod prepare;
 mod quotients;
 
 pub struct Hints {
+    pub mask: Mask,
+    /// The sample batches, in the order of stwo's `ColumnSampleBatch`, which determines the
+    /// layout of the quotient steps.
+    pub sample_batches: Vec<SampleBatchLayout>,
     pub fiat_shamir_hints: FiatShamirHints,
     pub per_query_quotients_hints: Vec<PerQueryQuotientHint>,
     pub per_query_fold_hints: Vec<PerQueryFoldHints>,
+    /// The log size of the precomputed tree of the points and twiddles.
+    pub precomputed_tree_log_size: usize,
+    /// Where the second step takes the circle points and the twiddles of the queries from.
+    pub domain_point_source: DomainPointSource,
+    /// For each query, the first earlier query with the same parent, whose per-query steps
+    /// already cover it.
+    pub duplicate_queries: Vec<Option<usize>>,
 }
 
 impl Hints {
@@ -34,7 +52,11 @@ impl Hints {
         )
         .unwrap();
 
-        let prepare_output = prepare::compute_prepare_hints(&fiat_shamir_output, &proof).unwrap();
+        let mask = Mask::plonk();
+
+        let prepare_output =
+            prepare::compute_prepare_hints(&fiat_shamir_output, &proof, &mask).unwrap();
+        let sample_batches = prepare_output.sample_batches.clone();
 
         let (quotients_output, per_query_quotients_hints) =
             quotients::compute_quotients_hints(&fiat_shamir_output, &prepare_output);
@@ -46,10 +68,49 @@ impl Hints {
             &quotients_output,
         );
 
+        let precomputed_tree_log_size = prepare::precomputed_tree_log_size(&fiat_shamir_output);
+        let duplicate_queries = find_duplicate_queries(&fiat_shamir_output.queries_parents);
+
         Hints {
+            mask,
+            sample_batches,
             fiat_shamir_hints,
             per_query_quotients_hints,
             per_query_fold_hints,
+            precomputed_tree_log_size,
+            domain_point_source: DomainPointSource::default(),
+            duplicate_queries,
         }
     }
+
+    /// The earlier query that the query duplicates, if any.
+    pub fn duplicate_of(&self, query_idx: usize) -> Option<usize> {
+        self.duplicate_queries[query_idx]
+    }
+}
+
+/// For each query, find the first earlier query with the same parent.
+///
+/// Two queries with the same parent open the same twin leaves in every tree and fold into the
+/// same values, so the per-query steps of the later one would verify exactly the same things, and
+/// the verification takes its skip leaf instead.
+pub fn find_duplicate_queries(queries_parents: &[usize]) -> Vec<Option<usize>> {
+    queries_parents
+        .iter()
+        .enumerate()
+        .map(|(i, parent)| queries_parents[0..i].iter().position(|p| p == parent))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::dsl::plonk::hints::find_duplicate_queries;
+
+    #[test]
+    fn test_find_duplicate_queries() {
+        assert_eq!(
+            find_duplicate_queries(&[5, 3, 5, 7, 3, 5]),
+            vec![None, None, Some(0), None, Some(1), Some(0)]
+        );
+    }
 }
@@ -1,8 +1,12 @@
+use crate::algorithms::precomputed_tree_builder::precomputed_tree_builder;
 use crate::dsl::plonk::hints::fiat_shamir::FiatShamirOutput;
+use crate::dsl::plonk::hints::LOG_N_ROWS;
+use crate::dsl::plonk::mask::{Mask, SampleBatchLayout};
 use bitcoin_circle_stark::constraints::ColumnLineCoeffs;
 use bitcoin_circle_stark::precomputed_merkle_tree::PrecomputedMerkleTree;
 use itertools::Itertools;
 use std::iter::zip;
+use std::sync::Arc;
 use stwo_prover::core::backend::cpu::quotients::denominator_inverses;
 use stwo_prover::core::constraints::complex_conjugate_line_coeffs_normalized;
 use stwo_prover::core::fields::cm31::CM31;
@@ -14,19 +18,29 @@ use stwo_prover::core::vcs::sha256_merkle::Sha256MerkleHasher;
 /// Prepare Output
 pub struct PrepareOutput {
     /// Precomputed Merkle tree for point and twiddles.
-    pub precomputed_merkle_tree: PrecomputedMerkleTree,
+    pub precomputed_merkle_tree: Arc<PrecomputedMerkleTree>,
 
     /// Expected denominator inverses.
     pub denominator_inverses_expected: Vec<Vec<Vec<CM31>>>,
 
-    /// Column line coefficients.
+    /// The layout of the sample batches, in the order of stwo's `ColumnSampleBatch`.
+    pub sample_batches: Vec<SampleBatchLayout>,
+
+    /// Column line coefficients, one for each sample batch.
     pub column_line_coeffs: Vec<ColumnLineCoeffs>,
 }
 
+/// The log size of the precomputed tree of the points and twiddles, which covers the twin
+/// positions of the largest evaluation domain.
+pub fn precomputed_tree_log_size(fs_output: &FiatShamirOutput) -> usize {
+    (fs_output.max_column_log_degree_bound + fs_output.fri_log_blowup_factor - 1) as usize
+}
+
 /// prepare output for quotients and verifier hints
 pub fn compute_prepare_hints(
     fs_output: &FiatShamirOutput,
     proof: &StarkProof<Sha256MerkleHasher>,
+    mask: &Mask,
 ) -> Result<PrepareOutput, VerificationError> {
     let column_size: Vec<u32> = fs_output
         .commitment_scheme_column_log_sizes
@@ -41,53 +55,24 @@ pub fn compute_prepare_hints(
         fs_output.max_column_log_degree_bound + fs_output.fri_log_blowup_factor
     );
 
-    assert_eq!(proof.commitment_scheme_proof.sampled_values.0.len(), 4);
-
-    // trace polynomials, four polynomials
-    assert_eq!(proof.commitment_scheme_proof.sampled_values.0[0].len(), 4);
-    for i in 0..4 {
-        assert_eq!(
-            proof.commitment_scheme_proof.sampled_values.0[0][i].len(),
-            1
-        );
-    }
-
-    // interaction polynomials, eight polynomials
-    assert_eq!(proof.commitment_scheme_proof.sampled_values.0[1].len(), 8);
-    for i in 0..4 {
-        assert_eq!(
-            proof.commitment_scheme_proof.sampled_values.0[1][i].len(),
-            1
-        );
-    }
-    for i in 4..8 {
-        assert_eq!(
-            proof.commitment_scheme_proof.sampled_values.0[1][i].len(),
-            2
-        );
-    }
-
-    // constant polynomials, four polynomials
-    assert_eq!(proof.commitment_scheme_proof.sampled_values.0[2].len(), 4);
-    for i in 0..4 {
-        assert_eq!(
-            proof.commitment_scheme_proof.sampled_values.0[2][i].len(),
-            1
-        );
-    }
-
-    // composition polynomials, four polynomials
-    assert_eq!(proof.commitment_scheme_proof.sampled_values.0[3].len(), 4);
-    for i in 0..4 {
-        assert_eq!(
-            proof.commitment_scheme_proof.sampled_values.0[3][i].len(),
-            1
-        );
+    // check that the sampled points follow the mask
+    assert_eq!(
+        proof.commitment_scheme_proof.sampled_values.0.len(),
+        mask.offsets.len()
+    );
+    let oods_point = fs_output.sampled_points[3][0][0];
+    let trace_step = CanonicCoset::new(LOG_N_ROWS).step();
+    for (tree_offsets, tree_points) in mask.offsets.iter().zip_eq(fs_output.sampled_points.iter()) {
+        for (column_offsets, column_points) in tree_offsets.iter().zip_eq(tree_points.iter()) {
+            for (&offset, &point) in column_offsets.iter().zip_eq(column_points.iter()) {
+                assert_eq!(point, oods_point + trace_step.mul_signed(offset).into_ef());
+            }
+        }
     }
 
-    let precomputed_merkle_tree = PrecomputedMerkleTree::new(
-        (fs_output.max_column_log_degree_bound + fs_output.fri_log_blowup_factor - 1) as usize,
-    );
+    let precomputed_merkle_tree = precomputed_tree_builder()
+        .tree(precomputed_tree_log_size(fs_output))
+        .unwrap();
 
     // construct the list of samples
     // Answer FRI queries.
@@ -125,114 +110,54 @@ pub fn compute_prepare_hints(
             .collect()
     };
 
-    let column_line_coeffs_trace = ColumnLineCoeffs::from_values_and_point(
-        &[
-            samples[0][0].value,
-            samples[1][0].value,
-            samples[2][0].value,
-            samples[3][0].value,
-        ],
-        samples[0][0].point,
-    );
-
-    for i in 0..4 {
-        assert_eq!(
-            expected_line_coeffs[0][i].0,
-            column_line_coeffs_trace.fp_imag_div_y_imag[i],
-        );
-        assert_eq!(
-            expected_line_coeffs[0][i].1,
-            column_line_coeffs_trace.cross_term[i],
-        );
-    }
-
-    let column_line_coeffs_interaction = ColumnLineCoeffs::from_values_and_point(
-        &[
-            samples[4][0].value,
-            samples[5][0].value,
-            samples[6][0].value,
-            samples[7][0].value,
-            samples[8][0].value,
-            samples[9][0].value,
-            samples[10][0].value,
-            samples[11][0].value,
-        ],
-        samples[4][0].point,
-    );
-
-    for i in 0..8 {
-        assert_eq!(
-            expected_line_coeffs[0][4 + i].0,
-            column_line_coeffs_interaction.fp_imag_div_y_imag[i],
-        );
-        assert_eq!(
-            expected_line_coeffs[0][4 + i].1,
-            column_line_coeffs_interaction.cross_term[i],
-        );
-    }
-
-    let column_line_coeffs_interaction_shifted = ColumnLineCoeffs::from_values_and_point(
-        &[
-            samples[8][1].value,
-            samples[9][1].value,
-            samples[10][1].value,
-            samples[11][1].value,
-        ],
-        samples[8][1].point,
-    );
-
-    for i in 0..4 {
-        assert_eq!(
-            expected_line_coeffs[1][i].0,
-            column_line_coeffs_interaction_shifted.fp_imag_div_y_imag[i],
-        );
-        assert_eq!(
-            expected_line_coeffs[1][i].1,
-            column_line_coeffs_interaction_shifted.cross_term[i],
-        );
-    }
-
-    let column_line_coeffs_constant = ColumnLineCoeffs::from_values_and_point(
-        &[
-            samples[12][0].value,
-            samples[13][0].value,
-            samples[14][0].value,
-            samples[15][0].value,
-        ],
-        samples[12][0].point,
-    );
+    // the layout of the quotients follows the sample batches of stwo, and the mask must agree
+    // with it, as the DSL reads the OODS values through the mask
+    let distinct_offsets = mask.distinct_offsets();
+    assert_eq!(column_sample_batches.len(), distinct_offsets.len());
 
-    for i in 0..4 {
-        assert_eq!(
-            expected_line_coeffs[0][12 + i].0,
-            column_line_coeffs_constant.fp_imag_div_y_imag[i],
-        );
-        assert_eq!(
-            expected_line_coeffs[0][12 + i].1,
-            column_line_coeffs_constant.cross_term[i],
-        );
-    }
+    let sample_batches = column_sample_batches
+        .iter()
+        .map(|sample_batch| {
+            let offset = *distinct_offsets
+                .iter()
+                .find(|&&offset| {
+                    sample_batch.point == oods_point + trace_step.mul_signed(offset).into_ef()
+                })
+                .expect("the sample point is not in the mask");
+
+            let layout = SampleBatchLayout {
+                offset,
+                columns: sample_batch
+                    .columns_and_values
+                    .iter()
+                    .map(|(column, _)| *column)
+                    .collect(),
+            };
+            assert_eq!(layout.columns, mask.columns_at(offset));
 
-    let column_line_coeffs_composition = ColumnLineCoeffs::from_values_and_point(
-        &[
-            samples[16][0].value,
-            samples[17][0].value,
-            samples[18][0].value,
-            samples[19][0].value,
-        ],
-        samples[16][0].point,
-    );
+            layout
+        })
+        .collect_vec();
 
-    for i in 0..4 {
-        assert_eq!(
-            expected_line_coeffs[0][16 + i].0,
-            column_line_coeffs_composition.fp_imag_div_y_imag[i],
-        );
-        assert_eq!(
-            expected_line_coeffs[0][16 + i].1,
-            column_line_coeffs_composition.cross_term[i],
-        );
-    }
+    let column_line_coeffs = column_sample_batches
+        .iter()
+        .zip_eq(expected_line_coeffs.iter())
+        .map(|(sample_batch, expected)| {
+            let values = sample_batch
+                .columns_and_values
+                .iter()
+                .map(|(_, value)| *value)
+                .collect_vec();
+            let coeffs = ColumnLineCoeffs::from_values_and_point(&values, sample_batch.point);
+
+            for (i, (fp_imag_div_y_imag, cross_term)) in expected.iter().enumerate() {
+                assert_eq!(*fp_imag_div_y_imag, coeffs.fp_imag_div_y_imag[i]);
+                assert_eq!(*cross_term, coeffs.cross_term[i]);
+            }
+
+            coeffs
+        })
+        .collect_vec();
 
     let commitment_domain =
         CanonicCoset::new(fs_output.max_column_log_degree_bound + fs_output.fri_log_blowup_factor)
@@ -251,12 +176,7 @@ pub fn compute_prepare_hints(
     Ok(PrepareOutput {
         precomputed_merkle_tree,
         denominator_inverses_expected,
-        column_line_coeffs: vec![
-            column_line_coeffs_trace,
-            column_line_coeffs_interaction,
-            column_line_coeffs_interaction_shifted,
-            column_line_coeffs_constant,
-            column_line_coeffs_composition,
-        ],
+        sample_batches,
+        column_line_coeffs,
     })
 }
@@ -0,0 +1,297 @@
+use crate::dsl::plonk::hints::Hints;
+use crate::dsl::plonk::ldm_schema::LdmSchema;
+use crate::dsl::plonk::ldm_trace::{LdmValue, StepLdm};
+use anyhow::Result;
+use bitcoin_script_dsl::constraint_system::ConstraintSystemRef;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+/// The lifetime of an LDM entry, as indices into the step sequence.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LdmLifetime {
+    /// The step that writes the entry.
+    pub written_in: usize,
+    /// The last step that reads the entry, or `None` if the entry is dead.
+    pub last_read_in: Option<usize>,
+}
+
+/// The liveness of the LDM entries over the step sequence.
+///
+/// The state carried from one step to the next is only the write hash and the read hash, so an
+/// entry costs hashing when it is written and whenever it is read. An entry that is never read
+/// only adds to the hashing work, and the steps skip writing it (see `LiveLdm`).
+#[derive(Clone, Debug)]
+pub struct LdmLiveness {
+    pub num_steps: usize,
+    pub lifetimes: BTreeMap<String, LdmLifetime>,
+}
+
+impl LdmLiveness {
+    pub fn from_schema(schema: &LdmSchema) -> Self {
+        let lifetimes = schema
+            .keys
+            .iter()
+            .map(|(key, declared)| {
+                let lifetime = LdmLifetime {
+                    written_in: schema.step_index(declared.producer),
+                    last_read_in: declared
+                        .consumers
+                        .iter()
+                        .map(|consumer| schema.step_index(*consumer))
+                        .max(),
+                };
+                (key.clone(), lifetime)
+            })
+            .collect();
+
+        Self {
+            num_steps: schema.steps.len(),
+            lifetimes,
+        }
+    }
+
+    /// The entries that are written but never read.
+    pub fn dead_entries(&self) -> Vec<&str> {
+        self.lifetimes
+            .iter()
+            .filter(|(_, lifetime)| lifetime.last_read_in.is_none())
+            .map(|(key, _)| key.as_str())
+            .collect()
+    }
+
+    /// The entries that are live after the step, i.e., written in or before the step and read
+    /// after it.
+    pub fn live_after(&self, step: usize) -> Vec<&str> {
+        self.lifetimes
+            .iter()
+            .filter(|(_, lifetime)| {
+                lifetime.written_in <= step && lifetime.last_read_in.is_some_and(|s| s > step)
+            })
+            .map(|(key, _)| key.as_str())
+            .collect()
+    }
+
+    /// The entries whose last read is in the step.
+    pub fn last_read_in(&self, step: usize) -> Vec<&str> {
+        self.lifetimes
+            .iter()
+            .filter(|(_, lifetime)| lifetime.last_read_in == Some(step))
+            .map(|(key, _)| key.as_str())
+            .collect()
+    }
+
+    /// A table of the number of live entries after each step and the entries that die in it.
+    pub fn report(&self, schema: &LdmSchema) -> String {
+        let mut out = String::new();
+        for (idx, step) in schema.steps.iter().enumerate() {
+            let dying = self.last_read_in(idx);
+            writeln!(
+                out,
+                "{:>2} {:<40} live after: {:>4}, last read: {:>4}",
+                idx,
+                step.to_string(),
+                self.live_after(idx).len(),
+                dying.len()
+            )
+            .unwrap();
+        }
+
+        let dead = self.dead_entries();
+        if !dead.is_empty() {
+            writeln!(
+                out,
+                "entries that are never read, whose writes are skipped:"
+            )
+            .unwrap();
+            for key in dead.iter() {
+                writeln!(out, "- `{}`", key).unwrap();
+            }
+        }
+        out
+    }
+}
+
+/// An LDM that skips the writes of the entries that no step reads.
+///
+/// The steps are generated with this in front of the LDM, so a dead entry costs no hashing in the
+/// step that computes it. Reading a skipped entry fails, as the entry is never written.
+pub struct LiveLdm<L: StepLdm> {
+    pub ldm: L,
+    pub dead: BTreeSet<String>,
+}
+
+impl<L: StepLdm> LiveLdm<L> {
+    pub fn new(ldm: L, liveness: &LdmLiveness) -> Self {
+        Self {
+            ldm,
+            dead: liveness
+                .dead_entries()
+                .into_iter()
+                .map(str::to_string)
+                .collect(),
+        }
+    }
+
+    /// The LDM for the steps of the Plonk verifier, which skips the dead entries of its schema.
+    pub fn plonk(hints: &Hints, ldm: L) -> Self {
+        Self::new(ldm, &LdmLiveness::from_schema(&LdmSchema::plonk(hints)))
+    }
+}
+
+impl<L: StepLdm> StepLdm for LiveLdm<L> {
+    fn init(&mut self, cs: &ConstraintSystemRef) -> Result<()> {
+        self.ldm.init(cs)
+    }
+
+    fn read<T: LdmValue>(&mut self, key: impl ToString) -> Result<T> {
+        self.ldm.read(key)
+    }
+
+    fn write<T: LdmValue>(&mut self, key: impl ToString, value: &T) -> Result<()> {
+        let key = key.to_string();
+        if self.dead.contains(&key) {
+            return Ok(());
+        }
+        self.ldm.write(key, value)
+    }
+
+    fn save(&mut self) -> Result<()> {
+        self.ldm.save()
+    }
+
+    fn check(&mut self) -> Result<()> {
+        self.ldm.check()
+    }
+
+    fn state(&self) -> [Vec<u8>; 2] {
+        self.ldm.state()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::dsl::plonk::ldm_liveness::{LdmLiveness, LiveLdm};
+    use crate::dsl::plonk::ldm_schema::{LdmKeySchema, LdmSchema, LdmType};
+    use crate::dsl::plonk::ldm_trace::StepLdm;
+    use crate::dsl::plonk::split::StepKind;
+    use bitcoin_circle_stark::treepp::*;
+    use bitcoin_script_dsl::builtins::m31::M31Var;
+    use bitcoin_script_dsl::bvar::AllocVar;
+    use bitcoin_script_dsl::compiler::Compiler;
+    use bitcoin_script_dsl::constraint_system::{ConstraintSystem, ConstraintSystemRef};
+    use bitcoin_script_dsl::ldm::LDM;
+    use bitcoin_script_dsl::test_program;
+    use std::collections::{BTreeMap, BTreeSet};
+    use stwo_prover::core::fields::m31::M31;
+
+    #[test]
+    fn test_liveness() {
+        let steps = vec![
+            StepKind::FiatShamir1,
+            StepKind::FiatShamir2AndConstraintNum,
+            StepKind::ConstraintDenom,
+            StepKind::Cleanup,
+        ];
+
+        let mut keys = BTreeMap::new();
+        let mut declare = |key: &str, producer: StepKind, consumers: &[StepKind]| {
+            keys.insert(
+                key.to_string(),
+                LdmKeySchema {
+                    ty: LdmType::QM31,
+                    producer,
+                    consumers: consumers.iter().copied().collect::<BTreeSet<_>>(),
+                },
+            );
+        };
+        declare(
+            "z",
+            StepKind::FiatShamir1,
+            &[StepKind::FiatShamir2AndConstraintNum],
+        );
+        declare(
+            "oods_x",
+            StepKind::FiatShamir1,
+            &[
+                StepKind::FiatShamir2AndConstraintNum,
+                StepKind::ConstraintDenom,
+            ],
+        );
+        declare("last_layer", StepKind::FiatShamir1, &[]);
+
+        let schema = LdmSchema { steps, keys };
+        let liveness = LdmLiveness::from_schema(&schema);
+
+        assert_eq!(liveness.dead_entries(), vec!["last_layer"]);
+        assert_eq!(liveness.live_after(0), vec!["oods_x", "z"]);
+        assert_eq!(liveness.live_after(1), vec!["oods_x"]);
+        assert!(liveness.live_after(2).is_empty());
+        assert_eq!(liveness.last_read_in(1), vec!["z"]);
+        assert_eq!(liveness.last_read_in(2), vec!["oods_x"]);
+    }
+
+    #[test]
+    fn test_live_ldm() {
+        // three steps: the first writes `a` and `b`, the second reads `a`, and the last checks
+        // the LDM, either compiled for the sizes of the scripts or executed
+        let run = |dead: &[&str], execute: bool| {
+            let mut ldm = LiveLdm {
+                ldm: LDM::new(),
+                dead: dead.iter().map(|key| key.to_string()).collect(),
+            };
+
+            let mut sizes = vec![];
+            let mut finish = |cs: ConstraintSystemRef, ldm: &LiveLdm<LDM>| {
+                if execute {
+                    test_program(
+                        cs,
+                        script! {
+                            { ldm.state()[0].clone() }
+                            { ldm.state()[1].clone() }
+                        },
+                    )
+                    .unwrap();
+                } else {
+                    sizes.push(Compiler::compile(cs).unwrap().script.len());
+                }
+            };
+
+            let cs = ConstraintSystem::new_ref();
+            ldm.init(&cs).unwrap();
+            let a = M31Var::new_hint(&cs, M31::from(3u32)).unwrap();
+            let b = &a + &a;
+            ldm.write("a", &a).unwrap();
+            ldm.write("b", &b).unwrap();
+            ldm.save().unwrap();
+            finish(cs, &ldm);
+
+            let cs = ConstraintSystem::new_ref();
+            ldm.init(&cs).unwrap();
+            let a: M31Var = ldm.read("a").unwrap();
+            a.equalverify(&M31Var::new_constant(&cs, M31::from(3u32)).unwrap())
+                .unwrap();
+            ldm.save().unwrap();
+            finish(cs, &ldm);
+
+            let cs = ConstraintSystem::new_ref();
+            ldm.init(&cs).unwrap();
+            ldm.check().unwrap();
+            ldm.save().unwrap();
+            finish(cs, &ldm);
+
+            sizes
+        };
+
+        // the steps pass with and without the dead write
+        run(&[], true);
+        run(&["b"], true);
+
+        // skipping the write of `b` shortens the step that writes it, and the check at the end
+        // does not get longer
+        let all = run(&[], false);
+        let live = run(&["b"], false);
+        assert!(live[0] < all[0]);
+        assert_eq!(live[1], all[1]);
+        assert!(live[2] <= all[2]);
+    }
+}
@@ -0,0 +1,544 @@
+use crate::dsl::plonk::hints::{Hints, LOG_N_ROWS};
+use crate::dsl::plonk::ldm_trace::{LdmAccess, LdmAccessKind};
+use crate::dsl::plonk::mask::{
+    alpha_power_key, chunk_alpha_power, column_line_coeffs_chunks, needed_alpha_powers,
+    quotient_chunks, sample_point_name, TREE_NAMES,
+};
+use crate::dsl::plonk::split::{step_sequence, StepKind};
+use anyhow::{Error, Result};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use stwo_prover::core::prover::N_QUERIES;
+
+/// The type of an LDM entry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LdmType {
+    M31,
+    CM31,
+    QM31,
+    Hash,
+}
+
+impl LdmType {
+    /// The type of the entry from the name of the variable type, e.g., `QM31Var`.
+    ///
+    /// A digest is a `HashVar` under SHA-256 and a `StrVar` under other hashers.
+    pub fn from_type_name(type_name: &str) -> Option<Self> {
+        match type_name {
+            "M31Var" => Some(LdmType::M31),
+            "CM31Var" => Some(LdmType::CM31),
+            "QM31Var" => Some(LdmType::QM31),
+            "HashVar" | "StrVar" => Some(LdmType::Hash),
+            _ => None,
+        }
+    }
+}
+
+/// The declaration of an LDM entry.
+#[derive(Clone, Debug)]
+pub struct LdmKeySchema {
+    pub ty: LdmType,
+    /// The step that writes the entry.
+    pub producer: StepKind,
+    /// The steps that read the entry.
+    pub consumers: BTreeSet<StepKind>,
+}
+
+/// The declaration of all the LDM entries that the steps communicate through.
+#[derive(Clone, Debug)]
+pub struct LdmSchema {
+    /// The steps in the order they are executed.
+    pub steps: Vec<StepKind>,
+    pub keys: BTreeMap<String, LdmKeySchema>,
+}
+
+impl LdmSchema {
+    /// The schema of the Plonk verifier.
+    pub fn plonk(hints: &Hints) -> Self {
+        let mut schema = Self {
+            steps: step_sequence(hints),
+            keys: BTreeMap::new(),
+        };
+
+        let batches = &hints.sample_batches;
+        let chunks = quotient_chunks(batches);
+        let fri_layers = hints.fiat_shamir_hints.fri_layer_commitments.len();
+
+        // part1: the Fiat-Shamir transcript
+        for key in ["z", "alpha", "composition_fold_random_coeff"] {
+            schema.declare(key, LdmType::QM31, StepKind::FiatShamir1);
+            schema.consume(key, StepKind::FiatShamir2AndConstraintNum);
+        }
+        schema.declare(
+            "composition_commitment",
+            LdmType::Hash,
+            StepKind::FiatShamir1,
+        );
+        schema.consume(
+            "composition_commitment",
+            StepKind::FiatShamir2AndConstraintNum,
+        );
+
+        for (tree, name) in TREE_NAMES.iter().enumerate() {
+            let num_values: usize = hints.mask.offsets[tree].iter().map(|v| v.len()).sum();
+            for i in 0..num_values {
+                let key = format!("{}_oods_value_{}", name, i);
+                schema.declare(&key, LdmType::QM31, StepKind::FiatShamir1);
+                if *name == "composition" {
+                    schema.consume(&key, StepKind::ConstraintDenom);
+                } else {
+                    schema.consume(&key, StepKind::FiatShamir2AndConstraintNum);
+                }
+            }
+        }
+
+        schema.declare(
+            "line_batch_random_coeff",
+            LdmType::QM31,
+            StepKind::FiatShamir1,
+        );
+        schema.consume("line_batch_random_coeff", StepKind::PairVanishingAndAlphas);
+
+        schema.declare(
+            "fri_fold_random_coeff",
+            LdmType::QM31,
+            StepKind::FiatShamir1,
+        );
+        for i in 0..fri_layers {
+            schema.declare(
+                format!("fri_tree_commitments_{}", i),
+                LdmType::Hash,
+                StepKind::FiatShamir1,
+            );
+            schema.declare(
+                format!("folding_alpha_{}", i),
+                LdmType::QM31,
+                StepKind::FiatShamir1,
+            );
+        }
+        schema.declare("last_layer", LdmType::QM31, StepKind::FiatShamir1);
+
+        for query_idx in 0..N_QUERIES {
+            let key = format!("query_{}", query_idx);
+            schema.declare(&key, LdmType::M31, StepKind::FiatShamir1);
+            schema.consume(&key, StepKind::FiatShamir2AndConstraintNum);
+        }
+
+        for tree in 0..4 {
+            // the composition tree is queried in part2
+            let producer = if tree < 3 {
+                StepKind::FiatShamir1
+            } else {
+                StepKind::FiatShamir2AndConstraintNum
+            };
+            let tree_start = hints.mask.tree_start(tree);
+            for column in tree_start..tree_start + hints.mask.offsets[tree].len() {
+                for query_idx in 0..N_QUERIES {
+                    for side in ["l", "r"] {
+                        schema.declare(
+                            format!("queried_results_{}_{}_{}", column, query_idx, side),
+                            LdmType::M31,
+                            producer,
+                        );
+                    }
+                }
+            }
+        }
+
+        // the sample points: the OODS point is drawn in part1, and the others are shifted from it
+        // in part3
+        for batch in batches.iter() {
+            let name = sample_point_name(batch.offset);
+            let producer = if batch.offset == 0 {
+                StepKind::FiatShamir1
+            } else {
+                StepKind::ConstraintDenom
+            };
+            for coordinate in ["x", "y"] {
+                let key = format!("{}_{}", name, coordinate);
+                schema.declare(&key, LdmType::QM31, producer);
+                schema.consume(&key, StepKind::PairVanishingAndAlphas);
+                if batch.offset == 0 {
+                    schema.consume(&key, StepKind::ConstraintDenom);
+                }
+            }
+        }
+
+        // part2: the queried points and the numerator of the constraint
+        for query_idx in 0..N_QUERIES {
+            for coordinate in ["x", "y"] {
+                schema.declare(
+                    format!("circle_point_{}_{}", coordinate, query_idx),
+                    LdmType::M31,
+                    StepKind::FiatShamir2AndConstraintNum,
+                );
+            }
+            for i in 1..=5 {
+                schema.declare(
+                    format!("twiddle_factor_{}_{}", i, query_idx),
+                    LdmType::M31,
+                    StepKind::FiatShamir2AndConstraintNum,
+                );
+            }
+        }
+        schema.declare(
+            "constraint_num",
+            LdmType::QM31,
+            StepKind::FiatShamir2AndConstraintNum,
+        );
+        schema.consume("constraint_num", StepKind::ConstraintDenom);
+
+        // part4: the pair vanishing and the powers of alpha
+        for batch in batches.iter() {
+            let name = sample_point_name(batch.offset);
+            for coeff in ["a", "b"] {
+                schema.declare(
+                    format!("prepared_{}_{}", name, coeff),
+                    LdmType::CM31,
+                    StepKind::PairVanishingAndAlphas,
+                );
+            }
+        }
+        for power in needed_alpha_powers(batches) {
+            if power != 1 {
+                schema.declare(
+                    alpha_power_key(power),
+                    LdmType::QM31,
+                    StepKind::PairVanishingAndAlphas,
+                );
+            }
+        }
+
+        // part5: the column line coefficients
+        for (step_idx, samples) in column_line_coeffs_chunks(batches).iter().enumerate() {
+            let step = StepKind::ColumnLineCoeffs { step_idx };
+            for &(batch_idx, pos) in samples.iter() {
+                let batch = &batches[batch_idx];
+                schema.consume(format!("{}_y", sample_point_name(batch.offset)), step);
+                schema.consume(
+                    hints.mask.oods_value_key(batch.columns[pos], batch.offset),
+                    step,
+                );
+                for coeff in ["a", "b"] {
+                    schema.declare(
+                        format!("column_line_coeffs_{}_{}_{}", batch_idx, pos, coeff),
+                        LdmType::CM31,
+                        step,
+                    );
+                }
+            }
+        }
+
+        // the per-query steps, where the folding step also reads the earlier queries for the skip
+        // leaf of the query
+        for query_idx in 0..N_QUERIES {
+            let folding = StepKind::Folding { query_idx };
+            let last_step = StepKind::LastStep { query_idx };
+
+            for i in 0..=query_idx {
+                schema.consume(format!("query_{}", i), folding);
+            }
+            for i in 0..LOG_N_ROWS as usize {
+                schema.consume(format!("fri_tree_commitments_{}", i), folding);
+                schema.consume(format!("folding_alpha_{}", i), folding);
+            }
+            for i in 1..=5 {
+                schema.consume(format!("twiddle_factor_{}_{}", i, query_idx), folding);
+            }
+            schema.consume("last_layer", folding);
+            schema.declare(
+                format!("expected_entry_quotient_{}", query_idx),
+                LdmType::QM31,
+                folding,
+            );
+
+            for (chunk_idx, chunk) in chunks.iter().enumerate() {
+                let step = StepKind::QuotientChunk {
+                    query_idx,
+                    chunk_idx,
+                };
+                let batch = &batches[chunk.batch];
+                let batch_name = sample_point_name(batch.offset);
+
+                schema.consume(format!("circle_point_y_{}", query_idx), step);
+
+                if chunk_idx == 0 {
+                    schema.consume(format!("circle_point_x_{}", query_idx), step);
+                    for batch in batches.iter() {
+                        let name = sample_point_name(batch.offset);
+                        for coeff in ["a", "b"] {
+                            schema.consume(format!("prepared_{}_{}", name, coeff), step);
+                        }
+                        for side in ["l", "r"] {
+                            schema.declare(
+                                format!("denominator_inverse_{}_{}_{}", name, query_idx, side),
+                                LdmType::CM31,
+                                step,
+                            );
+                        }
+                    }
+                }
+
+                for pos in chunk.start..chunk.end {
+                    for side in ["l", "r"] {
+                        schema.consume(
+                            format!(
+                                "queried_results_{}_{}_{}",
+                                batch.columns[pos], query_idx, side
+                            ),
+                            step,
+                        );
+                    }
+                    for coeff in ["a", "b"] {
+                        schema.consume(
+                            format!("column_line_coeffs_{}_{}_{}", chunk.batch, pos, coeff),
+                            step,
+                        );
+                    }
+                }
+
+                let chunk_power = chunk_alpha_power(batches, chunk);
+                for power in (1..chunk.end - chunk.start).chain(Some(chunk_power)) {
+                    if power > 0 {
+                        schema.consume(alpha_power_key(power), step);
+                    }
+                }
+
+                for side in ["l", "r"] {
+                    if chunk.start > 0 {
+                        schema.consume(
+                            format!(
+                                "quotient_batch_sum_{}_{}_{}_{}",
+                                batch_name, chunk.start, query_idx, side
+                            ),
+                            step,
+                        );
+                    }
+
+                    if chunk.end < batch.columns.len() {
+                        schema.declare(
+                            format!(
+                                "quotient_batch_sum_{}_{}_{}_{}",
+                                batch_name, chunk.end, query_idx, side
+                            ),
+                            LdmType::QM31,
+                            step,
+                        );
+                    } else {
+                        schema.consume(
+                            format!("denominator_inverse_{}_{}_{}", batch_name, query_idx, side),
+                            step,
+                        );
+                        if chunk.batch > 0 {
+                            let prev_batch_name =
+                                sample_point_name(batches[chunk.batch - 1].offset);
+                            schema.consume(
+                                format!("quotient_{}_{}_{}", prev_batch_name, query_idx, side),
+                                step,
+                            );
+                        }
+                        schema.declare(
+                            format!("quotient_{}_{}_{}", batch_name, query_idx, side),
+                            LdmType::QM31,
+                            step,
+                        );
+                    }
+                }
+            }
+
+            let last_batch_name = sample_point_name(batches.last().unwrap().offset);
+            for side in ["l", "r"] {
+                schema.consume(
+                    format!("quotient_{}_{}_{}", last_batch_name, query_idx, side),
+                    last_step,
+                );
+            }
+            schema.consume(format!("circle_point_y_{}", query_idx), last_step);
+            schema.consume("fri_fold_random_coeff", last_step);
+            schema.consume(format!("expected_entry_quotient_{}", query_idx), last_step);
+        }
+
+        schema
+    }
+
+    fn declare(&mut self, key: impl ToString, ty: LdmType, producer: StepKind) {
+        let key = key.to_string();
+        assert!(
+            !self.keys.contains_key(&key),
+            "`{}` is declared more than once",
+            key
+        );
+        self.keys.insert(
+            key,
+            LdmKeySchema {
+                ty,
+                producer,
+                consumers: BTreeSet::new(),
+            },
+        );
+    }
+
+    fn consume(&mut self, key: impl ToString, consumer: StepKind) {
+        let key = key.to_string();
+        self.keys
+            .get_mut(&key)
+            .unwrap_or_else(|| panic!("`{}` is consumed before it is declared", key))
+            .consumers
+            .insert(consumer);
+    }
+
+    /// Check a read or a write against the schema.
+    pub fn check(&self, access: &LdmAccess) -> Result<()> {
+        let step = self.steps.get(access.step).ok_or_else(|| {
+            Error::msg(format!(
+                "`{}` is accessed in step {}, which is not in the schema",
+                access.key, access.step
+            ))
+        })?;
+
+        let declared = self.keys.get(&access.key).ok_or_else(|| {
+            Error::msg(format!(
+                "`{}` is accessed in {} but not declared in the schema",
+                access.key, step
+            ))
+        })?;
+
+        if LdmType::from_type_name(access.type_name) != Some(declared.ty) {
+            return Err(Error::msg(format!(
+                "`{}` is accessed as {} in {}, but it is declared as {:?}",
+                access.key, access.type_name, step, declared.ty
+            )));
+        }
+
+        match access.kind {
+            LdmAccessKind::Write if declared.producer != *step => Err(Error::msg(format!(
+                "`{}` is written in {}, but it is declared to be written in {}",
+                access.key, step, declared.producer
+            ))),
+            LdmAccessKind::Read if !declared.consumers.contains(step) => Err(Error::msg(format!(
+                "`{}` is read in {}, which is not declared as a consumer",
+                access.key, step
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check that the run has written and read every entry as declared, except for the writes of
+    /// the entries that no step reads, which the steps skip.
+    pub fn check_complete(&self, accesses: &[LdmAccess]) -> Result<()> {
+        let mut seen = BTreeSet::new();
+        for access in accesses.iter() {
+            seen.insert((access.key.as_str(), self.steps[access.step], access.kind));
+        }
+
+        for (key, declared) in self.keys.iter() {
+            if !declared.consumers.is_empty()
+                && !seen.contains(&(key.as_str(), declared.producer, LdmAccessKind::Write))
+            {
+                return Err(Error::msg(format!(
+                    "`{}` is declared to be written in {}, but it is not",
+                    key, declared.producer
+                )));
+            }
+            for consumer in declared.consumers.iter() {
+                if !seen.contains(&(key.as_str(), *consumer, LdmAccessKind::Read)) {
+                    return Err(Error::msg(format!(
+                        "`{}` is declared to be read in {}, but it is not",
+                        key, consumer
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The index of the step in the execution order.
+    pub fn step_index(&self, step: StepKind) -> usize {
+        self.steps.iter().position(|&s| s == step).unwrap()
+    }
+
+    /// For each step, the earlier steps whose outputs it reads through the LDM.
+    pub fn dependency_graph(&self) -> BTreeMap<usize, BTreeSet<usize>> {
+        let mut graph = BTreeMap::<usize, BTreeSet<usize>>::new();
+        for idx in 0..self.steps.len() {
+            graph.insert(idx, BTreeSet::new());
+        }
+
+        for declared in self.keys.values() {
+            let producer = self.step_index(declared.producer);
+            for consumer in declared.consumers.iter() {
+                let consumer = self.step_index(*consumer);
+                if consumer != producer {
+                    graph.get_mut(&consumer).unwrap().insert(producer);
+                }
+            }
+        }
+
+        graph
+    }
+
+    /// The dependency graph in the DOT format.
+    pub fn dependency_graph_dot(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, "digraph ldm {{").unwrap();
+        for (idx, step) in self.steps.iter().enumerate() {
+            writeln!(out, "    {} [label=\"{}: {}\"];", idx, idx, step).unwrap();
+        }
+        for (consumer, producers) in self.dependency_graph().iter() {
+            for producer in producers.iter() {
+                writeln!(out, "    {} -> {};", producer, consumer).unwrap();
+            }
+        }
+        writeln!(out, "}}").unwrap();
+        out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::dsl::plonk::hints::Hints;
+    use crate::dsl::plonk::ldm_schema::LdmSchema;
+
+    #[test]
+    fn test_dependency_graph() {
+        let hints = Hints::instance();
+        let schema = LdmSchema::plonk(&hints);
+
+        assert_eq!(schema.steps.len(), 72);
+
+        // every entry is read only after it is written
+        for (key, declared) in schema.keys.iter() {
+            let producer = schema.step_index(declared.producer);
+            for consumer in declared.consumers.iter() {
+                assert!(
+                    schema.step_index(*consumer) > producer,
+                    "`{}` is read in {} before it is written",
+                    key,
+                    consumer
+                );
+            }
+        }
+
+        let graph = schema.dependency_graph();
+        assert_eq!(graph.len(), 72);
+        // the cleanup step reads nothing
+        assert!(graph.get(&71).unwrap().is_empty());
+
+        // the entries that the per-query steps of a query write are only read by themselves, so
+        // its skip leaf can write them from hints
+        for (key, declared) in schema.keys.iter() {
+            if let Some(query_idx) = declared.producer.query_idx() {
+                assert!(
+                    declared
+                        .consumers
+                        .iter()
+                        .all(|consumer| consumer.query_idx() == Some(query_idx)),
+                    "`{}` is read outside the per-query steps of query {}",
+                    key,
+                    query_idx
+                );
+            }
+        }
+    }
+}
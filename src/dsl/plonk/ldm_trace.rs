@@ -0,0 +1,497 @@
+use crate::algorithms::div::{cm31_value, qm31_value};
+use crate::algorithms::hasher::m31_to_script_num;
+use crate::dsl::disassemble::record_region;
+use crate::dsl::plonk::ldm_schema::LdmSchema;
+use anyhow::{Error, Result};
+use bitcoin_script_dsl::builtins::cm31::CM31Var;
+use bitcoin_script_dsl::builtins::hash::HashVar;
+use bitcoin_script_dsl::builtins::m31::M31Var;
+use bitcoin_script_dsl::builtins::qm31::QM31Var;
+use bitcoin_script_dsl::builtins::str::StrVar;
+use bitcoin_script_dsl::bvar::{AllocVar, BVar};
+use bitcoin_script_dsl::constraint_system::ConstraintSystemRef;
+use bitcoin_script_dsl::ldm::LDM;
+use std::collections::BTreeMap;
+
+/// A variable that can be stored in the LDM.
+pub trait LdmValue: BVar + AllocVar + 'static {
+    /// The elements of the witness that hold the value when it is read as a hint, in the
+    /// encoding of the compiled witness.
+    fn hint_elements(&self) -> Vec<Vec<u8>>;
+
+    /// The same value as a hint in the constraint system.
+    fn to_hint(&self, cs: &ConstraintSystemRef) -> Result<Self>;
+}
+
+impl LdmValue for M31Var {
+    fn hint_elements(&self) -> Vec<Vec<u8>> {
+        vec![m31_to_script_num(self.value)]
+    }
+
+    fn to_hint(&self, cs: &ConstraintSystemRef) -> Result<Self> {
+        M31Var::new_hint(cs, self.value)
+    }
+}
+
+impl LdmValue for CM31Var {
+    fn hint_elements(&self) -> Vec<Vec<u8>> {
+        [self.real.hint_elements(), self.imag.hint_elements()].concat()
+    }
+
+    fn to_hint(&self, cs: &ConstraintSystemRef) -> Result<Self> {
+        CM31Var::new_hint(cs, cm31_value(self))
+    }
+}
+
+impl LdmValue for QM31Var {
+    fn hint_elements(&self) -> Vec<Vec<u8>> {
+        [self.first.hint_elements(), self.second.hint_elements()].concat()
+    }
+
+    fn to_hint(&self, cs: &ConstraintSystemRef) -> Result<Self> {
+        QM31Var::new_hint(cs, qm31_value(self))
+    }
+}
+
+impl LdmValue for HashVar {
+    fn hint_elements(&self) -> Vec<Vec<u8>> {
+        vec![self.value.clone()]
+    }
+
+    fn to_hint(&self, cs: &ConstraintSystemRef) -> Result<Self> {
+        HashVar::new_hint(cs, self.value.clone())
+    }
+}
+
+impl LdmValue for StrVar {
+    fn hint_elements(&self) -> Vec<Vec<u8>> {
+        vec![self.value.clone()]
+    }
+
+    fn to_hint(&self, cs: &ConstraintSystemRef) -> Result<Self> {
+        StrVar::new_hint(cs, self.value.clone())
+    }
+}
+
+/// The LDM as the steps use it.
+///
+/// The production steps are generated with the LDM of the DSL, and `TracedLDM` implements the
+/// same interface to record the accesses when the steps are generated for debugging and analysis.
+pub trait StepLdm {
+    /// Start a step with its constraint system.
+    fn init(&mut self, cs: &ConstraintSystemRef) -> Result<()>;
+
+    fn read<T: LdmValue>(&mut self, key: impl ToString) -> Result<T>;
+
+    fn write<T: LdmValue>(&mut self, key: impl ToString, value: &T) -> Result<()>;
+
+    /// End the step, which outputs the write hash and the read hash.
+    fn save(&mut self) -> Result<()>;
+
+    /// Check that every entry that has been read is the one that has been written.
+    fn check(&mut self) -> Result<()>;
+
+    /// The write hash and the read hash after the last saved step.
+    fn state(&self) -> [Vec<u8>; 2];
+}
+
+impl StepLdm for LDM {
+    fn init(&mut self, cs: &ConstraintSystemRef) -> Result<()> {
+        LDM::init(self, cs)
+    }
+
+    fn read<T: LdmValue>(&mut self, key: impl ToString) -> Result<T> {
+        LDM::read(self, key)
+    }
+
+    fn write<T: LdmValue>(&mut self, key: impl ToString, value: &T) -> Result<()> {
+        LDM::write(self, key, value)
+    }
+
+    fn save(&mut self) -> Result<()> {
+        LDM::save(self)
+    }
+
+    fn check(&mut self) -> Result<()> {
+        LDM::check(self)
+    }
+
+    fn state(&self) -> [Vec<u8>; 2] {
+        [
+            self.write_hash_var.as_ref().unwrap().value.clone(),
+            self.read_hash_var.as_ref().unwrap().value.clone(),
+        ]
+    }
+}
+
+/// Whether an LDM entry is read or written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LdmAccessKind {
+    Read,
+    Write,
+}
+
+/// An access to an LDM entry.
+#[derive(Clone, Debug)]
+pub struct LdmAccess {
+    /// The step that accesses the entry.
+    pub step: usize,
+    pub key: String,
+    pub kind: LdmAccessKind,
+    /// The name of the variable type, e.g., `QM31Var`.
+    pub type_name: &'static str,
+    /// The value that is written, or the value that an honest hint of the read holds, as the
+    /// elements of the witness.
+    pub value: Vec<Vec<u8>>,
+}
+
+/// An LDM that records every read and write, along with the step it happens in and the value.
+///
+/// The steps take this in place of the LDM when they are generated for debugging and analysis.
+/// A new step starts whenever the LDM is initialized with the constraint system of the step.
+///
+/// If it has a schema, every read and write is checked against the schema, and an access that the
+/// schema does not declare fails the step.
+///
+/// The code that the LDM emits is marked with `record_region`, so the disassembly and the
+/// debugger can tell it apart from the rest of the step.
+pub struct TracedLDM {
+    ldm: LDM,
+    cs: Option<ConstraintSystemRef>,
+    num_steps: usize,
+    pub accesses: Vec<LdmAccess>,
+    pub schema: Option<LdmSchema>,
+}
+
+impl Default for TracedLDM {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TracedLDM {
+    pub fn new() -> Self {
+        Self {
+            ldm: LDM::new(),
+            cs: None,
+            num_steps: 0,
+            accesses: vec![],
+            schema: None,
+        }
+    }
+
+    pub fn with_schema(schema: LdmSchema) -> Self {
+        Self {
+            schema: Some(schema),
+            ..Self::new()
+        }
+    }
+
+    fn cs(&self) -> Result<ConstraintSystemRef> {
+        self.cs
+            .clone()
+            .ok_or_else(|| Error::msg("The LDM has not been initialized"))
+    }
+
+    /// The index of the step that is being generated.
+    pub fn current_step(&self) -> usize {
+        assert!(self.num_steps > 0, "the LDM has not been initialized");
+        self.num_steps - 1
+    }
+
+    fn access<T>(&self, key: &str, kind: LdmAccessKind) -> Result<LdmAccess> {
+        let type_name = std::any::type_name::<T>();
+        let access = LdmAccess {
+            step: self.current_step(),
+            key: key.to_string(),
+            kind,
+            type_name: type_name.rsplit("::").next().unwrap_or(type_name),
+            value: vec![],
+        };
+        if let Some(schema) = &self.schema {
+            schema.check(&access)?;
+        }
+        Ok(access)
+    }
+
+    /// The accesses of a step.
+    pub fn accesses_of_step(&self, step: usize) -> Vec<&LdmAccess> {
+        self.accesses
+            .iter()
+            .filter(|access| access.step == step)
+            .collect()
+    }
+
+    /// The entries that break the write-once, read-after-write discipline that the final check of
+    /// the LDM relies on.
+    pub fn issues(&self) -> Vec<LdmIssue> {
+        find_issues(&self.accesses)
+    }
+}
+
+impl StepLdm for TracedLDM {
+    fn init(&mut self, cs: &ConstraintSystemRef) -> Result<()> {
+        self.num_steps += 1;
+        self.cs = Some(cs.clone());
+        record_region(cs, "ldm init", || self.ldm.init(cs))
+    }
+
+    fn read<T: LdmValue>(&mut self, key: impl ToString) -> Result<T> {
+        let key = key.to_string();
+        let mut access = self.access::<T>(&key, LdmAccessKind::Read)?;
+        let cs = self.cs()?;
+        let value: T = record_region(&cs, &format!("ldm read {}", key), || {
+            self.ldm.read(key.clone())
+        })?;
+        access.value = value.hint_elements();
+        self.accesses.push(access);
+        Ok(value)
+    }
+
+    fn write<T: LdmValue>(&mut self, key: impl ToString, value: &T) -> Result<()> {
+        let key = key.to_string();
+        let mut access = self.access::<T>(&key, LdmAccessKind::Write)?;
+        access.value = value.hint_elements();
+        self.accesses.push(access);
+        let cs = self.cs()?;
+        record_region(&cs, &format!("ldm write {}", key), || {
+            self.ldm.write(key.clone(), value)
+        })
+    }
+
+    fn save(&mut self) -> Result<()> {
+        let cs = self.cs()?;
+        record_region(&cs, "ldm save", || self.ldm.save())
+    }
+
+    fn check(&mut self) -> Result<()> {
+        let cs = self.cs()?;
+        record_region(&cs, "ldm check", || self.ldm.check())
+    }
+
+    fn state(&self) -> [Vec<u8>; 2] {
+        StepLdm::state(&self.ldm)
+    }
+}
+
+/// Where the hint of an LDM read is in the witness of the step.
+#[derive(Clone, Debug)]
+pub struct LdmReadHint {
+    pub key: String,
+    /// The positions of the elements of the value in the witness, which is empty if they cannot
+    /// be located.
+    pub positions: Vec<usize>,
+    /// The value that an honest hint holds, as the elements of the witness.
+    pub honest: Vec<Vec<u8>>,
+}
+
+impl LdmReadHint {
+    /// Whether the witness holds a value other than the honest one for the read.
+    pub fn is_tampered(&self, witness: &[Vec<u8>]) -> bool {
+        self.positions
+            .iter()
+            .zip(self.honest.iter())
+            .any(|(&pos, honest)| witness.get(pos) != Some(honest))
+    }
+}
+
+/// Locate the hints of the reads of a step in its honest witness.
+///
+/// The elements of a value are looked up as a run in the witness, in order or in reverse, and a
+/// position is only assigned to one read.
+pub fn locate_read_hints(accesses: &[&LdmAccess], witness: &[Vec<u8>]) -> Vec<LdmReadHint> {
+    let mut taken = vec![false; witness.len()];
+
+    accesses
+        .iter()
+        .filter(|access| access.kind == LdmAccessKind::Read)
+        .map(|access| {
+            let n = access.value.len();
+            let mut positions = vec![];
+            if n > 0 && n <= witness.len() {
+                for start in 0..=witness.len() - n {
+                    if taken[start..start + n].iter().any(|&t| t) {
+                        continue;
+                    }
+                    let run = &witness[start..start + n];
+                    if run.iter().eq(access.value.iter()) {
+                        positions = (start..start + n).collect();
+                    } else if run.iter().eq(access.value.iter().rev()) {
+                        positions = (start..start + n).rev().collect();
+                    } else {
+                        continue;
+                    }
+                    break;
+                }
+            }
+            for &pos in positions.iter() {
+                taken[pos] = true;
+            }
+
+            LdmReadHint {
+                key: access.key.clone(),
+                positions,
+                honest: access.value.clone(),
+            }
+        })
+        .collect()
+}
+
+/// An LDM entry that would make the hash check fail.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LdmIssue {
+    /// The entry is written more than once.
+    WrittenTwice {
+        key: String,
+        first_step: usize,
+        second_step: usize,
+    },
+    /// The entry is read before it is written, or never written.
+    ReadBeforeWritten { key: String, step: usize },
+}
+
+impl LdmIssue {
+    pub fn key(&self) -> &str {
+        match self {
+            LdmIssue::WrittenTwice { key, .. } => key,
+            LdmIssue::ReadBeforeWritten { key, .. } => key,
+        }
+    }
+
+    /// Whether the issue involves the step.
+    pub fn involves_step(&self, step: usize) -> bool {
+        match self {
+            LdmIssue::WrittenTwice {
+                first_step,
+                second_step,
+                ..
+            } => *first_step == step || *second_step == step,
+            LdmIssue::ReadBeforeWritten { step: s, .. } => *s == step,
+        }
+    }
+}
+
+impl std::fmt::Display for LdmIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LdmIssue::WrittenTwice {
+                key,
+                first_step,
+                second_step,
+            } => write!(
+                f,
+                "`{}` is written in step {} and again in step {}",
+                key, first_step, second_step
+            ),
+            LdmIssue::ReadBeforeWritten { key, step } => {
+                write!(f, "`{}` is read in step {} before it is written", key, step)
+            }
+        }
+    }
+}
+
+/// Find the entries in the accesses that break the write-once, read-after-write discipline.
+pub fn find_issues(accesses: &[LdmAccess]) -> Vec<LdmIssue> {
+    let mut written_in = BTreeMap::<&str, usize>::new();
+    let mut issues = vec![];
+
+    for access in accesses.iter() {
+        match access.kind {
+            LdmAccessKind::Write => {
+                if let Some(&first_step) = written_in.get(access.key.as_str()) {
+                    issues.push(LdmIssue::WrittenTwice {
+                        key: access.key.clone(),
+                        first_step,
+                        second_step: access.step,
+                    });
+                } else {
+                    written_in.insert(&access.key, access.step);
+                }
+            }
+            LdmAccessKind::Read => {
+                if !written_in.contains_key(access.key.as_str()) {
+                    issues.push(LdmIssue::ReadBeforeWritten {
+                        key: access.key.clone(),
+                        step: access.step,
+                    });
+                }
+            }
+        }
+    }
+
+    issues
+}
+
+#[cfg(test)]
+mod test {
+    use crate::dsl::plonk::ldm_trace::{
+        find_issues, locate_read_hints, LdmAccess, LdmAccessKind, LdmIssue,
+    };
+
+    #[test]
+    fn test_find_issues() {
+        let access = |step: usize, key: &str, kind: LdmAccessKind| LdmAccess {
+            step,
+            key: key.to_string(),
+            kind,
+            type_name: "QM31Var",
+            value: vec![],
+        };
+
+        let accesses = vec![
+            access(0, "alpha", LdmAccessKind::Write),
+            access(1, "alpha", LdmAccessKind::Read),
+            access(1, "z", LdmAccessKind::Read),
+            access(2, "z", LdmAccessKind::Write),
+            access(3, "alpha", LdmAccessKind::Write),
+        ];
+
+        assert_eq!(
+            find_issues(&accesses),
+            vec![
+                LdmIssue::ReadBeforeWritten {
+                    key: "z".to_string(),
+                    step: 1
+                },
+                LdmIssue::WrittenTwice {
+                    key: "alpha".to_string(),
+                    first_step: 0,
+                    second_step: 3
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_locate_read_hints() {
+        let access = |key: &str, value: &[u8]| LdmAccess {
+            step: 0,
+            key: key.to_string(),
+            kind: LdmAccessKind::Read,
+            type_name: "CM31Var",
+            value: value.iter().map(|&v| vec![v]).collect(),
+        };
+        let accesses = [
+            access("a", &[1, 2]),
+            access("b", &[3, 4]),
+            access("c", &[1, 2]),
+        ];
+        let accesses = accesses.iter().collect::<Vec<_>>();
+
+        let witness = [9, 1, 2, 4, 3, 1, 2]
+            .iter()
+            .map(|&v| vec![v])
+            .collect::<Vec<_>>();
+
+        let read_hints = locate_read_hints(&accesses, &witness);
+        assert_eq!(read_hints[0].positions, vec![1, 2]);
+        assert_eq!(read_hints[1].positions, vec![4, 3]);
+        assert_eq!(read_hints[2].positions, vec![5, 6]);
+        assert!(read_hints.iter().all(|hint| !hint.is_tampered(&witness)));
+
+        let mut tampered = witness.clone();
+        tampered[3] = vec![5];
+        assert!(!read_hints[0].is_tampered(&tampered));
+        assert!(read_hints[1].is_tampered(&tampered));
+    }
+}
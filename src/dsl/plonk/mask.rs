@@ -0,0 +1,239 @@
+use itertools::Itertools;
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+
+/// Names of the commitment trees, which are also the prefixes of the LDM keys of their OODS
+/// values (e.g., `interaction_oods_value_5`).
+pub const TREE_NAMES: [&str; 4] = ["trace", "interaction", "constant", "composition"];
+
+/// Number of columns whose numerators are accumulated in a single per-query step.
+pub const NUM_COLUMNS_PER_QUOTIENT_CHUNK: usize = 4;
+
+/// Number of column line coefficients computed in a single step.
+pub const NUM_SAMPLES_PER_COLUMN_LINE_COEFFS_STEP: usize = 8;
+
+/// The mask of the AIR.
+#[derive(Clone, Debug)]
+pub struct Mask {
+    /// For each commitment tree and each of its columns, the offsets (in trace steps) from the
+    /// OODS point at which the column is sampled, in the same order as the sampled values in the
+    /// proof.
+    pub offsets: Vec<Vec<Vec<isize>>>,
+}
+
+impl Mask {
+    /// The mask of the Plonk AIR.
+    ///
+    /// The last four interaction columns, which hold the cumulative sum of the logup, are also
+    /// sampled at the previous row.
+    pub fn plonk() -> Self {
+        Self {
+            offsets: vec![
+                vec![vec![0]; 4],
+                [vec![vec![0]; 4], vec![vec![0, -1]; 4]].concat(),
+                vec![vec![0]; 4],
+                vec![vec![0]; 4],
+            ],
+        }
+    }
+
+    /// The total number of columns over all the trees.
+    pub fn num_columns(&self) -> usize {
+        self.offsets.iter().map(|tree| tree.len()).sum()
+    }
+
+    /// The index of the first column of the tree among the columns of all the trees.
+    pub fn tree_start(&self, tree: usize) -> usize {
+        self.offsets[0..tree].iter().map(|tree| tree.len()).sum()
+    }
+
+    /// Map a column index (among the columns of all the trees) to the tree and the index of the
+    /// column within the tree.
+    pub fn locate(&self, column: usize) -> (usize, usize) {
+        let mut column_in_tree = column;
+        for (tree, tree_offsets) in self.offsets.iter().enumerate() {
+            if column_in_tree < tree_offsets.len() {
+                return (tree, column_in_tree);
+            }
+            column_in_tree -= tree_offsets.len();
+        }
+        unreachable!()
+    }
+
+    /// All the distinct offsets, in the order of their first appearance.
+    pub fn distinct_offsets(&self) -> Vec<isize> {
+        self.offsets
+            .iter()
+            .flatten()
+            .flatten()
+            .copied()
+            .unique()
+            .collect()
+    }
+
+    /// The columns sampled at the given offset, in ascending order.
+    pub fn columns_at(&self, offset: isize) -> Vec<usize> {
+        (0..self.num_columns())
+            .filter(|&column| {
+                let (tree, column_in_tree) = self.locate(column);
+                self.offsets[tree][column_in_tree].contains(&offset)
+            })
+            .collect()
+    }
+
+    /// Group the columns by their sample points.
+    ///
+    /// The batches follow the order of `distinct_offsets`, and the columns within a batch are in
+    /// ascending order.
+    pub fn sample_batches(&self) -> Vec<SampleBatchLayout> {
+        self.distinct_offsets()
+            .into_iter()
+            .map(|offset| SampleBatchLayout {
+                offset,
+                columns: self.columns_at(offset),
+            })
+            .collect()
+    }
+
+    /// The LDM key of the OODS value of the column sampled at the given offset.
+    pub fn oods_value_key(&self, column: usize, offset: isize) -> String {
+        let (tree, column_in_tree) = self.locate(column);
+
+        let mut idx: usize = self.offsets[tree][0..column_in_tree]
+            .iter()
+            .map(|offsets| offsets.len())
+            .sum();
+        idx += self.offsets[tree][column_in_tree]
+            .iter()
+            .position(|&v| v == offset)
+            .unwrap();
+
+        format!("{}_oods_value_{}", TREE_NAMES[tree], idx)
+    }
+}
+
+/// The columns that are sampled at the same point.
+#[derive(Clone, Debug)]
+pub struct SampleBatchLayout {
+    /// The offset (in trace steps) of the sample point from the OODS point.
+    pub offset: isize,
+    /// The columns sampled at this point, as indices among the columns of all the trees.
+    pub columns: Vec<usize>,
+}
+
+/// The name of the sample point at the given offset, used as the prefix of its LDM keys.
+pub fn sample_point_name(offset: isize) -> String {
+    match offset.cmp(&0) {
+        Ordering::Equal => "oods".to_string(),
+        Ordering::Less => format!("oods_shifted_by_{}", -offset),
+        Ordering::Greater => format!("oods_shifted_forward_by_{}", offset),
+    }
+}
+
+/// A range of consecutive columns in a sample batch whose numerators are computed in one
+/// per-query step.
+#[derive(Clone, Debug)]
+pub struct QuotientChunk {
+    /// The index of the sample batch.
+    pub batch: usize,
+    /// The position of the first column in the batch.
+    pub start: usize,
+    /// The position after the last column in the batch.
+    pub end: usize,
+}
+
+/// Split the sample batches into chunks of at most `NUM_COLUMNS_PER_QUOTIENT_CHUNK` columns.
+pub fn quotient_chunks(batches: &[SampleBatchLayout]) -> Vec<QuotientChunk> {
+    let mut chunks = vec![];
+    for (batch_idx, batch) in batches.iter().enumerate() {
+        let mut start = 0;
+        while start < batch.columns.len() {
+            let end = (start + NUM_COLUMNS_PER_QUOTIENT_CHUNK).min(batch.columns.len());
+            chunks.push(QuotientChunk {
+                batch: batch_idx,
+                start,
+                end,
+            });
+            start = end;
+        }
+    }
+    chunks
+}
+
+/// The power of the line batch random coefficient (alpha) that the chunk is multiplied with.
+///
+/// The quotients are combined as
+/// ```text
+/// sum_b alpha^{s_b} · v_b(X)^{-1} · (sum_j alpha^{n_b - 1 - j} · g_{b,j}(X))
+/// ```
+/// where `n_b` is the number of columns in the batch `b` and `s_b` is the number of columns in
+/// the later batches. Within a chunk, the last column gets alpha^0, so the chunk as a whole is
+/// multiplied by `alpha^{s_b + n_b - end}`.
+pub fn chunk_alpha_power(batches: &[SampleBatchLayout], chunk: &QuotientChunk) -> usize {
+    let num_columns_in_later_batches: usize = batches[chunk.batch + 1..]
+        .iter()
+        .map(|batch| batch.columns.len())
+        .sum();
+    num_columns_in_later_batches + batches[chunk.batch].columns.len() - chunk.end
+}
+
+/// All the powers of alpha (except alpha^0) used in the per-query steps.
+pub fn needed_alpha_powers(batches: &[SampleBatchLayout]) -> BTreeSet<usize> {
+    let mut powers = BTreeSet::new();
+    for chunk in quotient_chunks(batches).iter() {
+        powers.extend(1..chunk.end - chunk.start);
+
+        let chunk_power = chunk_alpha_power(batches, chunk);
+        if chunk_power > 0 {
+            powers.insert(chunk_power);
+        }
+    }
+    powers
+}
+
+/// The LDM key of the power of the line batch random coefficient.
+pub fn alpha_power_key(power: usize) -> String {
+    if power == 1 {
+        "line_batch_random_coeff".to_string()
+    } else {
+        format!("line_batch_random_coeff_{}", power)
+    }
+}
+
+/// Split all the samples, as `(batch, position in the batch)`, into the steps that compute their
+/// column line coefficients.
+pub fn column_line_coeffs_chunks(batches: &[SampleBatchLayout]) -> Vec<Vec<(usize, usize)>> {
+    batches
+        .iter()
+        .enumerate()
+        .flat_map(|(batch_idx, batch)| (0..batch.columns.len()).map(move |pos| (batch_idx, pos)))
+        .chunks(NUM_SAMPLES_PER_COLUMN_LINE_COEFFS_STEP)
+        .into_iter()
+        .map(|chunk| chunk.collect())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::dsl::plonk::mask::{chunk_alpha_power, quotient_chunks, Mask};
+
+    #[test]
+    fn test_plonk_layout() {
+        let mask = Mask::plonk();
+        assert_eq!(mask.num_columns(), 20);
+        assert_eq!(mask.oods_value_key(9, -1), "interaction_oods_value_7");
+
+        let batches = mask.sample_batches();
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[0].columns, (0..20).collect::<Vec<_>>());
+        assert_eq!(batches[1].offset, -1);
+        assert_eq!(batches[1].columns, (8..12).collect::<Vec<_>>());
+
+        // the last column of the OODS batch comes right before the columns of the shifted batch
+        let chunks = quotient_chunks(&batches);
+        assert_eq!(chunks.len(), 6);
+        assert_eq!(chunk_alpha_power(&batches, &chunks[4]), 4);
+        assert_eq!(chunk_alpha_power(&batches, &chunks[0]), 20);
+        assert_eq!(chunk_alpha_power(&batches, &chunks[5]), 0);
+    }
+}
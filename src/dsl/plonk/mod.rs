@@ -1,205 +1,239 @@
 pub mod hints;
 
+pub mod split;
+
+/// The covenant that chains the steps together, which introspects the transactions with `OP_CAT`.
 pub mod covenant;
 
+/// Chaining the steps by Winternitz signatures on the state, which reassembles the signed hashes
+/// with `OP_CAT`.
+pub mod winternitz_handoff;
+
+/// Disprove leaves for an optimistic deployment, where only a step whose claimed state is wrong is
+/// executed on-chain.
+pub mod disprove;
+
+/// Exporting the taproot tree of the covenant, so that its address can be reproduced.
+pub mod taproot_export;
+
+/// The PSBT of the transaction that starts the covenant.
+pub mod bootstrap;
+
+pub mod ldm_trace;
+
+pub mod ldm_schema;
+
+pub mod ldm_liveness;
+
+pub mod debugger;
+
+pub mod mask;
+
 pub mod part1_fiat_shamir1;
 pub mod part2_fiat_shamir2_and_constraint_num;
 pub mod part3_constraint_denom;
 pub mod part4_pair_vanishing_and_alphas;
-pub mod part5_column_line_coeffs1;
-pub mod part6_column_line_coeffs2;
-pub mod part7_column_line_coeffs3;
+pub mod part5_column_line_coeffs;
 
 pub mod per_query_part1_folding;
-pub mod per_query_part2_num_trace;
-pub mod per_query_part3_num_constant;
-pub mod per_query_part4_num_composition;
-pub mod per_query_part5_num_interaction_shifted;
-pub mod per_query_part6_num_interaction1;
-pub mod per_query_part7_num_interaction2;
-pub mod per_query_part8_last_step;
+pub mod per_query_part2_quotient_chunk;
+pub mod per_query_part3_last_step;
+pub mod per_query_skip;
 
-pub mod part8_cleanup;
+pub mod part6_cleanup;
 
 #[cfg(test)]
 mod test {
+    use crate::algorithms::domain_point::DomainPointSource;
     use crate::dsl::plonk::hints::Hints;
+    use crate::dsl::plonk::ldm_liveness::LiveLdm;
+    use crate::dsl::plonk::ldm_schema::LdmSchema;
+    use crate::dsl::plonk::ldm_trace::{LdmAccessKind, StepLdm, TracedLDM};
+    use crate::dsl::plonk::split::{
+        compute_all_information, compute_debug_information, step_sequence, StepKind,
+    };
     use bitcoin_circle_stark::treepp::*;
-    use bitcoin_script_dsl::ldm::LDM;
+    use bitcoin_script_dsl::constraint_system::ConstraintSystemRef;
     use bitcoin_script_dsl::test_program;
     use stwo_prover::core::prover::N_QUERIES;
 
     #[test]
     fn test_generate_dsl() {
         let hints = Hints::instance();
-        let mut ldm = LDM::new();
-
-        let cs = super::part1_fiat_shamir1::generate_cs(&hints, &mut ldm).unwrap();
-        test_program(
-            cs,
-            script! {
-                { ldm.write_hash_var.as_ref().unwrap().value.clone() }
-                { ldm.read_hash_var.as_ref().unwrap().value.clone() }
-            },
-        )
-        .unwrap();
-
-        let cs =
-            super::part2_fiat_shamir2_and_constraint_num::generate_cs(&hints, &mut ldm).unwrap();
-        test_program(
-            cs,
-            script! {
-                { ldm.write_hash_var.as_ref().unwrap().value.clone() }
-                { ldm.read_hash_var.as_ref().unwrap().value.clone() }
-            },
-        )
-        .unwrap();
-
-        let cs = super::part3_constraint_denom::generate_cs(&hints, &mut ldm).unwrap();
-        test_program(
-            cs,
-            script! {
-                { ldm.write_hash_var.as_ref().unwrap().value.clone() }
-                { ldm.read_hash_var.as_ref().unwrap().value.clone() }
-            },
-        )
-        .unwrap();
-
-        let cs = super::part4_pair_vanishing_and_alphas::generate_cs(&hints, &mut ldm).unwrap();
-        test_program(
-            cs,
-            script! {
-                { ldm.write_hash_var.as_ref().unwrap().value.clone() }
-                { ldm.read_hash_var.as_ref().unwrap().value.clone() }
-            },
-        )
-        .unwrap();
-
-        let cs = super::part5_column_line_coeffs1::generate_cs(&hints, &mut ldm).unwrap();
-        test_program(
-            cs,
-            script! {
-                { ldm.write_hash_var.as_ref().unwrap().value.clone() }
-                { ldm.read_hash_var.as_ref().unwrap().value.clone() }
-            },
-        )
-        .unwrap();
-
-        let cs = super::part6_column_line_coeffs2::generate_cs(&hints, &mut ldm).unwrap();
-        test_program(
-            cs,
-            script! {
-                { ldm.write_hash_var.as_ref().unwrap().value.clone() }
-                { ldm.read_hash_var.as_ref().unwrap().value.clone() }
-            },
-        )
-        .unwrap();
-
-        let cs = super::part7_column_line_coeffs3::generate_cs(&hints, &mut ldm).unwrap();
-        test_program(
-            cs,
-            script! {
-                { ldm.write_hash_var.as_ref().unwrap().value.clone() }
-                { ldm.read_hash_var.as_ref().unwrap().value.clone() }
-            },
-        )
-        .unwrap();
-
-        for i in 0..N_QUERIES {
-            let cs = super::per_query_part1_folding::generate_cs(&hints, &mut ldm, i).unwrap();
-            test_program(
-                cs,
-                script! {
-                    { ldm.write_hash_var.as_ref().unwrap().value.clone() }
-                    { ldm.read_hash_var.as_ref().unwrap().value.clone() }
-                },
-            )
-            .unwrap();
+        let mut ldm = LiveLdm::plonk(&hints, TracedLDM::with_schema(LdmSchema::plonk(&hints)));
 
-            let cs = super::per_query_part2_num_trace::generate_cs(&hints, &mut ldm, i).unwrap();
+        let check = |cs: ConstraintSystemRef, ldm: &LiveLdm<TracedLDM>| {
             test_program(
                 cs,
                 script! {
-                    { ldm.write_hash_var.as_ref().unwrap().value.clone() }
-                    { ldm.read_hash_var.as_ref().unwrap().value.clone() }
+                    { ldm.state()[0].clone() }
+                    { ldm.state()[1].clone() }
                 },
             )
             .unwrap();
+        };
 
-            let cs = super::per_query_part3_num_constant::generate_cs(&hints, &mut ldm, i).unwrap();
-            test_program(
-                cs,
-                script! {
-                    { ldm.write_hash_var.as_ref().unwrap().value.clone() }
-                    { ldm.read_hash_var.as_ref().unwrap().value.clone() }
-                },
-            )
-            .unwrap();
+        for step in step_sequence(&hints) {
+            let cs = step.generate_cs(&hints, &mut ldm).unwrap();
+            check(cs, &ldm);
+        }
 
-            let cs =
-                super::per_query_part4_num_composition::generate_cs(&hints, &mut ldm, i).unwrap();
-            test_program(
-                cs,
-                script! {
-                    { ldm.write_hash_var.as_ref().unwrap().value.clone() }
-                    { ldm.read_hash_var.as_ref().unwrap().value.clone() }
-                },
-            )
+        assert!(ldm.ldm.issues().is_empty());
+        ldm.ldm
+            .schema
+            .as_ref()
+            .unwrap()
+            .check_complete(&ldm.ldm.accesses)
             .unwrap();
+    }
 
-            let cs =
-                super::per_query_part5_num_interaction_shifted::generate_cs(&hints, &mut ldm, i)
-                    .unwrap();
-            test_program(
-                cs,
-                script! {
-                    { ldm.write_hash_var.as_ref().unwrap().value.clone() }
-                    { ldm.read_hash_var.as_ref().unwrap().value.clone() }
-                },
-            )
-            .unwrap();
+    #[test]
+    fn test_domain_points_in_script() {
+        let mut hints = Hints::instance();
+
+        // the second step writes the same points and twiddles either way, so the LDM ends up in
+        // the same state
+        let mut states = vec![];
+        for domain_point_source in [
+            DomainPointSource::PrecomputedTree,
+            DomainPointSource::InScript,
+        ] {
+            hints.domain_point_source = domain_point_source;
+            let mut ldm = TracedLDM::with_schema(LdmSchema::plonk(&hints));
+
+            for step in step_sequence(&hints)[0..2].iter() {
+                let cs = step.generate_cs(&hints, &mut ldm).unwrap();
+                test_program(
+                    cs,
+                    script! {
+                        { ldm.state()[0].clone() }
+                        { ldm.state()[1].clone() }
+                    },
+                )
+                .unwrap();
+            }
+            assert!(ldm.issues().is_empty());
+            states.push(ldm.state());
+        }
+        assert_eq!(states[0], states[1]);
+    }
 
-            let cs =
-                super::per_query_part6_num_interaction1::generate_cs(&hints, &mut ldm, i).unwrap();
-            test_program(
-                cs,
-                script! {
-                    { ldm.write_hash_var.as_ref().unwrap().value.clone() }
-                    { ldm.read_hash_var.as_ref().unwrap().value.clone() }
-                },
-            )
-            .unwrap();
+    #[test]
+    fn test_traced_ldm_does_not_change_steps() {
+        let all_information = compute_all_information();
+        let debug_information = compute_debug_information();
+
+        assert_eq!(
+            all_information.scripts,
+            debug_information.all_information.scripts
+        );
+        assert_eq!(
+            all_information.witnesses,
+            debug_information.all_information.witnesses
+        );
+        assert_eq!(
+            all_information.outputs,
+            debug_information.all_information.outputs
+        );
+
+        // the regions are recorded where the steps make the calls
+        let names = debug_information.recorded_regions[0]
+            .iter()
+            .map(|region| region.name.as_str())
+            .collect::<Vec<_>>();
+        assert!(names.contains(&"merkle twin tree"));
+        assert!(names.contains(&"proof of work"));
+        assert!(names.contains(&"ldm save"));
+    }
 
-            let cs =
-                super::per_query_part7_num_interaction2::generate_cs(&hints, &mut ldm, i).unwrap();
-            test_program(
-                cs,
-                script! {
-                    { ldm.write_hash_var.as_ref().unwrap().value.clone() }
-                    { ldm.read_hash_var.as_ref().unwrap().value.clone() }
-                },
-            )
-            .unwrap();
+    #[test]
+    fn test_skip_leaves() {
+        let hints = Hints::instance();
+        let steps = step_sequence(&hints);
+        // the skip leaves are checked to leave the LDM in the same state as the steps they skip
+        // when they are generated
+        let all_information = compute_all_information();
+
+        assert_eq!(all_information.skips.len(), N_QUERIES - 1);
+        for (skip_idx, skip) in all_information.skips.iter().enumerate() {
+            let query_idx = skip.query_idx;
+            assert_eq!(steps[skip.from_pc], StepKind::Folding { query_idx });
+            assert_eq!(steps[skip.to_pc - 1], StepKind::LastStep { query_idx });
+            assert_eq!(skip.duplicate_of, hints.duplicate_of(query_idx));
+
+            let idx = all_information.scripts.len() + skip_idx;
+            assert_eq!(all_information.leaf_pc(idx), skip.from_pc);
+            assert_eq!(all_information.next_pc(idx), skip.to_pc);
+        }
 
-            let cs = super::per_query_part8_last_step::generate_cs(&hints, &mut ldm, i).unwrap();
-            test_program(
-                cs,
-                script! {
-                    { ldm.write_hash_var.as_ref().unwrap().value.clone() }
-                    { ldm.read_hash_var.as_ref().unwrap().value.clone() }
-                },
-            )
-            .unwrap();
+        // the path of the proof runs from the first step to the end, through the skip leaves of
+        // the queries that share a parent with an earlier query
+        let mut pc = 0;
+        for idx in all_information.path() {
+            assert_eq!(all_information.leaf_pc(idx), pc);
+            pc = all_information.next_pc(idx);
         }
+        assert_eq!(pc, steps.len());
+    }
 
-        let cs = super::part8_cleanup::generate_cs(&hints, &mut ldm).unwrap();
-        test_program(
-            cs,
-            script! {
-                { ldm.write_hash_var.as_ref().unwrap().value.clone() }
-                { ldm.read_hash_var.as_ref().unwrap().value.clone() }
-            },
-        )
-        .unwrap();
+    #[test]
+    fn test_earlier_query_reads() {
+        let hints = Hints::instance();
+        let steps = step_sequence(&hints);
+        let debug_information = compute_debug_information();
+        let scripts = &debug_information.all_information.scripts;
+
+        // the folding step of each query reads the earlier queries for the skip leaf of the
+        // query, which adds the script that hashes them into the LDM, and the queries to the
+        // witness
+        for (idx, step) in steps.iter().enumerate() {
+            let StepKind::Folding { query_idx } = *step else {
+                continue;
+            };
+            let is_earlier_query = |key: &str| {
+                key.strip_prefix("query_")
+                    .and_then(|i| i.parse::<usize>().ok())
+                    .is_some_and(|i| i < query_idx)
+            };
+
+            let script_bytes: usize = debug_information.recorded_regions[idx]
+                .iter()
+                .filter(|region| {
+                    region
+                        .name
+                        .strip_prefix("ldm read ")
+                        .is_some_and(is_earlier_query)
+                })
+                .map(|region| region.script.len())
+                .sum();
+            let witness_bytes: usize = debug_information
+                .ldm_accesses
+                .iter()
+                .filter(|access| {
+                    access.step == idx
+                        && access.kind == LdmAccessKind::Read
+                        && is_earlier_query(&access.key)
+                })
+                .flat_map(|access| access.value.iter())
+                .map(|element| element.len() + 1)
+                .sum();
+
+            println!(
+                "{}: {} bytes of script and {} bytes of witness for {} earlier queries, in a script of {} bytes",
+                step,
+                script_bytes,
+                witness_bytes,
+                query_idx,
+                scripts[idx].len()
+            );
+
+            if query_idx == 0 {
+                assert_eq!(script_bytes + witness_bytes, 0);
+            } else {
+                assert!(script_bytes > 0 && witness_bytes > 0);
+            }
+            // the reads add less than 5% to the step
+            assert!(20 * (script_bytes + witness_bytes) < scripts[idx].len());
+        }
     }
 }
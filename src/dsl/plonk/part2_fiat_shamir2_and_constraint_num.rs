@@ -1,25 +1,27 @@
+use crate::algorithms::channel::{ChannelDigestVar, ChannelVar};
+use crate::algorithms::domain_point::{compute_point_and_twiddles, DomainPointSource};
 use crate::algorithms::precomputed_tree::query_and_verify_precomputed_merkle_tree;
+use crate::algorithms::precomputed_tree_builder::precomputed_tree_root;
 use crate::algorithms::twin_tree::query_and_verify_merkle_twin_tree;
-use crate::dsl::plonk::hints::Hints;
-use anyhow::Result;
-use bitcoin_circle_stark::precomputed_merkle_tree::{
-    get_precomputed_merkle_tree_roots, PRECOMPUTED_MERKLE_TREE_ROOTS,
-};
-use bitcoin_script_dsl::builtins::cm31::CM31Var;
-use bitcoin_script_dsl::builtins::hash::HashVar;
+use crate::dsl::disassemble::record_region;
+use crate::dsl::plonk::hints::{Hints, LOG_N_ROWS};
+use crate::dsl::plonk::ldm_trace::StepLdm;
+use anyhow::{Error, Result};
 use bitcoin_script_dsl::builtins::m31::M31Var;
 use bitcoin_script_dsl::builtins::qm31::QM31Var;
 use bitcoin_script_dsl::builtins::table::TableVar;
 use bitcoin_script_dsl::bvar::AllocVar;
 use bitcoin_script_dsl::constraint_system::{ConstraintSystem, ConstraintSystemRef};
-use bitcoin_script_dsl::ldm::LDM;
 use stwo_prover::core::prover::N_QUERIES;
 
-pub fn generate_cs(hints: &Hints, ldm: &mut LDM) -> Result<ConstraintSystemRef> {
+pub fn generate_cs<C: ChannelVar, L: StepLdm>(
+    hints: &Hints,
+    ldm: &mut L,
+) -> Result<ConstraintSystemRef> {
     let cs = ConstraintSystem::new_ref();
     ldm.init(&cs)?;
 
-    let composition_commitment_var: HashVar = ldm.read("composition_commitment")?;
+    let composition_commitment_var: ChannelDigestVar<C> = ldm.read("composition_commitment")?;
 
     let mut queries = Vec::<M31Var>::new();
     for i in 0..N_QUERIES {
@@ -27,78 +29,91 @@ pub fn generate_cs(hints: &Hints, ldm: &mut LDM) -> Result<ConstraintSystemRef>
     }
 
     // Step 1: query the composition commitment on the queries
+    let tree_start = hints.mask.tree_start(3);
     for (i, (query, proof)) in queries
         .iter()
         .zip(hints.fiat_shamir_hints.merkle_proofs_compositions.iter())
         .enumerate()
     {
-        let res = query_and_verify_merkle_twin_tree(&composition_commitment_var, query, proof)?;
-
-        let left = QM31Var {
-            first: CM31Var {
-                real: res.0[0].clone(),
-                imag: res.0[1].clone(),
-            },
-            second: CM31Var {
-                real: res.0[2].clone(),
-                imag: res.0[3].clone(),
-            },
-        };
-        let right = QM31Var {
-            first: CM31Var {
-                real: res.1[0].clone(),
-                imag: res.1[1].clone(),
-            },
-            second: CM31Var {
-                real: res.1[2].clone(),
-                imag: res.1[3].clone(),
-            },
-        };
-        ldm.write(format!("composition_queried_results_{}_l", i), &left)?;
-        ldm.write(format!("composition_queried_results_{}_r", i), &right)?;
+        let res = record_region(&cs, "merkle twin tree", || {
+            query_and_verify_merkle_twin_tree::<C::Hasher>(
+                &composition_commitment_var,
+                query,
+                proof,
+            )
+        })?;
+        assert_eq!(res.0.len(), hints.mask.offsets[3].len());
+
+        for (j, (left, right)) in res.0.iter().zip(res.1.iter()).enumerate() {
+            ldm.write(format!("queried_results_{}_{}_l", tree_start + j, i), left)?;
+            ldm.write(format!("queried_results_{}_{}_r", tree_start + j, i), right)?;
+        }
     }
 
-    let precomputed_merkle_tree_roots =
-        PRECOMPUTED_MERKLE_TREE_ROOTS.get_or_init(get_precomputed_merkle_tree_roots);
+    let table = TableVar::new_constant(&cs, ())?;
+
+    let precomputed_merkle_tree_root = match hints.domain_point_source {
+        DomainPointSource::PrecomputedTree => {
+            Some(precomputed_tree_root(hints.precomputed_tree_log_size)?)
+        }
+        DomainPointSource::InScript => None,
+    };
 
     for (i, (query, pre_query_quotients_hint)) in queries
         .iter()
         .zip(hints.per_query_quotients_hints.iter())
         .enumerate()
     {
-        let proof = &pre_query_quotients_hint.precomputed_merkle_proofs[0];
-        let res = query_and_verify_precomputed_merkle_tree(
-            &precomputed_merkle_tree_roots.get(&15).unwrap().to_vec(),
-            query,
-            proof,
-        )?;
+        let res = match &precomputed_merkle_tree_root {
+            Some(root) => {
+                let proof = &pre_query_quotients_hint.precomputed_merkle_proofs[0];
+                record_region(&cs, "precomputed merkle tree", || {
+                    query_and_verify_precomputed_merkle_tree(root, query, proof)
+                })?
+            }
+            None => record_region(&cs, "domain point and twiddles", || {
+                compute_point_and_twiddles(&table, query, hints.precomputed_tree_log_size)
+            })?,
+        };
         ldm.write(format!("circle_point_x_{}", i), &res.circle_point_x_var)?;
         ldm.write(format!("circle_point_y_{}", i), &res.circle_point_y_var)?;
-        ldm.write(format!("twiddle_factor_1_{}", i), &res.twiddles_var[13])?;
-        ldm.write(format!("twiddle_factor_2_{}", i), &res.twiddles_var[12])?;
-        ldm.write(format!("twiddle_factor_3_{}", i), &res.twiddles_var[11])?;
-        ldm.write(format!("twiddle_factor_4_{}", i), &res.twiddles_var[10])?;
-        ldm.write(format!("twiddle_factor_5_{}", i), &res.twiddles_var[9])?;
-    }
 
-    let table = TableVar::new_constant(&cs, ())?;
+        // the twiddles of the folding layers, counted from the top of the tree
+        for layer in 1..=LOG_N_ROWS as usize {
+            let twiddle_var = (hints.precomputed_tree_log_size - 1)
+                .checked_sub(layer)
+                .and_then(|idx| res.twiddles_var.get(idx))
+                .ok_or_else(|| {
+                    Error::msg(format!(
+                        "The precomputed tree of log size {} has no twiddle for the folding layer {}",
+                        hints.precomputed_tree_log_size, layer
+                    ))
+                })?;
+            ldm.write(format!("twiddle_factor_{}_{}", layer, i), twiddle_var)?;
+        }
+    }
 
     let a_val_var: QM31Var = ldm.read("trace_oods_value_1")?;
     let b_val_var: QM31Var = ldm.read("trace_oods_value_2")?;
     let c_val_var: QM31Var = ldm.read("trace_oods_value_3")?;
     let op_var: QM31Var = ldm.read("constant_oods_value_3")?;
 
-    let a_val_times_b_val = &a_val_var * (&table, &b_val_var);
+    let qm31_mult = |a: &QM31Var, b: &QM31Var| record_region(&cs, "qm31 mult", || a * (&table, b));
+
+    let a_val_times_b_val = qm31_mult(&a_val_var, &b_val_var);
 
-    let mut res1 = &(&(&op_var * (&table, &(&(&a_val_var + &b_val_var) - &a_val_times_b_val)))
+    let mut res1 = &(&qm31_mult(&op_var, &(&(&a_val_var + &b_val_var) - &a_val_times_b_val))
         + &a_val_times_b_val)
         - &c_val_var;
 
     let composition_fold_random_coeff_var: QM31Var = ldm.read("composition_fold_random_coeff")?;
-    let composition_fold_random_coeff_squared_var =
-        &composition_fold_random_coeff_var * &composition_fold_random_coeff_var;
+    let composition_fold_random_coeff_squared_var = record_region(&cs, "qm31 mult", || {
+        &composition_fold_random_coeff_var * &composition_fold_random_coeff_var
+    });
 
-    res1 = &res1 * &composition_fold_random_coeff_squared_var;
+    res1 = record_region(&cs, "qm31 mult", || {
+        &res1 * &composition_fold_random_coeff_squared_var
+    });
 
     let a_wire_var: QM31Var = ldm.read("constant_oods_value_0")?;
     let b_wire_var: QM31Var = ldm.read("constant_oods_value_1")?;
@@ -106,11 +121,11 @@ pub fn generate_cs(hints: &Hints, ldm: &mut LDM) -> Result<ConstraintSystemRef>
     let alpha_var: QM31Var = ldm.read("alpha")?;
     let z_var: QM31Var = ldm.read("z")?;
 
-    let denominator_1_var = &(&a_wire_var + &(&alpha_var * (&table, &a_val_var))) - &z_var;
-    let denominator_2_var = &(&b_wire_var + &(&alpha_var * (&table, &b_val_var))) - &z_var;
+    let denominator_1_var = &(&a_wire_var + &qm31_mult(&alpha_var, &a_val_var)) - &z_var;
+    let denominator_2_var = &(&b_wire_var + &qm31_mult(&alpha_var, &b_val_var)) - &z_var;
 
     let num_aggregated_var = &denominator_1_var + &denominator_2_var;
-    let denom_aggregated_var = &denominator_1_var * (&table, &denominator_2_var);
+    let denom_aggregated_var = qm31_mult(&denominator_1_var, &denominator_2_var);
 
     let a_b_logup_0_var: QM31Var = ldm.read("interaction_oods_value_0")?;
     let a_b_logup_1_var: QM31Var = ldm.read("interaction_oods_value_1")?;
@@ -121,12 +136,14 @@ pub fn generate_cs(hints: &Hints, ldm: &mut LDM) -> Result<ConstraintSystemRef>
     a_b_logup_var = &a_b_logup_var + &a_b_logup_2_var.shift_by_j();
     a_b_logup_var = &a_b_logup_var + &a_b_logup_3_var.shift_by_ij();
 
-    let mut res2 = &(&a_b_logup_var * (&table, &denom_aggregated_var)) - &num_aggregated_var;
-    res2 = &res2 * &composition_fold_random_coeff_var;
+    let mut res2 = &qm31_mult(&a_b_logup_var, &denom_aggregated_var) - &num_aggregated_var;
+    res2 = record_region(&cs, "qm31 mult", || {
+        &res2 * &composition_fold_random_coeff_var
+    });
 
     let res12 = &res1 + &res2;
 
-    let denominator_3_var = &(&c_wire_var + &(&alpha_var * (&table, &c_val_var))) - &z_var;
+    let denominator_3_var = &(&c_wire_var + &qm31_mult(&alpha_var, &c_val_var)) - &z_var;
 
     let c_logup_0_var: QM31Var = ldm.read("interaction_oods_value_4")?;
     let c_logup_1_var: QM31Var = ldm.read("interaction_oods_value_6")?;
@@ -150,7 +167,7 @@ pub fn generate_cs(hints: &Hints, ldm: &mut LDM) -> Result<ConstraintSystemRef>
     let claimed_sum_divided = QM31Var::new_hint(&cs, hints.fiat_shamir_hints.claimed_sum_divided)?;
 
     let mut res3 = &(&(&c_logup_var - &c_logup_next_var) - &a_b_logup_var) + &claimed_sum_divided;
-    res3 = &res3 * (&table, &denominator_3_var);
+    res3 = qm31_mult(&res3, &denominator_3_var);
 
     let mult_var: QM31Var = ldm.read("trace_oods_value_0")?;
     res3 = &res3 + &mult_var;
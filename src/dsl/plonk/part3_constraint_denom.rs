@@ -1,16 +1,17 @@
-use crate::algorithms::point::{
-    add_constant_m31_point, add_constant_m31_point_x_only, SecureCirclePointVar,
-};
+use crate::algorithms::div::DivVar;
+use crate::algorithms::point::SecureCirclePointVar;
+use crate::dsl::disassemble::record_region;
 use crate::dsl::plonk::hints::{Hints, LOG_N_ROWS};
+use crate::dsl::plonk::ldm_trace::StepLdm;
+use crate::dsl::plonk::mask::sample_point_name;
 use anyhow::Result;
 use bitcoin_script_dsl::builtins::qm31::QM31Var;
 use bitcoin_script_dsl::builtins::table::TableVar;
 use bitcoin_script_dsl::bvar::{AllocVar, BVar};
 use bitcoin_script_dsl::constraint_system::{ConstraintSystem, ConstraintSystemRef};
-use bitcoin_script_dsl::ldm::LDM;
 use stwo_prover::core::poly::circle::CanonicCoset;
 
-pub fn generate_cs(_: &Hints, ldm: &mut LDM) -> Result<ConstraintSystemRef> {
+pub fn generate_cs<L: StepLdm>(hints: &Hints, ldm: &mut L) -> Result<ConstraintSystemRef> {
     let cs = ConstraintSystem::new_ref();
     ldm.init(&cs)?;
 
@@ -25,18 +26,13 @@ pub fn generate_cs(_: &Hints, ldm: &mut LDM) -> Result<ConstraintSystemRef> {
     let table = TableVar::new_constant(&cs, ())?;
 
     let coset = CanonicCoset::new(LOG_N_ROWS).coset;
-    let shift = -coset.initial + coset.step_size.half().to_point();
-    let mut cur_x = add_constant_m31_point_x_only(&oods_point, &table, shift);
-    for _ in 1..coset.log_size {
-        cur_x = &cur_x * (&table, &cur_x);
-        cur_x = &cur_x + &cur_x;
-        cur_x = cur_x.sub1();
-    }
-
-    let constraint_denom = cur_x.inverse(&table);
+    let vanishing = record_region(&cs, "coset vanishing", || {
+        oods_point.coset_vanishing(&table, coset)
+    });
 
     let constraint_num: QM31Var = ldm.read("constraint_num")?;
-    let computed_composition = &constraint_denom * (&table, &constraint_num);
+    let computed_composition =
+        record_region(&cs, "qm31 div", || constraint_num.div(&table, &vanishing))?;
 
     let composition_0_var: QM31Var = ldm.read("composition_oods_value_0")?;
     let composition_1_var: QM31Var = ldm.read("composition_oods_value_1")?;
@@ -49,14 +45,21 @@ pub fn generate_cs(_: &Hints, ldm: &mut LDM) -> Result<ConstraintSystemRef> {
 
     computed_composition.equalverify(&composition_var)?;
 
-    // shift the oods point
+    // shift the oods point to the other sample points in the mask
     let trace_step = CanonicCoset::new(LOG_N_ROWS).step();
-    let shift_minus_1 = trace_step.mul_signed(-1);
+    for offset in hints.mask.distinct_offsets() {
+        if offset == 0 {
+            continue;
+        }
 
-    let oods_shifted_by_1 = add_constant_m31_point(&oods_point, &table, shift_minus_1);
+        let shifted_point = record_region(&cs, "shift point", || {
+            oods_point.shift_by(&table, trace_step, offset)
+        });
 
-    ldm.write("oods_shifted_by_1_x", &oods_shifted_by_1.x)?;
-    ldm.write("oods_shifted_by_1_y", &oods_shifted_by_1.y)?;
+        let name = sample_point_name(offset);
+        ldm.write(format!("{}_x", name), &shifted_point.x)?;
+        ldm.write(format!("{}_y", name), &shifted_point.y)?;
+    }
 
     ldm.save()?;
     Ok(cs)
@@ -1,67 +1,86 @@
-use crate::algorithms::pair_vanishing::prepare_pair_vanishing;
+use crate::algorithms::pair_vanishing::prepare_pair_vanishing_batch;
 use crate::algorithms::point::SecureCirclePointVar;
+use crate::dsl::disassemble::record_region;
 use crate::dsl::plonk::hints::Hints;
+use crate::dsl::plonk::ldm_trace::StepLdm;
+use crate::dsl::plonk::mask::{alpha_power_key, needed_alpha_powers, sample_point_name};
 use bitcoin_script_dsl::builtins::qm31::QM31Var;
 use bitcoin_script_dsl::builtins::table::TableVar;
 use bitcoin_script_dsl::bvar::AllocVar;
 use bitcoin_script_dsl::constraint_system::{ConstraintSystem, ConstraintSystemRef};
-use bitcoin_script_dsl::ldm::LDM;
+use std::collections::BTreeMap;
 
-pub fn generate_cs(_: &Hints, ldm: &mut LDM) -> anyhow::Result<ConstraintSystemRef> {
+pub fn generate_cs<L: StepLdm>(hints: &Hints, ldm: &mut L) -> anyhow::Result<ConstraintSystemRef> {
     let cs = ConstraintSystem::new_ref();
     ldm.init(&cs)?;
 
-    let oods_x: QM31Var = ldm.read("oods_x")?;
-    let oods_y: QM31Var = ldm.read("oods_y")?;
+    let mut points = vec![];
+    for batch in hints.sample_batches.iter() {
+        let name = sample_point_name(batch.offset);
 
-    let oods_point = SecureCirclePointVar {
-        x: oods_x,
-        y: oods_y,
-    };
+        let x: QM31Var = ldm.read(format!("{}_x", name))?;
+        let y: QM31Var = ldm.read(format!("{}_y", name))?;
+        points.push(SecureCirclePointVar { x, y });
+    }
 
     let table = TableVar::new_constant(&cs, ())?;
 
-    let prepared_oods = prepare_pair_vanishing(&oods_point, &table);
-    ldm.write("prepared_oods_a", &prepared_oods.0)?;
-    ldm.write("prepared_oods_b", &prepared_oods.1)?;
-
-    let oods_shifted_by_1_x: QM31Var = ldm.read("oods_shifted_by_1_x")?;
-    let oods_shifted_by_1_y: QM31Var = ldm.read("oods_shifted_by_1_y")?;
-
-    let oods_shifted_by_1_point = SecureCirclePointVar {
-        x: oods_shifted_by_1_x,
-        y: oods_shifted_by_1_y,
-    };
+    let prepared = record_region(&cs, "pair vanishing", || {
+        prepare_pair_vanishing_batch(&points, &table)
+    })?;
+    for (batch, (a, b)) in hints.sample_batches.iter().zip(prepared.iter()) {
+        let name = sample_point_name(batch.offset);
 
-    let prepared_oods_shifted_by_1 = prepare_pair_vanishing(&oods_shifted_by_1_point, &table);
-    ldm.write(
-        "prepared_oods_shifted_by_1_a",
-        &prepared_oods_shifted_by_1.0,
-    )?;
-    ldm.write(
-        "prepared_oods_shifted_by_1_b",
-        &prepared_oods_shifted_by_1.1,
-    )?;
+        ldm.write(format!("prepared_{}_a", name), a)?;
+        ldm.write(format!("prepared_{}_b", name), b)?;
+    }
 
     let alpha: QM31Var = ldm.read("line_batch_random_coeff")?;
 
-    // The needed alphas are:
-    // - alpha, alpha^2, alpha^3, alpha^4, alpha^8, alpha^12, alpha^20
-
-    let alpha2 = &alpha * (&table, &alpha);
-    let alpha3 = &alpha2 * (&table, &alpha);
-    let alpha4 = &alpha2 * (&table, &alpha2);
-    let alpha8 = &alpha4 * (&table, &alpha4);
-    let alpha12 = &alpha8 * (&table, &alpha4);
-    let alpha20 = &alpha8 * (&table, &alpha12);
+    // The needed alphas are determined by the layout of the sample batches, see
+    // `chunk_alpha_power`.
+    let mut powers = BTreeMap::new();
+    powers.insert(1, alpha);
 
-    ldm.write("line_batch_random_coeff_2", &alpha2)?;
-    ldm.write("line_batch_random_coeff_3", &alpha3)?;
-    ldm.write("line_batch_random_coeff_4", &alpha4)?;
-    ldm.write("line_batch_random_coeff_8", &alpha8)?;
-    ldm.write("line_batch_random_coeff_12", &alpha12)?;
-    ldm.write("line_batch_random_coeff_20", &alpha20)?;
+    for power in needed_alpha_powers(&hints.sample_batches) {
+        compute_alpha_power(&table, &mut powers, power);
+        if power != 1 {
+            ldm.write(alpha_power_key(power), powers.get(&power).unwrap())?;
+        }
+    }
 
     ldm.save()?;
     Ok(cs)
 }
+
+/// Compute alpha^power, reusing the powers that have been computed before.
+///
+/// If there are two known powers that add up to `power`, it takes one multiplication. Otherwise,
+/// it falls back to square-and-multiply.
+fn compute_alpha_power(table: &TableVar, powers: &mut BTreeMap<usize, QM31Var>, power: usize) {
+    if powers.contains_key(&power) {
+        return;
+    }
+
+    let pair = powers
+        .keys()
+        .rev()
+        .find(|&&k| k < power && powers.contains_key(&(power - k)))
+        .copied();
+
+    let qm31_mult =
+        |a: &QM31Var, b: &QM31Var| record_region(&a.cs(), "qm31 mult", || a * (table, b));
+
+    let res = if let Some(k) = pair {
+        qm31_mult(powers.get(&k).unwrap(), powers.get(&(power - k)).unwrap())
+    } else if power % 2 == 0 {
+        compute_alpha_power(table, powers, power / 2);
+        let half = powers.get(&(power / 2)).unwrap();
+        qm31_mult(half, half)
+    } else {
+        compute_alpha_power(table, powers, power - 1);
+        qm31_mult(powers.get(&(power - 1)).unwrap(), powers.get(&1).unwrap())
+    };
+
+    powers.insert(power, res);
+}
@@ -0,0 +1,59 @@
+use crate::algorithms::column_line_coeffs::column_line_coeffs;
+use crate::dsl::disassemble::record_region;
+use crate::dsl::plonk::hints::Hints;
+use crate::dsl::plonk::ldm_trace::StepLdm;
+use crate::dsl::plonk::mask::{column_line_coeffs_chunks, sample_point_name};
+use anyhow::Result;
+use bitcoin_script_dsl::builtins::qm31::QM31Var;
+use bitcoin_script_dsl::builtins::table::TableVar;
+use bitcoin_script_dsl::bvar::AllocVar;
+use bitcoin_script_dsl::constraint_system::{ConstraintSystem, ConstraintSystemRef};
+use itertools::Itertools;
+
+/// The number of steps that compute the column line coefficients.
+pub fn num_steps(hints: &Hints) -> usize {
+    column_line_coeffs_chunks(&hints.sample_batches).len()
+}
+
+pub fn generate_cs<L: StepLdm>(
+    hints: &Hints,
+    ldm: &mut L,
+    step_idx: usize,
+) -> Result<ConstraintSystemRef> {
+    let cs = ConstraintSystem::new_ref();
+    ldm.init(&cs)?;
+
+    let samples = &column_line_coeffs_chunks(&hints.sample_batches)[step_idx];
+
+    let table = TableVar::new_constant(&cs, ())?;
+
+    // samples of the same batch share the sample point and are processed together
+    for batch_idx in samples.iter().map(|(batch_idx, _)| *batch_idx).dedup() {
+        let batch = &hints.sample_batches[batch_idx];
+        let positions = samples
+            .iter()
+            .filter(|(b, _)| *b == batch_idx)
+            .map(|(_, pos)| *pos)
+            .collect_vec();
+
+        let y: QM31Var = ldm.read(format!("{}_y", sample_point_name(batch.offset)))?;
+
+        let mut oods_values = Vec::<QM31Var>::new();
+        for &pos in positions.iter() {
+            oods_values
+                .push(ldm.read(hints.mask.oods_value_key(batch.columns[pos], batch.offset))?);
+        }
+
+        let res = record_region(&cs, "column line coeffs", || {
+            column_line_coeffs(&table, &y, &oods_values)
+        })?;
+
+        for (&pos, (a, b)) in positions.iter().zip(res.iter()) {
+            ldm.write(format!("column_line_coeffs_{}_{}_a", batch_idx, pos), a)?;
+            ldm.write(format!("column_line_coeffs_{}_{}_b", batch_idx, pos), b)?;
+        }
+    }
+
+    ldm.save()?;
+    Ok(cs)
+}
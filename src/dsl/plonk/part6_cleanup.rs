@@ -1,9 +1,9 @@
 use crate::dsl::plonk::hints::Hints;
+use crate::dsl::plonk::ldm_trace::StepLdm;
 use anyhow::Result;
 use bitcoin_script_dsl::constraint_system::{ConstraintSystem, ConstraintSystemRef};
-use bitcoin_script_dsl::ldm::LDM;
 
-pub fn generate_cs(_: &Hints, ldm: &mut LDM) -> Result<ConstraintSystemRef> {
+pub fn generate_cs<L: StepLdm>(_: &Hints, ldm: &mut L) -> Result<ConstraintSystemRef> {
     let cs = ConstraintSystem::new_ref();
     ldm.init(&cs)?;
     ldm.check()?;
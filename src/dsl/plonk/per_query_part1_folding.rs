@@ -1,24 +1,42 @@
+use crate::algorithms::channel::{ChannelDigestVar, ChannelVar};
 use crate::algorithms::folding::{decompose_positions, ibutterfly, skip_one_and_extract_bits};
 use crate::algorithms::twin_tree::query_and_verify_merkle_twin_tree;
+use crate::dsl::disassemble::record_region;
 use crate::dsl::plonk::hints::{Hints, LOG_N_ROWS};
+use crate::dsl::plonk::ldm_trace::StepLdm;
 use anyhow::Result;
 use bitcoin_script_dsl::builtins::cm31::CM31Var;
-use bitcoin_script_dsl::builtins::hash::HashVar;
 use bitcoin_script_dsl::builtins::m31::M31Var;
 use bitcoin_script_dsl::builtins::qm31::QM31Var;
 use bitcoin_script_dsl::builtins::table::TableVar;
 use bitcoin_script_dsl::bvar::{AllocVar, BVar};
 use bitcoin_script_dsl::constraint_system::{ConstraintSystem, ConstraintSystemRef};
-use bitcoin_script_dsl::ldm::LDM;
 
-pub fn generate_cs(hints: &Hints, ldm: &mut LDM, query_idx: usize) -> Result<ConstraintSystemRef> {
+pub fn generate_cs<C: ChannelVar, L: StepLdm>(
+    hints: &Hints,
+    ldm: &mut L,
+    query_idx: usize,
+) -> Result<ConstraintSystemRef> {
     let cs = ConstraintSystem::new_ref();
     ldm.init(&cs)?;
 
     let query: M31Var = ldm.read(format!("query_{}", query_idx))?;
-    let queries = decompose_positions(&query, 5);
+    let queries = record_region(&cs, "decompose positions", || {
+        decompose_positions(&query, 5)
+    });
+
+    // the earlier queries are only read for the skip leaf of the query, which makes the same LDM
+    // accesses as this step and checks that one of them has the same parent (see `per_query_skip`).
+    //
+    // This costs the honest steps one LDM read per earlier query, i.e., at most N_QUERIES - 1 of
+    // them in a step, which `test_earlier_query_reads` measures. Reading them in the skip leaf
+    // only would leave the LDM in another state after the leaf than after the steps it skips, so
+    // every later step would need another witness for each combination of the skip leaves taken.
+    for i in 0..query_idx {
+        let _: M31Var = ldm.read(format!("query_{}", i))?;
+    }
 
-    let mut fri_tree_commitments_vars = Vec::<HashVar>::new();
+    let mut fri_tree_commitments_vars = Vec::<ChannelDigestVar<C>>::new();
     for i in 0..LOG_N_ROWS {
         fri_tree_commitments_vars.push(ldm.read(format!("fri_tree_commitments_{}", i))?);
     }
@@ -29,7 +47,9 @@ pub fn generate_cs(hints: &Hints, ldm: &mut LDM, query_idx: usize) -> Result<Con
         .zip(hints.per_query_fold_hints[query_idx].twin_proofs.iter())
         .zip(queries.iter())
     {
-        let res = query_and_verify_merkle_twin_tree(commitment, cur_query, proof)?;
+        let res = record_region(&cs, "merkle twin tree", || {
+            query_and_verify_merkle_twin_tree::<C::Hasher>(commitment, cur_query, proof)
+        })?;
 
         let left = QM31Var {
             first: CM31Var {
@@ -55,7 +75,9 @@ pub fn generate_cs(hints: &Hints, ldm: &mut LDM, query_idx: usize) -> Result<Con
         folding_intermediate_vars.push((left, right));
     }
 
-    let swap_bits_vars = skip_one_and_extract_bits(&query, 5);
+    let swap_bits_vars = record_region(&cs, "extract swap bits", || {
+        skip_one_and_extract_bits(&query, 5)
+    });
 
     let mut twiddles_vars = Vec::<M31Var>::new();
     twiddles_vars.push(ldm.read(format!("twiddle_factor_1_{}", query_idx))?);
@@ -77,15 +99,19 @@ pub fn generate_cs(hints: &Hints, ldm: &mut LDM, query_idx: usize) -> Result<Con
         .zip(twiddles_vars.iter())
         .zip(folding_alphas_vars.iter())
     {
-        let ifft_results_vars = ibutterfly(
-            &table,
-            &folding_intermediate_result.0,
-            &folding_intermediate_result.1,
-            twiddle_var,
-        );
-
-        folded_results_vars
-            .push(&ifft_results_vars.0 + &(&ifft_results_vars.1 * (&table, folding_alpha_var)));
+        let ifft_results_vars = record_region(&cs, "ibutterfly", || {
+            ibutterfly(
+                &table,
+                &folding_intermediate_result.0,
+                &folding_intermediate_result.1,
+                twiddle_var,
+            )
+        });
+
+        let folded_var = record_region(&cs, "qm31 mult", || {
+            &ifft_results_vars.1 * (&table, folding_alpha_var)
+        });
+        folded_results_vars.push(&ifft_results_vars.0 + &folded_var);
     }
 
     for i in 0..4 {
@@ -0,0 +1,180 @@
+use crate::algorithms::quotient::{
+    apply_twin, combine_numerators, denominator_inverses_from_prepared,
+};
+use crate::dsl::disassemble::record_region;
+use crate::dsl::plonk::hints::Hints;
+use crate::dsl::plonk::ldm_trace::StepLdm;
+use crate::dsl::plonk::mask::{
+    alpha_power_key, chunk_alpha_power, quotient_chunks, sample_point_name,
+};
+use anyhow::Result;
+use bitcoin_script_dsl::builtins::cm31::CM31Var;
+use bitcoin_script_dsl::builtins::m31::M31Var;
+use bitcoin_script_dsl::builtins::qm31::QM31Var;
+use bitcoin_script_dsl::builtins::table::TableVar;
+use bitcoin_script_dsl::bvar::AllocVar;
+use bitcoin_script_dsl::constraint_system::{ConstraintSystem, ConstraintSystemRef};
+
+/// The number of per-query steps that accumulate the quotients.
+pub fn num_steps(hints: &Hints) -> usize {
+    quotient_chunks(&hints.sample_batches).len()
+}
+
+pub fn generate_cs<L: StepLdm>(
+    hints: &Hints,
+    ldm: &mut L,
+    query_idx: usize,
+    chunk_idx: usize,
+) -> Result<ConstraintSystemRef> {
+    let cs = ConstraintSystem::new_ref();
+    ldm.init(&cs)?;
+
+    let batches = &hints.sample_batches;
+    let chunk = &quotient_chunks(batches)[chunk_idx];
+    let batch = &batches[chunk.batch];
+    let batch_name = sample_point_name(batch.offset);
+
+    let y: M31Var = ldm.read(format!("circle_point_y_{}", query_idx))?;
+    let table = TableVar::new_constant(&cs, ())?;
+
+    // Step 1: in the first chunk, compute the denominator inverses for all the sample points
+    if chunk_idx == 0 {
+        let x: M31Var = ldm.read(format!("circle_point_x_{}", query_idx))?;
+
+        let mut prepared = Vec::<(CM31Var, CM31Var)>::new();
+        for batch in batches.iter() {
+            let name = sample_point_name(batch.offset);
+            prepared.push((
+                ldm.read(format!("prepared_{}_a", name))?,
+                ldm.read(format!("prepared_{}_b", name))?,
+            ));
+        }
+
+        let denominator_inverses = record_region(&cs, "denominator inverses", || {
+            denominator_inverses_from_prepared(&table, &prepared, &x, &y)
+        });
+
+        for (batch, (l, r)) in batches.iter().zip(denominator_inverses.iter()) {
+            let name = sample_point_name(batch.offset);
+            ldm.write(format!("denominator_inverse_{}_{}_l", name, query_idx), l)?;
+            ldm.write(format!("denominator_inverse_{}_{}_r", name, query_idx), r)?;
+        }
+    }
+
+    // Step 2: compute the numerators of the columns in this chunk
+    let mut numerators_l = vec![];
+    let mut numerators_r = vec![];
+    for pos in chunk.start..chunk.end {
+        let column = batch.columns[pos];
+
+        let queried_result_l: M31Var =
+            ldm.read(format!("queried_results_{}_{}_l", column, query_idx))?;
+        let queried_result_r: M31Var =
+            ldm.read(format!("queried_results_{}_{}_r", column, query_idx))?;
+
+        let a: CM31Var = ldm.read(format!("column_line_coeffs_{}_{}_a", chunk.batch, pos))?;
+        let b: CM31Var = ldm.read(format!("column_line_coeffs_{}_{}_b", chunk.batch, pos))?;
+
+        let numerator = record_region(&cs, "apply twin", || {
+            apply_twin(&table, &y, &queried_result_l, &queried_result_r, &a, &b)
+        });
+        numerators_l.push(numerator.0);
+        numerators_r.push(numerator.1);
+    }
+
+    // Step 3: combine the numerators with alpha
+    let mut alpha_powers = Vec::<QM31Var>::new();
+    for power in 1..chunk.end - chunk.start {
+        alpha_powers.push(ldm.read(alpha_power_key(power))?);
+    }
+
+    let mut sum_l = record_region(&cs, "combine numerators", || {
+        combine_numerators(&table, &alpha_powers, &numerators_l)
+    })?;
+    let mut sum_r = record_region(&cs, "combine numerators", || {
+        combine_numerators(&table, &alpha_powers, &numerators_r)
+    })?;
+
+    let chunk_power = chunk_alpha_power(batches, chunk);
+    if chunk_power > 0 {
+        let alpha_chunk_power: QM31Var = ldm.read(alpha_power_key(chunk_power))?;
+        sum_l = record_region(&cs, "qm31 mult", || &alpha_chunk_power * (&table, &sum_l));
+        sum_r = record_region(&cs, "qm31 mult", || &alpha_chunk_power * (&table, &sum_r));
+    }
+
+    // Step 4: add the sum of the previous chunks of the same batch
+    if chunk.start > 0 {
+        let batch_sum_l: QM31Var = ldm.read(format!(
+            "quotient_batch_sum_{}_{}_{}_l",
+            batch_name, chunk.start, query_idx
+        ))?;
+        let batch_sum_r: QM31Var = ldm.read(format!(
+            "quotient_batch_sum_{}_{}_{}_r",
+            batch_name, chunk.start, query_idx
+        ))?;
+
+        sum_l = &batch_sum_l + &sum_l;
+        sum_r = &batch_sum_r + &sum_r;
+    }
+
+    if chunk.end < batch.columns.len() {
+        // the key records the number of columns summed so far
+        ldm.write(
+            format!(
+                "quotient_batch_sum_{}_{}_{}_l",
+                batch_name, chunk.end, query_idx
+            ),
+            &sum_l,
+        )?;
+        ldm.write(
+            format!(
+                "quotient_batch_sum_{}_{}_{}_r",
+                batch_name, chunk.end, query_idx
+            ),
+            &sum_r,
+        )?;
+    } else {
+        // Step 5: if the batch is complete, divide it by the vanishing polynomial of its sample
+        // point and add it to the quotient
+        let denominator_inverse_l: CM31Var = ldm.read(format!(
+            "denominator_inverse_{}_{}_l",
+            batch_name, query_idx
+        ))?;
+        let denominator_inverse_r: CM31Var = ldm.read(format!(
+            "denominator_inverse_{}_{}_r",
+            batch_name, query_idx
+        ))?;
+
+        let mut quotient_l = record_region(&cs, "qm31 by cm31 mult", || {
+            &sum_l * (&table, &denominator_inverse_l)
+        });
+        let mut quotient_r = record_region(&cs, "qm31 by cm31 mult", || {
+            &sum_r * (&table, &denominator_inverse_r)
+        });
+
+        // the key records the last sample batch that has been added to the quotient
+        if chunk.batch > 0 {
+            let prev_batch_name = sample_point_name(batches[chunk.batch - 1].offset);
+
+            let prev_quotient_l: QM31Var =
+                ldm.read(format!("quotient_{}_{}_l", prev_batch_name, query_idx))?;
+            let prev_quotient_r: QM31Var =
+                ldm.read(format!("quotient_{}_{}_r", prev_batch_name, query_idx))?;
+
+            quotient_l = &prev_quotient_l + &quotient_l;
+            quotient_r = &prev_quotient_r + &quotient_r;
+        }
+
+        ldm.write(
+            format!("quotient_{}_{}_l", batch_name, query_idx),
+            &quotient_l,
+        )?;
+        ldm.write(
+            format!("quotient_{}_{}_r", batch_name, query_idx),
+            &quotient_r,
+        )?;
+    }
+
+    ldm.save()?;
+    Ok(cs)
+}
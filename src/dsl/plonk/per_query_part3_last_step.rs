@@ -0,0 +1,49 @@
+use crate::dsl::disassemble::record_region;
+use crate::dsl::plonk::hints::Hints;
+use crate::dsl::plonk::ldm_trace::StepLdm;
+use crate::dsl::plonk::mask::sample_point_name;
+use bitcoin_script_dsl::builtins::m31::M31Var;
+use bitcoin_script_dsl::builtins::qm31::QM31Var;
+use bitcoin_script_dsl::builtins::table::TableVar;
+use bitcoin_script_dsl::bvar::{AllocVar, BVar};
+use bitcoin_script_dsl::constraint_system::{ConstraintSystem, ConstraintSystemRef};
+
+pub fn generate_cs<L: StepLdm>(
+    hints: &Hints,
+    ldm: &mut L,
+    query_idx: usize,
+) -> anyhow::Result<ConstraintSystemRef> {
+    let cs = ConstraintSystem::new_ref();
+    ldm.init(&cs)?;
+
+    // the quotient has accumulated all the sample batches once the last batch has been added
+    let last_batch_name = sample_point_name(hints.sample_batches.last().unwrap().offset);
+
+    let quotient_l: QM31Var = ldm.read(format!("quotient_{}_{}_l", last_batch_name, query_idx))?;
+    let quotient_r: QM31Var = ldm.read(format!("quotient_{}_{}_r", last_batch_name, query_idx))?;
+
+    let table = TableVar::new_constant(&cs, ())?;
+
+    let y: M31Var = ldm.read(format!("circle_point_y_{}", query_idx))?;
+    let y_inv = y.inverse(&table);
+
+    let ifft_results_vars = {
+        let new_v0 = &quotient_l + &quotient_r;
+        let diff = &quotient_l - &quotient_r;
+        let new_v1 = record_region(&cs, "qm31 by m31 mult", || &diff * (&table, &y_inv));
+        (new_v0, new_v1)
+    };
+
+    let fri_fold_random_coeff_var: QM31Var = ldm.read("fri_fold_random_coeff")?;
+    let mut folded_result = record_region(&cs, "qm31 mult", || {
+        &fri_fold_random_coeff_var * (&table, &ifft_results_vars.1)
+    });
+    folded_result = &folded_result + &ifft_results_vars.0;
+
+    let expected_entry_quotient: QM31Var =
+        ldm.read(format!("expected_entry_quotient_{}", query_idx))?;
+    expected_entry_quotient.equalverify(&folded_result)?;
+
+    ldm.save()?;
+    Ok(cs)
+}
@@ -0,0 +1,135 @@
+use crate::algorithms::folding::{decompose_positions, verify_equal_to_any};
+use crate::dsl::disassemble::record_region;
+use crate::dsl::plonk::hints::{Hints, PlonkChannelVar};
+use crate::dsl::plonk::ldm_trace::{LdmValue, StepLdm};
+use anyhow::{Error, Result};
+use bitcoin_script_dsl::builtins::m31::M31Var;
+use bitcoin_script_dsl::constraint_system::{ConstraintSystem, ConstraintSystemRef};
+use std::any::Any;
+use std::collections::BTreeMap;
+
+/// Generate the skip leaf of a query, which takes the place of the per-query steps of the query
+/// if it has the same parent as an earlier query.
+///
+/// Two queries with the same parent open the same twin leaves in every tree and fold into the
+/// same values, so the per-query steps of the earlier query already verify the later one. The
+/// leaf checks in script that the parent of the query is the parent of one of the earlier
+/// queries, which the folding step of the query reads for this purpose.
+///
+/// The leaf makes the same LDM accesses, in the same order, as the steps it skips, so that it
+/// leaves the LDM in the same state and the steps after it are the same on both paths. The entries
+/// that the skipped steps read are read as usual. The entries that they write are only read by the
+/// skipped steps themselves, so the leaf writes them from hints.
+pub fn generate_cs<L: StepLdm>(
+    hints: &Hints,
+    ldm: &mut L,
+    query_idx: usize,
+) -> Result<ConstraintSystemRef> {
+    if query_idx == 0 {
+        return Err(Error::msg(
+            "The first query has no earlier query to share a parent with",
+        ));
+    }
+
+    let cs = ConstraintSystem::new_ref();
+    ldm.init(&cs)?;
+
+    // the constraint systems of the skipped steps are dropped, and only their LDM accesses are
+    // made in the leaf
+    let mut replay = ReplayLdm {
+        ldm: &mut *ldm,
+        cs: cs.clone(),
+        step_cs: None,
+        queries: BTreeMap::new(),
+    };
+    super::per_query_part1_folding::generate_cs::<PlonkChannelVar, _>(
+        hints,
+        &mut replay,
+        query_idx,
+    )?;
+    for chunk_idx in 0..super::per_query_part2_quotient_chunk::num_steps(hints) {
+        super::per_query_part2_quotient_chunk::generate_cs(
+            hints,
+            &mut replay,
+            query_idx,
+            chunk_idx,
+        )?;
+    }
+    super::per_query_part3_last_step::generate_cs(hints, &mut replay, query_idx)?;
+    let queries = replay.queries;
+
+    let parent_of = |idx: usize| -> Result<M31Var> {
+        let query = queries
+            .get(&idx)
+            .ok_or_else(|| Error::msg(format!("The folding step does not read `query_{}`", idx)))?;
+        Ok(record_region(&cs, "decompose positions", || decompose_positions(query, 1)).remove(0))
+    };
+
+    let parent = parent_of(query_idx)?;
+    let earlier_parents = (0..query_idx).map(parent_of).collect::<Result<Vec<_>>>()?;
+    record_region(&cs, "shared parent", || {
+        verify_equal_to_any(&parent, &earlier_parents)
+    });
+
+    ldm.save()?;
+    Ok(cs)
+}
+
+/// The LDM as the skipped steps see it, which passes their accesses on to the LDM of the leaf.
+struct ReplayLdm<'a, L: StepLdm> {
+    ldm: &'a mut L,
+    /// The constraint system of the leaf.
+    cs: ConstraintSystemRef,
+    /// The constraint system of the skipped step that is being generated.
+    step_cs: Option<ConstraintSystemRef>,
+    /// The queries that have been read, by their indices.
+    queries: BTreeMap<usize, M31Var>,
+}
+
+impl<L: StepLdm> ReplayLdm<'_, L> {
+    fn step_cs(&self) -> Result<&ConstraintSystemRef> {
+        self.step_cs
+            .as_ref()
+            .ok_or_else(|| Error::msg("The skipped step has not initialized the LDM"))
+    }
+}
+
+impl<L: StepLdm> StepLdm for ReplayLdm<'_, L> {
+    fn init(&mut self, cs: &ConstraintSystemRef) -> Result<()> {
+        self.step_cs = Some(cs.clone());
+        Ok(())
+    }
+
+    fn read<T: LdmValue>(&mut self, key: impl ToString) -> Result<T> {
+        let key = key.to_string();
+        let value: T = self.ldm.read(&key)?;
+
+        let query_idx = key
+            .strip_prefix("query_")
+            .and_then(|idx| idx.parse::<usize>().ok());
+        if let (Some(query_idx), Some(query)) =
+            (query_idx, (&value as &dyn Any).downcast_ref::<M31Var>())
+        {
+            self.queries.insert(query_idx, query.clone());
+        }
+
+        value.to_hint(self.step_cs()?)
+    }
+
+    fn write<T: LdmValue>(&mut self, key: impl ToString, value: &T) -> Result<()> {
+        self.ldm.write(key, &value.to_hint(&self.cs)?)
+    }
+
+    fn save(&mut self) -> Result<()> {
+        // the leaf saves the LDM once, after all the skipped steps
+        Ok(())
+    }
+
+    fn check(&mut self) -> Result<()> {
+        Err(Error::msg("The per-query steps do not check the LDM"))
+    }
+
+    fn state(&self) -> [Vec<u8>; 2] {
+        self.ldm.state()
+    }
+}
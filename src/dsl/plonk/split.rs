@@ -0,0 +1,429 @@
+use crate::dsl::disassemble::{disassemble, strip_region_markers, with_recording, RecordedRegion};
+use crate::dsl::plonk::hints::{Hints, PlonkChannelVar};
+use crate::dsl::plonk::ldm_liveness::LiveLdm;
+use crate::dsl::plonk::ldm_schema::LdmSchema;
+use crate::dsl::plonk::ldm_trace::{locate_read_hints, LdmAccess, LdmReadHint, StepLdm, TracedLDM};
+use anyhow::Result;
+use bitcoin::script::write_scriptint;
+use bitcoin_circle_stark::treepp::*;
+use bitcoin_script_dsl::compiler::Compiler;
+use bitcoin_script_dsl::constraint_system::{ConstraintSystemRef, Element};
+use bitcoin_script_dsl::ldm::LDM;
+use std::sync::OnceLock;
+use stwo_prover::core::prover::N_QUERIES;
+
+pub type Witness = Vec<Vec<u8>>;
+
+#[derive(Clone)]
+pub struct PlonkVerifierInput {
+    pub stack: Witness,
+    pub hints: Witness,
+}
+
+impl From<PlonkVerifierInput> for Script {
+    fn from(input: PlonkVerifierInput) -> Script {
+        script! {
+            for elem in input.stack {
+                { elem }
+            }
+            for elem in input.hints {
+                { elem }
+            }
+        }
+    }
+}
+
+pub struct PlonkAllInformation {
+    pub scripts: Vec<Script>,
+    pub witnesses: Vec<Witness>,
+    pub outputs: Vec<Witness>,
+    /// The skip leaves of the queries after the first, which the covenant numbers after the
+    /// steps.
+    pub skips: Vec<PlonkSkipInformation>,
+}
+
+/// The skip leaf of a query, which takes the place of its per-query steps if it has the same
+/// parent as an earlier query (see `per_query_skip`).
+pub struct PlonkSkipInformation {
+    pub query_idx: usize,
+    /// The pc of the first per-query step of the query.
+    pub from_pc: usize,
+    /// The pc of the step after the per-query steps of the query.
+    pub to_pc: usize,
+    pub script: Script,
+    pub witness: Witness,
+    /// The earlier query with the same parent in the proof, if any, in which case the verification
+    /// of the proof takes the skip leaf.
+    pub duplicate_of: Option<usize>,
+}
+
+pub static PLONK_ALL_INFORMATION: OnceLock<PlonkAllInformation> = OnceLock::new();
+
+impl PlonkAllInformation {
+    /// The input of the leaf, which is either a step or a skip leaf.
+    pub fn get_input(&self, idx: usize) -> PlonkVerifierInput {
+        let pc = self.leaf_pc(idx);
+        PlonkVerifierInput {
+            stack: if pc == 0 {
+                vec![]
+            } else {
+                self.outputs[pc - 1].clone()
+            },
+            hints: match self.skip(idx) {
+                Some(skip) => skip.witness.clone(),
+                None => self.witnesses[idx].clone(),
+            },
+        }
+    }
+
+    /// The pc that the leaf runs from.
+    pub fn leaf_pc(&self, idx: usize) -> usize {
+        match self.skip(idx) {
+            Some(skip) => skip.from_pc,
+            None => idx,
+        }
+    }
+
+    /// The skip leaf with the id, if the id is not a step.
+    pub fn skip(&self, idx: usize) -> Option<&PlonkSkipInformation> {
+        idx.checked_sub(self.scripts.len())
+            .and_then(|skip_idx| self.skips.get(skip_idx))
+    }
+
+    /// The pc after the leaf.
+    ///
+    /// A skip leaf leaves the same stack as the last step it skips, so the state after a leaf is
+    /// the output of the step before the pc either way.
+    pub fn next_pc(&self, idx: usize) -> usize {
+        match self.skip(idx) {
+            Some(skip) => skip.to_pc,
+            None => idx + 1,
+        }
+    }
+
+    /// The leaf that the verification of the proof takes at the pc.
+    pub fn leaf_at(&self, pc: usize) -> usize {
+        self.skips
+            .iter()
+            .position(|skip| skip.from_pc == pc && skip.duplicate_of.is_some())
+            .map(|skip_idx| self.scripts.len() + skip_idx)
+            .unwrap_or(pc)
+    }
+
+    /// The leaves that the verification of the proof takes, in order.
+    pub fn path(&self) -> Vec<usize> {
+        let mut path = vec![];
+        let mut pc = 0;
+        while pc < self.scripts.len() {
+            let idx = self.leaf_at(pc);
+            path.push(idx);
+            pc = self.next_pc(idx);
+        }
+        path
+    }
+}
+
+/// Generate all the steps with the LDM of the DSL, which skips the entries that no step reads.
+pub fn compute_all_information() -> PlonkAllInformation {
+    let hints = Hints::instance();
+    let mut ldm = LiveLdm::plonk(&hints, LDM::new());
+
+    let mut scripts = vec![];
+    let mut witnesses = vec![];
+    let mut outputs = vec![];
+
+    for step in step_sequence(&hints) {
+        let cs = step.generate_cs(&hints, &mut ldm).unwrap();
+        let (script, witness, output) = compile_step(cs, &ldm);
+        scripts.push(script);
+        witnesses.push(witness);
+        outputs.push(output);
+    }
+
+    let skips = compute_skips(&hints, &outputs);
+
+    PlonkAllInformation {
+        scripts,
+        witnesses,
+        outputs,
+        skips,
+    }
+}
+
+/// Generate the skip leaves of the queries after the first.
+///
+/// The leaves are generated along the path that takes every skip leaf, i.e., through the
+/// per-query steps of the first query and then the skip leaves of the others, whether or not the
+/// queries of the proof share a parent. A skip leaf leaves
+/// the LDM in the same state as the steps it skips, which is checked against the outputs of the
+/// steps, so each leaf starts from the same state as it would after the steps.
+fn compute_skips(hints: &Hints, outputs: &[Witness]) -> Vec<PlonkSkipInformation> {
+    let steps = step_sequence(hints);
+    let mut ldm = LiveLdm::plonk(hints, LDM::new());
+
+    let mut skips = vec![];
+    let mut pc = 0;
+    while steps[pc] != StepKind::Cleanup {
+        match steps[pc] {
+            StepKind::Folding { query_idx } if query_idx > 0 => {
+                let to_pc = steps
+                    .iter()
+                    .position(|&step| step == StepKind::LastStep { query_idx })
+                    .unwrap()
+                    + 1;
+
+                let cs = super::per_query_skip::generate_cs(hints, &mut ldm, query_idx).unwrap();
+                let (script, witness, output) = compile_step(cs, &ldm);
+                assert_eq!(
+                    output,
+                    outputs[to_pc - 1],
+                    "the skip leaf of query {} leaves the LDM in another state than the steps it skips",
+                    query_idx
+                );
+
+                skips.push(PlonkSkipInformation {
+                    query_idx,
+                    from_pc: pc,
+                    to_pc,
+                    script,
+                    witness,
+                    duplicate_of: hints.duplicate_of(query_idx),
+                });
+                pc = to_pc;
+            }
+            step => {
+                step.generate_cs(hints, &mut ldm).unwrap();
+                pc += 1;
+            }
+        }
+    }
+
+    skips
+}
+
+/// The information of all the steps, together with what is recorded while generating them for
+/// debugging.
+pub struct PlonkDebugInformation {
+    pub all_information: PlonkAllInformation,
+    /// The regions recorded in each step.
+    pub recorded_regions: Vec<Vec<RecordedRegion>>,
+    /// The LDM reads and writes of all the steps.
+    pub ldm_accesses: Vec<LdmAccess>,
+    /// Where the hints of the LDM reads of each step are in its witness.
+    pub read_hints: Vec<Vec<LdmReadHint>>,
+}
+
+/// The disassembly of every step, with the recorded regions marked.
+pub fn compute_all_disassemblies() -> Vec<String> {
+    let debug_information = compute_debug_information();
+
+    debug_information
+        .all_information
+        .scripts
+        .iter()
+        .zip(debug_information.recorded_regions.iter())
+        .map(|(script, recorded)| disassemble(script, recorded))
+        .collect()
+}
+
+/// Generate all the steps with `TracedLDM`, which produces the same steps as
+/// `compute_all_information`, along with what is recorded for debugging.
+pub fn compute_debug_information() -> PlonkDebugInformation {
+    let mut scripts = vec![];
+    let mut witnesses = vec![];
+    let mut outputs = vec![];
+    let mut recorded_regions = vec![];
+    let mut read_hints = vec![];
+
+    let hints = Hints::instance();
+    let mut ldm = LiveLdm::plonk(&hints, TracedLDM::with_schema(LdmSchema::plonk(&hints)));
+
+    for step in step_sequence(&hints) {
+        // the regions are marked while the step is generated, and the markers are stripped
+        // after it is compiled
+        let cs = with_recording(|| step.generate_cs(&hints, &mut ldm).unwrap());
+        let (script, witness, output) = compile_step(cs, &ldm);
+        let (script, recorded) = strip_region_markers(&script);
+        read_hints.push(locate_read_hints(
+            &ldm.ldm.accesses_of_step(ldm.ldm.current_step()),
+            &witness,
+        ));
+        scripts.push(script);
+        witnesses.push(witness);
+        outputs.push(output);
+        recorded_regions.push(recorded);
+    }
+
+    assert_eq!(scripts.len(), witnesses.len());
+    assert_eq!(scripts.len(), outputs.len());
+
+    let skips = compute_skips(&hints, &outputs);
+
+    PlonkDebugInformation {
+        all_information: PlonkAllInformation {
+            scripts,
+            witnesses,
+            outputs,
+            skips,
+        },
+        recorded_regions,
+        ldm_accesses: ldm.ldm.accesses,
+        read_hints,
+    }
+}
+
+/// A step of the split verifier, with its parameters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum StepKind {
+    FiatShamir1,
+    FiatShamir2AndConstraintNum,
+    ConstraintDenom,
+    PairVanishingAndAlphas,
+    ColumnLineCoeffs { step_idx: usize },
+    Folding { query_idx: usize },
+    QuotientChunk { query_idx: usize, chunk_idx: usize },
+    LastStep { query_idx: usize },
+    Cleanup,
+}
+
+impl StepKind {
+    /// The query of a per-query step.
+    pub fn query_idx(&self) -> Option<usize> {
+        match *self {
+            StepKind::Folding { query_idx }
+            | StepKind::QuotientChunk { query_idx, .. }
+            | StepKind::LastStep { query_idx } => Some(query_idx),
+            _ => None,
+        }
+    }
+
+    pub fn generate_cs<L: StepLdm>(
+        &self,
+        hints: &Hints,
+        ldm: &mut L,
+    ) -> Result<ConstraintSystemRef> {
+        match *self {
+            StepKind::FiatShamir1 => {
+                super::part1_fiat_shamir1::generate_cs::<PlonkChannelVar, _>(hints, ldm)
+            }
+            StepKind::FiatShamir2AndConstraintNum => {
+                super::part2_fiat_shamir2_and_constraint_num::generate_cs::<PlonkChannelVar, _>(
+                    hints, ldm,
+                )
+            }
+            StepKind::ConstraintDenom => super::part3_constraint_denom::generate_cs(hints, ldm),
+            StepKind::PairVanishingAndAlphas => {
+                super::part4_pair_vanishing_and_alphas::generate_cs(hints, ldm)
+            }
+            StepKind::ColumnLineCoeffs { step_idx } => {
+                super::part5_column_line_coeffs::generate_cs(hints, ldm, step_idx)
+            }
+            StepKind::Folding { query_idx } => super::per_query_part1_folding::generate_cs::<
+                PlonkChannelVar,
+                _,
+            >(hints, ldm, query_idx),
+            StepKind::QuotientChunk {
+                query_idx,
+                chunk_idx,
+            } => {
+                super::per_query_part2_quotient_chunk::generate_cs(hints, ldm, query_idx, chunk_idx)
+            }
+            StepKind::LastStep { query_idx } => {
+                super::per_query_part3_last_step::generate_cs(hints, ldm, query_idx)
+            }
+            StepKind::Cleanup => super::part6_cleanup::generate_cs(hints, ldm),
+        }
+    }
+}
+
+impl std::fmt::Display for StepKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StepKind::FiatShamir1 => write!(f, "part1_fiat_shamir1"),
+            StepKind::FiatShamir2AndConstraintNum => {
+                write!(f, "part2_fiat_shamir2_and_constraint_num")
+            }
+            StepKind::ConstraintDenom => write!(f, "part3_constraint_denom"),
+            StepKind::PairVanishingAndAlphas => write!(f, "part4_pair_vanishing_and_alphas"),
+            StepKind::ColumnLineCoeffs { step_idx } => {
+                write!(f, "part5_column_line_coeffs[{}]", step_idx)
+            }
+            StepKind::Folding { query_idx } => write!(f, "per_query_part1_folding[{}]", query_idx),
+            StepKind::QuotientChunk {
+                query_idx,
+                chunk_idx,
+            } => write!(
+                f,
+                "per_query_part2_quotient_chunk[{}][{}]",
+                query_idx, chunk_idx
+            ),
+            StepKind::LastStep { query_idx } => {
+                write!(f, "per_query_part3_last_step[{}]", query_idx)
+            }
+            StepKind::Cleanup => write!(f, "part6_cleanup"),
+        }
+    }
+}
+
+/// All the steps of the split verifier, in the order they are executed.
+///
+/// The steps do not depend on the proof. The per-query steps of a query that has the same parent
+/// as an earlier query can be skipped by its skip leaf (see `compute_skips`).
+pub fn step_sequence(hints: &Hints) -> Vec<StepKind> {
+    let mut steps = vec![
+        StepKind::FiatShamir1,
+        StepKind::FiatShamir2AndConstraintNum,
+        StepKind::ConstraintDenom,
+        StepKind::PairVanishingAndAlphas,
+    ];
+
+    for step_idx in 0..super::part5_column_line_coeffs::num_steps(hints) {
+        steps.push(StepKind::ColumnLineCoeffs { step_idx });
+    }
+
+    for query_idx in 0..N_QUERIES {
+        steps.push(StepKind::Folding { query_idx });
+        for chunk_idx in 0..super::per_query_part2_quotient_chunk::num_steps(hints) {
+            steps.push(StepKind::QuotientChunk {
+                query_idx,
+                chunk_idx,
+            });
+        }
+        steps.push(StepKind::LastStep { query_idx });
+    }
+
+    steps.push(StepKind::Cleanup);
+    steps
+}
+
+/// Compile a step into its script, its witness, and the stack it leaves for the next step.
+fn compile_step<L: StepLdm>(cs: ConstraintSystemRef, ldm: &L) -> (Script, Witness, Witness) {
+    let num_to_str = |v: i32| {
+        let mut out = [0u8; 8];
+        let len = write_scriptint(&mut out, v as i64);
+        out[0..len].to_vec()
+    };
+
+    let program = Compiler::compile(cs).unwrap();
+
+    let mut witness = vec![];
+    for entry in program.hint.iter() {
+        match &entry {
+            Element::Num(v) => {
+                witness.push(num_to_str(*v));
+            }
+            Element::Str(v) => {
+                witness.push(v.clone());
+            }
+        }
+    }
+
+    let [write_hash, read_hash] = ldm.state();
+    let output = convert_to_witness(script! {
+        { write_hash }
+        { read_hash }
+    })
+    .unwrap();
+
+    (program.script, witness, output)
+}
@@ -0,0 +1,188 @@
+use crate::dsl::plonk::covenant::PlonkVerifierProgram;
+use bitcoin::secp256k1::{Secp256k1, XOnlyPublicKey};
+use bitcoin::taproot::{
+    ControlBlock, LeafVersion, TapLeafHash, TapNodeHash, TaprootBuilder, TaprootSpendInfo,
+};
+use bitcoin_circle_stark::treepp::*;
+use covenants_gadgets::CovenantProgram;
+use std::fmt::Write;
+
+/// The x-coordinate of the NUMS point from BIP-341, which has no known discrete logarithm, so the
+/// output can only be spent through the script path.
+pub const NUMS_INTERNAL_KEY: [u8; 32] = [
+    0x50, 0x92, 0x9b, 0x74, 0xc1, 0xa0, 0x49, 0x54, 0xb7, 0x8b, 0x4b, 0x60, 0x35, 0xe9, 0x7a, 0x5e,
+    0x07, 0x8a, 0x5a, 0x0f, 0x28, 0xec, 0x96, 0xd5, 0x47, 0xbf, 0xee, 0x9a, 0xce, 0x80, 0x3a, 0xc0,
+];
+
+/// The NUMS point as an internal key.
+pub fn nums_internal_key() -> XOnlyPublicKey {
+    XOnlyPublicKey::from_slice(&NUMS_INTERNAL_KEY).unwrap()
+}
+
+/// A tapleaf of the covenant, i.e., the common prefix followed by the script of a step, or of a
+/// skip leaf, which the covenant numbers after the steps.
+pub struct TapleafExport {
+    pub step: usize,
+    pub script: Script,
+    pub leaf_version: LeafVersion,
+    pub tapleaf_hash: TapLeafHash,
+    pub control_block: ControlBlock,
+}
+
+/// Everything needed to reproduce the covenant address independently.
+pub struct TaprootExport {
+    pub internal_key: XOnlyPublicKey,
+    pub merkle_root: Option<TapNodeHash>,
+    pub output_key: XOnlyPublicKey,
+    pub script_pub_key: Script,
+    pub common_prefix: Script,
+    pub leaves: Vec<TapleafExport>,
+}
+
+/// Rebuild the taproot tree of the covenant from the common prefix and the scripts of the steps.
+///
+/// The tree is built the same way as `covenants_gadgets::get_script_pub_key`: every leaf has the
+/// same weight, and the internal key is the NUMS point. The test checks that the resulting
+/// scriptPubKey matches, so a change in how the tree is built shows up there.
+pub fn export_taproot_tree() -> TaprootExport {
+    let secp = Secp256k1::verification_only();
+
+    let common_prefix = PlonkVerifierProgram::get_common_prefix();
+    let leaf_scripts = PlonkVerifierProgram::get_all_scripts()
+        .into_iter()
+        .map(|(step, script)| {
+            (
+                step,
+                script! {
+                    { common_prefix.clone() }
+                    { script }
+                },
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let spend_info: TaprootSpendInfo = TaprootBuilder::with_huffman_tree(
+        leaf_scripts.iter().map(|(_, script)| (1, script.clone())),
+    )
+    .unwrap()
+    .finalize(&secp, nums_internal_key())
+    .unwrap();
+
+    let leaves = leaf_scripts
+        .into_iter()
+        .map(|(step, script)| {
+            let leaf_version = LeafVersion::TapScript;
+            let control_block = spend_info
+                .control_block(&(script.clone(), leaf_version))
+                .unwrap();
+            TapleafExport {
+                step,
+                tapleaf_hash: TapLeafHash::from_script(&script, leaf_version),
+                script,
+                leaf_version,
+                control_block,
+            }
+        })
+        .collect();
+
+    TaprootExport {
+        internal_key: spend_info.internal_key(),
+        merkle_root: spend_info.merkle_root(),
+        output_key: spend_info.output_key().to_inner(),
+        script_pub_key: Script::new_p2tr_tweaked(spend_info.output_key()),
+        common_prefix,
+        leaves,
+    }
+}
+
+impl TaprootExport {
+    /// The leaf of the step, or of the skip leaf.
+    pub fn leaf(&self, step: usize) -> Option<&TapleafExport> {
+        self.leaves.iter().find(|leaf| leaf.step == step)
+    }
+
+    /// A summary of the tree, one line per field and one block per leaf.
+    pub fn manifest(&self) -> String {
+        let mut out = String::new();
+
+        writeln!(out, "internal key: {}", self.internal_key).unwrap();
+        writeln!(
+            out,
+            "merkle root: {}",
+            self.merkle_root
+                .map(|root| root.to_string())
+                .unwrap_or_default()
+        )
+        .unwrap();
+        writeln!(out, "output key: {}", self.output_key).unwrap();
+        writeln!(
+            out,
+            "scriptPubKey: {}",
+            hex::encode(self.script_pub_key.as_bytes())
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "common prefix: {} bytes",
+            self.common_prefix.as_bytes().len()
+        )
+        .unwrap();
+
+        for leaf in self.leaves.iter() {
+            writeln!(out).unwrap();
+            writeln!(out, "step {}:", leaf.step).unwrap();
+            writeln!(out, "  script: {} bytes", leaf.script.as_bytes().len()).unwrap();
+            writeln!(
+                out,
+                "  leaf version: {:#04x}",
+                leaf.leaf_version.to_consensus()
+            )
+            .unwrap();
+            writeln!(out, "  tapleaf hash: {}", leaf.tapleaf_hash).unwrap();
+            writeln!(
+                out,
+                "  control block: {}",
+                hex::encode(leaf.control_block.serialize())
+            )
+            .unwrap();
+            for (depth, node) in leaf.control_block.merkle_branch.iter().enumerate() {
+                writeln!(out, "  merkle path {}: {}", depth, node).unwrap();
+            }
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::dsl::plonk::covenant::{
+        compute_all_information, PlonkVerifierProgram, PLONK_ALL_INFORMATION,
+    };
+    use crate::dsl::plonk::taproot_export::export_taproot_tree;
+    use bitcoin::secp256k1::Secp256k1;
+    use covenants_gadgets::get_script_pub_key;
+
+    #[test]
+    fn test_taproot_export() {
+        let export = export_taproot_tree();
+        assert_eq!(
+            export.script_pub_key,
+            get_script_pub_key::<PlonkVerifierProgram>()
+        );
+        let all_information = PLONK_ALL_INFORMATION.get_or_init(compute_all_information);
+        assert_eq!(
+            export.leaves.len(),
+            all_information.scripts.len() + all_information.skips.len()
+        );
+
+        let secp = Secp256k1::verification_only();
+        for leaf in export.leaves.iter() {
+            assert!(leaf.control_block.verify_taproot_commitment(
+                &secp,
+                export.output_key,
+                &leaf.script
+            ));
+        }
+    }
+}
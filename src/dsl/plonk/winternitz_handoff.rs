@@ -0,0 +1,234 @@
+use crate::algorithms::winternitz::{WinternitzPublicKey, WinternitzSecretKey};
+use crate::dsl::plonk::split::{PlonkAllInformation, Witness};
+use bitcoin::hashes::{hash160, Hash};
+use bitcoin_circle_stark::treepp::*;
+use bitcoin_script_dsl::builtins::table::utils::OP_HINT;
+
+/// Length of the digest of the state that is signed.
+pub const STATE_DIGEST_LEN: usize = 20;
+
+/// The maximum number of elements on the stack and the altstack together.
+pub const MAX_STACK_SIZE: usize = 1000;
+
+/// The digest of the state that a step leaves for the next one, i.e., the HASH160 of the LDM write
+/// hash followed by the read hash.
+///
+/// Signing the digest instead of the two hashes takes one signature of 86 elements per state,
+/// where signing the hashes takes two of 134 elements each.
+pub fn state_digest(state: &[Vec<u8>]) -> Vec<u8> {
+    assert_eq!(state.len(), 2);
+    hash160::Hash::hash(&state.concat())
+        .to_byte_array()
+        .to_vec()
+}
+
+/// The script that computes the digest of the state on the top of the stack.
+///
+/// stack:
+/// - write hash
+/// - read hash
+///
+/// output:
+/// - digest
+///
+/// The state is a pair of byte strings, which cannot be split into the signed digits in the
+/// script, so the digest is compared with the message that the signature verification
+/// reassembles, as a whole.
+pub fn state_digest_script() -> Script {
+    script! {
+        OP_CAT OP_HASH160
+    }
+}
+
+/// The one-time key that commits to the state after the step `step_idx`.
+///
+/// This is an alternative to the covenant for a BitVM-style optimistic deployment: the operator
+/// signs the digest of the state after each step, and the locking script of a step checks the
+/// signature on its input state and on its output state, so the steps are chained by the keys
+/// instead of by transaction introspection.
+pub fn state_key(seed: &[u8], step_idx: usize) -> WinternitzSecretKey {
+    WinternitzSecretKey::from_seed(
+        &[seed, b"state", &(step_idx as u32).to_le_bytes()].concat(),
+        STATE_DIGEST_LEN,
+    )
+}
+
+/// Generate the keys for all the handoffs of the split verifier.
+pub fn generate_handoff_keys(seed: &[u8], num_steps: usize) -> Vec<WinternitzSecretKey> {
+    (0..num_steps)
+        .map(|step_idx| state_key(seed, step_idx))
+        .collect()
+}
+
+/// The number of witness elements that the handoff adds to the step `idx`, on top of its hints.
+pub fn handoff_witness_len(public_keys: &[WinternitzPublicKey], idx: usize) -> usize {
+    let input = if idx > 0 {
+        public_keys[idx - 1].signature_len() + 2
+    } else {
+        0
+    };
+    input + public_keys[idx].signature_len()
+}
+
+/// The locking scripts of all the steps, given the public keys of all the handoffs.
+pub fn get_locking_scripts(
+    all_information: &PlonkAllInformation,
+    public_keys: &[WinternitzPublicKey],
+) -> Vec<Script> {
+    assert_eq!(all_information.scripts.len(), public_keys.len());
+
+    (0..all_information.scripts.len())
+        .map(|script_idx| {
+            script! {
+                // hint:
+                // - signature on the digest of the input state (if not the first step)
+                // - input write hash and read hash (if not the first step)
+                // - hints of the step
+                // - signature on the digest of the output state
+
+                if script_idx > 0 {
+                    { pull_input_state(&public_keys[script_idx - 1]) }
+                }
+
+                { all_information.scripts[script_idx].clone() }
+
+                // stack:
+                // - output write hash
+                // - output read hash
+
+                { state_digest_script() }
+                { pull_and_verify(&public_keys[script_idx]) }
+                OP_EQUALVERIFY
+
+                OP_DEPTH
+                0
+                OP_EQUALVERIFY
+                OP_TRUE
+            }
+        })
+        .collect()
+}
+
+/// The witness of the step, which signs the input state (unless it is the first step) and the
+/// output state.
+pub fn get_witness(
+    all_information: &PlonkAllInformation,
+    secret_keys: &[WinternitzSecretKey],
+    idx: usize,
+) -> Witness {
+    let mut witness = vec![];
+
+    if idx > 0 {
+        let input_state = &all_information.outputs[idx - 1];
+        witness.extend(secret_keys[idx - 1].sign(&state_digest(input_state)));
+        witness.extend(input_state.iter().cloned());
+    }
+
+    witness.extend(all_information.witnesses[idx].iter().cloned());
+    witness.extend(secret_keys[idx].sign(&state_digest(&all_information.outputs[idx])));
+
+    witness
+}
+
+/// Pull the signature on the digest of the input state and the input state from the hints, and
+/// check the signature.
+///
+/// output:
+/// - input write hash
+/// - input read hash
+pub(crate) fn pull_input_state(public_key: &WinternitzPublicKey) -> Script {
+    script! {
+        { pull_and_verify(public_key) }
+        OP_TOALTSTACK
+        OP_HINT OP_HINT
+        OP_2DUP { state_digest_script() }
+        OP_FROMALTSTACK OP_EQUALVERIFY
+    }
+}
+
+pub(crate) fn pull_and_verify(public_key: &WinternitzPublicKey) -> Script {
+    script! {
+        for _ in 0..public_key.signature_len() {
+            OP_HINT
+        }
+        { public_key.verify_script() }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::dsl::plonk::split::{compute_all_information, PlonkAllInformation};
+    use crate::dsl::plonk::winternitz_handoff::{
+        generate_handoff_keys, get_locking_scripts, get_witness, handoff_witness_len,
+        MAX_STACK_SIZE, STATE_DIGEST_LEN,
+    };
+    use bitcoin_circle_stark::treepp::*;
+
+    #[test]
+    fn test_winternitz_handoff() {
+        let all_information = compute_all_information();
+        let num_steps = all_information.scripts.len();
+
+        let secret_keys = generate_handoff_keys(b"handoff", num_steps);
+        let public_keys = secret_keys
+            .iter()
+            .map(|key| key.public_key())
+            .collect::<Vec<_>>();
+
+        let locking_scripts = get_locking_scripts(&all_information, &public_keys);
+
+        let run = |all_information: &PlonkAllInformation, idx: usize| {
+            let witness = get_witness(all_information, &secret_keys, idx);
+            assert_eq!(
+                witness.len(),
+                all_information.witnesses[idx].len() + handoff_witness_len(&public_keys, idx)
+            );
+            assert!(witness.len() <= MAX_STACK_SIZE);
+
+            let script = script! {
+                for elem in witness.iter() {
+                    { elem.clone() }
+                }
+                { locking_scripts[idx].clone() }
+            };
+            execute_script(script).success
+        };
+
+        // every step of the verifier fits with the signatures, as the interpreter enforces the
+        // limit of `MAX_STACK_SIZE` elements during the execution
+        for idx in 0..num_steps {
+            assert!(run(&all_information, idx), "step {} fails", idx);
+        }
+
+        // a signature on a different output state fails the verification
+        let mut witness = get_witness(&all_information, &secret_keys, 1);
+        let len = witness.len();
+        witness.truncate(len - public_keys[1].signature_len());
+        witness.extend(secret_keys[1].sign(&[0u8; STATE_DIGEST_LEN]));
+
+        let script = script! {
+            for elem in witness.iter() {
+                { elem.clone() }
+            }
+            { locking_scripts[1].clone() }
+        };
+        assert!(!execute_script(script).success);
+
+        // so does an input state other than the one that is signed
+        let mut tampered = compute_all_information();
+        tampered.outputs[0][1][0] ^= 1;
+        let witness = get_witness(&tampered, &secret_keys, 1);
+        let honest_signature_len = public_keys[0].signature_len();
+        let mut witness_with_honest_signature =
+            get_witness(&all_information, &secret_keys, 1)[0..honest_signature_len].to_vec();
+        witness_with_honest_signature.extend(witness[honest_signature_len..].iter().cloned());
+
+        let script = script! {
+            for elem in witness_with_honest_signature.iter() {
+                { elem.clone() }
+            }
+            { locking_scripts[1].clone() }
+        };
+        assert!(!execute_script(script).success);
+    }
+}
@@ -1,2 +1,5 @@
 pub mod algorithms;
 pub mod dsl;
+
+/// Submitting the transactions of the covenant to a node and tracking their confirmation.
+pub mod broadcast;